@@ -4,8 +4,10 @@
 
 ## Programmers: EAlmloff, Suhaan
 ## Date created: 2024-10-14
-## Last modified: 2024-12-08
+## Last modified: 2026-10-17
 ## Revision: -> Trained model with new notes and tags
+## Revision: -> Read embeddings from the on-disk embedding cache and classified with the same sentences used for training
+## Revision: -> Embedded with the embedder of the workspace and kept the default documents for each embedding model
 
 ## Key Features
 
@@ -53,12 +55,10 @@ This code is designed for natural language processing applications such as:
 
 // importing modules from the `kalosm` crate related to natural language processing tasks.
 // It includes imports for features such as accelerated
-// device availability, BertSpace, Chunker, Document, EmbedderExt, Embedding, SentenceChunker, and
-// DefaultSentenceChunker. These modules are used for tasks such as text chunking, document
-// processing, and embedding operations while classifying notes
+// device availability, BertSpace, Document, Embedding and SentenceChunker. These modules are used for tasks such as
+// text chunking, document processing, and embedding operations while classifying notes
 use kalosm::language::{
-    accelerated_device_if_available, BertSpace, Chunker, DefaultSentenceChunker, Document,
-    EmbedderExt, Embedding, SentenceChunker,
+    accelerated_device_if_available, BertSpace, Document, Embedding, SentenceChunker,
 };
 // Importing module to build a classification dataset, configure a classifier, and track the progress of the
 // classifier.
@@ -67,13 +67,13 @@ use kalosm_learning::{
 };
 // A range defines a range of values (in this file, bytes).
 // The `OnceLock` struct is a mutithreaded lock that can only be set once. In this file, we use it to lazily
-// create the embeddings for the default documents.
-use std::{ops::Range, sync::OnceLock};
+// create the embeddings for the default documents of each embedding model.
+use std::{collections::HashMap, ops::Range, sync::OnceLock};
 
-// Import `bert`, `note`, and `workspace` modules from the current crate which will be used later in this file.
+// Import the embedding cache, `note`, and `workspace` modules from the current crate which will be used later in this file.
 use crate::{
-    bert,
-    note::{ContextualDocument, Tag},
+    embedding_cache::embed_batch_cached,
+    note::{ContextualDocument, NoteId, Tag},
     workspace::Workspace,
 };
 
@@ -223,34 +223,46 @@ impl TagClassifier {
         documents: &[ContextualDocument],
         progress: impl Fn(ClassifierProgress),
     ) -> anyhow::Result<Self> {
-        static DEFAULT_EMBEDDED_DOCUMENTS: OnceLock<Vec<(String, Embedding<BertSpace>)>> =
-            OnceLock::new();
-        if DEFAULT_EMBEDDED_DOCUMENTS.get().is_none() {
-            let mut cached_doc_tags = Vec::new();
-            let mut cached_doc_text = Vec::new();
-            let default_docs = default_documents();
-            for document in &default_docs {
-                let text = document.document.body();
-                let chunks = chunk_text(text);
-                for chunk in &chunks {
-                    cached_doc_tags.push(document.tags.clone());
-                    cached_doc_text.push(&text[chunk.clone()]);
+        // The default documents embedded by every embedding model that was used, keyed by the id of the model
+        static DEFAULT_EMBEDDED_DOCUMENTS: OnceLock<
+            parking_lot::Mutex<HashMap<String, Vec<(String, Embedding<BertSpace>)>>>,
+        > = OnceLock::new();
+        let default_embedded_documents = DEFAULT_EMBEDDED_DOCUMENTS.get_or_init(Default::default);
+        let embedder = workspace.embedder().await?;
+        let model_id = embedder.model_id();
+        let cached = default_embedded_documents.lock().get(&model_id).cloned();
+        let mut tagged_documents = match cached {
+            Some(tagged_documents) => tagged_documents,
+            None => {
+                let mut cached_doc_tags = Vec::new();
+                let mut cached_doc_text = Vec::new();
+                let default_docs = default_documents();
+                for document in &default_docs {
+                    let text = document.document.body();
+                    let chunks = chunk_text(text);
+                    for chunk in &chunks {
+                        cached_doc_tags.push(document.tags.clone());
+                        cached_doc_text.push(&text[chunk.clone()]);
+                    }
                 }
-            }
 
-            let bert = bert().await.unwrap();
-            let embeddings = bert.embed_batch(&cached_doc_text).await.unwrap();
+                // The default documents never change, so after the first run they are read from the embedding cache
+                let embeddings = embed_batch_cached(&embedder, &cached_doc_text).await?;
 
-            let mut tag_embeddings = Vec::new();
-            for (tags, embedding) in cached_doc_tags.into_iter().zip(embeddings.into_iter()) {
-                for tag in tags {
-                    tag_embeddings.push((tag.name, embedding.clone()));
+                let mut tag_embeddings = Vec::new();
+                for (tags, embedding) in cached_doc_tags.into_iter().zip(embeddings.into_iter()) {
+                    for tag in tags {
+                        tag_embeddings.push((tag.name, embedding.clone()));
+                    }
                 }
-            }
 
-            DEFAULT_EMBEDDED_DOCUMENTS.set(tag_embeddings).unwrap();
-        }
-        let mut tagged_documents = DEFAULT_EMBEDDED_DOCUMENTS.get().unwrap().clone();
+                // Another workspace may have embedded the default documents while we were waiting for the model
+                default_embedded_documents
+                    .lock()
+                    .insert(model_id, tag_embeddings.clone());
+                tag_embeddings
+            }
+        };
         let mut new_document_tags = Vec::new();
         let mut new_document_text = Vec::new();
         for document in documents {
@@ -262,8 +274,7 @@ impl TagClassifier {
             }
         }
 
-        let bert = bert().await.unwrap();
-        let embeddings = bert.embed_batch(&new_document_text).await.unwrap();
+        let embeddings = embed_batch_cached(&embedder, &new_document_text).await?;
         for (tags, embedding) in new_document_tags.into_iter().zip(embeddings.into_iter()) {
             for tag in tags {
                 tagged_documents.push((tag.name, embedding.clone()));
@@ -283,11 +294,11 @@ impl TagClassifier {
         // After we have added all of the tags to the dataset, get the class count and train the model
         let class_count = workspace.tag_count() as u32;
         let config = ClassifierConfig::new().classes(class_count);
-        let device = accelerated_device_if_available().unwrap();
-        let classifier = Classifier::new(&device, config).unwrap();
+        let device = accelerated_device_if_available()?;
+        let classifier = Classifier::new(&device, config)?;
 
-        let device = accelerated_device_if_available().unwrap();
-        let dataset = dataset.build(&device).unwrap();
+        let device = accelerated_device_if_available()?;
+        let dataset = dataset.build(&device)?;
         classifier.train(&dataset, epochs, learning_rate, batch_size, progress)?;
 
         Ok(Self { classifier })
@@ -298,13 +309,15 @@ impl TagClassifier {
         workspace: &Workspace,
         document: &ContextualDocument,
     ) -> anyhow::Result<Tag> {
-        let bert = bert().await?;
-        let chunks = DefaultSentenceChunker
-            .chunk(&document.document, bert)
-            .await?;
-        let classes = chunks
+        // Split the document the same way as the training data so known sentences are read from the embedding cache
+        let text = document.document.body();
+        let sentences: Vec<&str> = chunk_text(text)
+            .into_iter()
+            .map(|sentence| &text[sentence])
+            .collect();
+        let embeddings = embed_batch_cached(&workspace.embedder().await?, &sentences).await?;
+        let classes = embeddings
             .into_iter()
-            .flat_map(|chunk| chunk.embeddings)
             .map(|embedding| self.classifier.run(&embedding.to_vec()))
             .fold(
                 Ok(None),
@@ -327,8 +340,8 @@ impl TagClassifier {
         let most_likely_tag = classes
             .iter()
             .enumerate()
-            .max_by(|(_, a), (_, b)| a.partial_cmp(b).unwrap())
-            .unwrap()
+            .max_by(|(_, a), (_, b)| a.total_cmp(b))
+            .ok_or_else(|| anyhow::anyhow!("The classifier has no tags"))?
             .0 as u32;
         let tag_id = workspace.get_tag_name(most_likely_tag);
         Ok(Tag {
@@ -367,82 +380,103 @@ const PHILOSOPHY_NOTE: &str = include_str!("./classifier-notes/philosophy.note")
 fn default_documents() -> Vec<ContextualDocument> {
     vec![
         ContextualDocument {
+            note_id: NoteId::new(),
             document: Document::from_parts("Intro to Integrals", INTEGRALS_NOTE),
             tags: vec![Tag {
                 name: "Math".to_string(),
                 manual: true,
             }],
+            created_at: 0,
         },
         ContextualDocument {
+            note_id: NoteId::new(),
             document: Document::from_parts("SIMD Intro", SIMD_NOTE),
             tags: vec![Tag {
                 name: "Computer Science".to_string(),
                 manual: true,
             }],
+            created_at: 0,
         },
         ContextualDocument {
+            note_id: NoteId::new(),
             document: Document::from_parts("Discrete Math", DISCRETE_MATH_NOTE),
             tags: vec![Tag {
                 name: "Math".to_string(),
                 manual: true,
             }],
+            created_at: 0,
         },
         ContextualDocument {
+            note_id: NoteId::new(),
             document: Document::from_parts("Statistics", STATS_NOTE),
             tags: vec![Tag {
                 name: "Math".to_string(),
                 manual: true,
             }],
+            created_at: 0,
         },
         ContextualDocument {
+            note_id: NoteId::new(),
             document: Document::from_parts("Reactivity systems", REACTIVITY_NOTE),
             tags: vec![Tag {
                 name: "Computer Science".to_string(),
                 manual: true,
             }],
+            created_at: 0,
         },
         ContextualDocument {
+            note_id: NoteId::new(),
             document: Document::from_parts("Operating Systems", OS_NOTE),
             tags: vec![Tag {
                 name: "Computer Science".to_string(),
                 manual: true,
             }],
+            created_at: 0,
         },
         ContextualDocument {
+            note_id: NoteId::new(),
             document: Document::from_parts("History", HISTORY_NOTE),
             tags: vec![Tag {
                 name: "History".to_string(),
                 manual: true,
             }],
+            created_at: 0,
         },
         ContextualDocument {
+            note_id: NoteId::new(),
             document: Document::from_parts("Philosophy", PHILOSOPHY_NOTE),
             tags: vec![Tag {
                 name: "Philosophy".to_string(),
                 manual: true,
             }],
+            created_at: 0,
         },
         ContextualDocument {
+            note_id: NoteId::new(),
             document: Document::from_parts("Science", SCIENCE_NOTE),
             tags: vec![Tag {
                 name: "Science".to_string(),
                 manual: true,
             }],
+            created_at: 0,
         },
         ContextualDocument {
+            note_id: NoteId::new(),
             document: Document::from_parts("Physics", PHYSICS_NOTE),
             tags: vec![Tag {
                 name: "Physics".to_string(),
                 manual: true,
             }],
+            created_at: 0,
         },
     ]
 }
 
 #[tokio::test]
 async fn test_tag_classifier() {
+    use crate::embedder::NotesEmbedder;
     use crate::note::{save_note, set_tags, Tag};
-    use crate::workspace::{delete_workspace, load_workspace, unload_workspace};
+    use crate::workspace::{delete_workspace, load_workspace_with, unload_workspace};
     use kalosm::language::Document;
     use std::env::temp_dir;
 
@@ -450,19 +484,17 @@ async fn test_tag_classifier() {
 
     let temp = temp_dir();
     let workspace_path = temp.join("testing-classifier-workspace");
-    let workspace = load_workspace(workspace_path);
+    let workspace = load_workspace_with(workspace_path, Some(NotesEmbedder::hashing())).unwrap();
     let title = "test-note".to_string();
     let text = "Computer science is the study of computation and its applications.".to_string();
     let tags = vec![Tag {
         name: "tag1".to_string(),
         manual: true,
     }];
-    save_note(title.clone(), text.clone(), workspace)
-        .await
-        .unwrap();
-    set_tags(title.clone(), tags.clone(), workspace)
+    let id = save_note(None, title.clone(), text.clone(), workspace)
         .await
         .unwrap();
+    set_tags(id.clone(), tags.clone(), workspace).await.unwrap();
     let title2 = "my-other-test-note".to_string();
     let text2 = "Economics describes a process for distributing limited resources in a way that maximizes overall satisfaction of a group of people.".to_string();
     let tags2 = vec![
@@ -475,26 +507,30 @@ async fn test_tag_classifier() {
             manual: true,
         },
     ];
-    save_note(title2.clone(), text2.clone(), workspace)
+    let id2 = save_note(None, title2.clone(), text2.clone(), workspace)
         .await
         .unwrap();
-    set_tags(title2.clone(), tags2.clone(), workspace)
+    set_tags(id2.clone(), tags2.clone(), workspace)
         .await
         .unwrap();
 
     {
-        let workspace = crate::workspace::get_workspace_ref(workspace);
+        let workspace = crate::workspace::get_workspace_ref(workspace).unwrap();
         let workspace = &*workspace;
         let _ = TagClassifier::new(
             workspace,
             &[
                 ContextualDocument {
+                    note_id: id,
                     document: Document::from_parts(title.clone(), text.clone()),
                     tags: tags.clone(),
+                    created_at: 0,
                 },
                 ContextualDocument {
+                    note_id: id2,
                     document: Document::from_parts(title2.clone(), text2.clone()),
                     tags: tags2.clone(),
+                    created_at: 0,
                 },
             ],
             |_| {},
@@ -502,6 +538,6 @@ async fn test_tag_classifier() {
         .await
         .unwrap();
     }
-    delete_workspace(workspace);
-    unload_workspace(workspace);
+    delete_workspace(workspace).unwrap();
+    unload_workspace(workspace).unwrap();
 }
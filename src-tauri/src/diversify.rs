@@ -0,0 +1,274 @@
+/*!
+# Prologue Comments
+## Name of Code Artifact: Search Result Diversification
+
+## Brief Description: This code re-ranks search results with maximal marginal relevance (MMR). Near-duplicate sentences like a repeated "The math is mathing QED" would otherwise crowd out distinct results. Each next result is the candidate with the best mix of relevance to the query and distance from the results that were already picked. The embeddings of the sentences are the ones the document table stored when the notes were indexed, so the model is only loaded for sentences without a stored embedding.
+## Programmer’s Name: agent
+
+## Date Created: 2026-10-17
+## Dates Revised and Description of Revisions:
+## -> 2026-10-17: Added MMR diversification for search and context_search.
+## -> 2026-10-17: Read the sentence embeddings from the embedding cache now that location records don't store them.
+## -> 2026-10-17: Used the sentence embeddings the document table stores and only embedded sentences without one.
+
+## Preconditions:
+- The candidates must be ordered from the most to the least relevant.
+
+## Acceptable and Unacceptable Input Values/Types:
+- The lambda must be between 0 and 1. 1 only ranks by relevance and 0 only by how different the results are from each other.
+
+## Postconditions:
+
+- The first result is always the most relevant candidate.
+
+## Return Values/Types:
+
+- `diversify_results` returns the re-ranked results, or a `LinknotesError` if the embeddings of the sentences can't be loaded.
+- `sentence_embeddings` returns the embedding of every sentence in the order the sentences were passed.
+
+## Side Effects:
+-  None
+
+## Invariants:
+
+- Results are only reordered and truncated. Their scores and ranges never change.
+
+## Known Faults:
+
+- Relevance is normalized to the range of the candidate scores, so how much the lambda diversifies depends on how close the candidates are to each other.
+
+*/
+
+use kalosm::language::*;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::ops::Range;
+use surrealdb::sql::Id;
+
+#[cfg(test)]
+use pretty_assertions::assert_eq;
+
+use crate::embedding_cache::embed_batch_cached;
+use crate::error::LinknotesError;
+use crate::note::{stored_chunk_embeddings, NoteId};
+use crate::search::{byte_range, note_bodies, SearchResult};
+use crate::workspace::Workspace;
+use crate::ContextualDocumentTable;
+
+/// The number of candidates retrieved for every result that is returned when results are diversified
+pub(crate) const MMR_CANDIDATES_PER_RESULT: usize = 4;
+
+/// Check that a lambda is between 0 and 1.
+///
+/// # Errors
+///
+/// Returns `LinknotesError::InvalidInput` if the lambda is outside of that range.
+pub(crate) fn check_lambda(lambda: Option<f32>) -> Result<Option<f32>, LinknotesError> {
+    match lambda {
+        Some(lambda) if !(0.0..=1.0).contains(&lambda) => Err(LinknotesError::InvalidInput(
+            format!("the MMR lambda must be between 0 and 1, but it is {lambda}"),
+        )),
+        lambda => Ok(lambda),
+    }
+}
+
+/// The number of candidates to retrieve for `results` results
+pub(crate) fn candidates(results: usize, lambda: Option<f32>) -> usize {
+    match lambda {
+        Some(_) => results.saturating_mul(MMR_CANDIDATES_PER_RESULT),
+        None => results,
+    }
+}
+
+/// Pick `results` candidates with maximal marginal relevance. Each next candidate maximizes
+/// `lambda * relevance - (1 - lambda) * similarity to the most similar picked candidate`. Relevance is normalized to the
+/// range 0 to 1 first, so it is comparable with the cosine similarity. Ties go to the earlier candidate.
+///
+/// Returns the indexes of the picked candidates in the order they were picked.
+pub(crate) fn mmr(
+    relevance: &[f32],
+    embeddings: &[Embedding<BertSpace>],
+    lambda: f32,
+    results: usize,
+) -> Vec<usize> {
+    let min = relevance.iter().copied().fold(f32::INFINITY, f32::min);
+    let max = relevance.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+    let normalized = |index: usize| {
+        if max > min {
+            (relevance[index] - min) / (max - min)
+        } else {
+            1.0
+        }
+    };
+
+    let mut picked: Vec<usize> = Vec::new();
+    let mut remaining: Vec<usize> = (0..relevance.len()).collect();
+    while picked.len() < results && !remaining.is_empty() {
+        let marginal_relevance = |candidate: usize| {
+            let redundancy = picked
+                .iter()
+                .map(|&other| embeddings[candidate].cosine_similarity(&embeddings[other]))
+                .reduce(f32::max)
+                .unwrap_or(0.0);
+            lambda * normalized(candidate) - (1.0 - lambda) * redundancy
+        };
+        let mut best = 0;
+        let mut best_score = f32::NEG_INFINITY;
+        for (position, &candidate) in remaining.iter().enumerate() {
+            let score = marginal_relevance(candidate);
+            if score > best_score {
+                best = position;
+                best_score = score;
+            }
+        }
+        picked.push(remaining.remove(best));
+    }
+    picked
+}
+
+/// A sentence of a note that needs an embedding.
+///
+/// # Fields
+///
+/// * `note_id` - The note the sentence is in.
+/// * `byte_range` - The byte range of the sentence in the body of the note.
+/// * `text` - The text of the sentence.
+pub(crate) struct NoteSentence<'a> {
+    pub(crate) note_id: &'a NoteId,
+    pub(crate) byte_range: Range<usize>,
+    pub(crate) text: &'a str,
+}
+
+/// Get the embeddings of sentences in notes. The embeddings the document table stored when the notes were indexed are
+/// used, and the embedder of the workspace is only loaded for sentences without a stored embedding.
+///
+/// # Errors
+///
+/// This function will return an error if the stored embeddings can't be read or the model fails to embed a sentence.
+pub(crate) async fn sentence_embeddings(
+    document_table: &ContextualDocumentTable,
+    workspace: &Workspace,
+    sentences: &[NoteSentence<'_>],
+) -> Result<Vec<Embedding<BertSpace>>, LinknotesError> {
+    /// The record of the document of a note. This type is only used for deserialization of a database query
+    #[derive(Deserialize)]
+    struct NoteDocument {
+        note_id: NoteId,
+        document_id: String,
+    }
+
+    let mut ids: Vec<&NoteId> = sentences.iter().map(|sentence| sentence.note_id).collect();
+    ids.sort();
+    ids.dedup();
+    let mut documents = document_table
+        .table()
+        .db()
+        .query("SELECT note_id, meta::id(id) AS document_id FROM type::table($table) WHERE note_id INSIDE $ids")
+        .bind(("table", document_table.table().table().to_string()))
+        .bind(("ids", ids.into_iter().cloned().collect::<Vec<_>>()))
+        .await?;
+    let documents: Vec<NoteDocument> = documents.take(0)?;
+    let mut stored = HashMap::new();
+    for document in documents {
+        let embeddings =
+            stored_chunk_embeddings(document_table, &Id::String(document.document_id)).await?;
+        stored.insert(document.note_id, embeddings);
+    }
+
+    let mut embeddings: Vec<Option<Embedding<BertSpace>>> = sentences
+        .iter()
+        .map(|sentence| {
+            stored
+                .get(sentence.note_id)
+                .and_then(|embeddings| embeddings.get(&sentence.byte_range))
+                .cloned()
+        })
+        .collect();
+    let missing: Vec<usize> = (0..sentences.len())
+        .filter(|index| embeddings[*index].is_none())
+        .collect();
+    if !missing.is_empty() {
+        // Only sentences that were never indexed, like the ones of a note that changed since the search, need the model
+        let texts: Vec<&str> = missing.iter().map(|index| sentences[*index].text).collect();
+        let embedded = embed_batch_cached(&workspace.embedder().await?, &texts)
+            .await
+            .map_err(LinknotesError::embedding_model)?;
+        for (index, embedding) in missing.into_iter().zip(embedded) {
+            embeddings[index] = Some(embedding);
+        }
+    }
+    embeddings
+        .into_iter()
+        .map(|embedding| {
+            embedding.ok_or_else(|| {
+                LinknotesError::embedding_model("the model didn't embed every sentence")
+            })
+        })
+        .collect()
+}
+
+/// Re-rank search results with maximal marginal relevance and keep `results` of them.
+///
+/// # Errors
+///
+/// This function will return an error if the bodies of the notes or the embeddings of the sentences can't be loaded.
+pub(crate) async fn diversify_results(
+    document_table: &ContextualDocumentTable,
+    workspace: &Workspace,
+    candidates: Vec<SearchResult>,
+    lambda: f32,
+    results: usize,
+) -> Result<Vec<SearchResult>, LinknotesError> {
+    let bodies = note_bodies(document_table, &candidates).await?;
+    // Results in notes that were removed after the search can't be compared
+    let candidates: Vec<SearchResult> = candidates
+        .into_iter()
+        .filter(|result| bodies.contains_key(&result.id))
+        .collect();
+    let sentences: Vec<NoteSentence> = candidates
+        .iter()
+        .map(|result| {
+            let body = &bodies[&result.id];
+            let byte_range = byte_range(body, result.character_range.clone());
+            NoteSentence {
+                note_id: &result.id,
+                text: &body[byte_range.clone()],
+                byte_range,
+            }
+        })
+        .collect();
+    let embeddings = sentence_embeddings(document_table, workspace, &sentences).await?;
+    let relevance: Vec<f32> = candidates.iter().map(|result| result.score).collect();
+
+    let mut candidates: Vec<Option<SearchResult>> = candidates.into_iter().map(Some).collect();
+    Ok(mmr(&relevance, &embeddings, lambda, results)
+        .into_iter()
+        .filter_map(|index| candidates[index].take())
+        .collect())
+}
+
+#[test]
+fn test_mmr() {
+    let embedding = |vector: [f32; 3]| Embedding::<BertSpace>::from(vector.to_vec());
+    // Two near-duplicates and a distinct candidate that is a little less relevant
+    let embeddings = [
+        embedding([1.0, 0.0, 0.0]),
+        embedding([1.0, 0.01, 0.0]),
+        embedding([0.0, 1.0, 0.0]),
+    ];
+    let relevance = [0.9, 0.89, 0.8];
+
+    // A lambda of 1 keeps the order of relevance
+    assert_eq!(mmr(&relevance, &embeddings, 1.0, 3), vec![0, 1, 2]);
+    // A lower lambda skips the duplicate
+    assert_eq!(mmr(&relevance, &embeddings, 0.5, 2), vec![0, 2]);
+    assert_eq!(mmr(&relevance, &embeddings, 0.5, 10).len(), 3);
+    assert_eq!(mmr(&[], &[], 0.5, 10), Vec::<usize>::new());
+
+    assert!(check_lambda(Some(0.5)).is_ok());
+    assert!(check_lambda(None).is_ok());
+    assert!(matches!(
+        check_lambda(Some(1.5)),
+        Err(LinknotesError::InvalidInput(_))
+    ));
+}
@@ -0,0 +1,282 @@
+/*!
+# Prologue Comments
+## Name of Code Artifact: Note Embedder
+
+## Brief Description: This code defines the embedder every workspace, search and the classifier use. It is either the BERT model or a hashing embedder that maps each word to a fixed dimension of the vector. The hashing embedder is deterministic and doesn't download anything, so tests pass it to `load_workspace_with` and can run offline in CI.
+## Programmer’s Name: agent
+
+## Date Created: 2026-10-17
+## Dates Revised and Description of Revisions:
+## -> 2026-10-17: Added the embedder abstraction and the hashing embedder.
+## -> 2026-10-17: Moved choosing the embedder of tests to the workspace instead of a process-wide switch.
+## -> 2026-10-17: Added an embedder that counts the texts it embeds so tests can check which sentences run the model.
+## -> 2026-10-17: Loaded the embedder of the model source a workspace records. The BERT embedder keeps the id of its model.
+## -> 2026-10-17: Added an embedder that only loads BERT when it first embeds text, so opening a workspace doesn't load the model.
+
+## Preconditions:
+- None
+
+## Acceptable and Unacceptable Input Values/Types:
+- Any `ModelSource`. `LINKNOTES_EMBEDDER` can be `bert` or `hashing` and picks the source of workspaces that don't record one. The app uses `bert` if it isn't set. Workspaces loaded with `load_workspace_with` ignore it.
+
+## Postconditions:
+
+- Both embedders return embeddings of the same vector space, so the rest of the code doesn't know which one is used.
+
+## Return Values/Types:
+
+- `embedder` returns the embedder of a model source, or an error if the BERT model can't be loaded.
+
+## Side Effects:
+-  Getting the embedder loads the BERT model the first time if BERT is configured. The lazy embedder loads it the first time it embeds text.
+
+## Invariants:
+
+- The hashing embedder always returns the same embedding for the same text.
+
+## Known Faults:
+
+- The hashing embedder only matches notes that share words. It is meant for tests, not for finding notes by meaning.
+
+*/
+
+use kalosm::language::*;
+use std::future::Future;
+use std::sync::Arc;
+
+#[cfg(test)]
+use pretty_assertions::assert_eq;
+
+use crate::bert;
+use crate::model::{set_model_status, ModelSource, ModelStatus};
+
+/// The dimension of the hashing embedder. This matches snowflake-arctic-embed-small
+pub(crate) const HASHING_DIMENSION: usize = 384;
+
+/// An embedder that hashes every word of the text into one dimension of the vector. Texts that share words are close
+/// to each other.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct HashingEmbedder {
+    dimension: usize,
+}
+
+impl HashingEmbedder {
+    pub(crate) fn new(dimension: usize) -> Self {
+        Self { dimension }
+    }
+
+    /// Embed a single text. Words are compared without case and punctuation
+    fn embed_text(&self, text: &str) -> Embedding<BertSpace> {
+        let mut vector = vec![0.0f32; self.dimension];
+        let words = text
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|word| !word.is_empty());
+        for word in words {
+            let hash = fnv1a(word.to_lowercase().as_bytes());
+            let index = (hash % self.dimension as u64) as usize;
+            // Use the top bit as the sign so collisions cancel out instead of always adding up
+            let sign = if hash >> 63 == 0 { 1.0 } else { -1.0 };
+            vector[index] += sign;
+        }
+        let norm = vector.iter().map(|value| value * value).sum::<f32>().sqrt();
+        if norm == 0.0 {
+            // Text without words still needs a vector with a direction for cosine distance
+            vector[0] = 1.0;
+        } else {
+            vector.iter_mut().for_each(|value| *value /= norm);
+        }
+        Embedding::from(vector)
+    }
+}
+
+/// The 64 bit FNV-1a hash. It is used instead of the standard library hasher because it never changes between
+/// versions of rust
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+/// A hashing embedder that counts the texts it embeds. Tests use it to check which texts run the model
+#[cfg(test)]
+#[derive(Debug, Clone)]
+pub(crate) struct CountingEmbedder {
+    hashing: HashingEmbedder,
+    embedded: Arc<std::sync::atomic::AtomicUsize>,
+    /// The directory embeddings are cached in, or `None` if they aren't cached
+    pub(crate) cache_dir: Option<std::path::PathBuf>,
+}
+
+#[cfg(test)]
+impl CountingEmbedder {
+    /// The number of texts embedded so far
+    pub(crate) fn embedded(&self) -> usize {
+        self.embedded.load(std::sync::atomic::Ordering::SeqCst)
+    }
+
+    fn embed_texts<'a>(&self, texts: impl Iterator<Item = &'a str>) -> Vec<Embedding<BertSpace>> {
+        let embeddings: Vec<_> = texts.map(|text| self.hashing.embed_text(text)).collect();
+        self.embedded
+            .fetch_add(embeddings.len(), std::sync::atomic::Ordering::SeqCst);
+        embeddings
+    }
+}
+
+/// The embedder notes are embedded with.
+#[derive(Clone)]
+pub(crate) enum NotesEmbedder {
+    /// A BERT model shared by every workspace with the same model source, and the id of the source
+    Bert {
+        model: Arc<CachedEmbeddingModel<Bert>>,
+        id: String,
+    },
+    /// A BERT model that is loaded the first time text is embedded. The document table of a workspace uses it so
+    /// opening a workspace for lexical search never loads the model
+    Lazy(ModelSource),
+    /// The deterministic embedder tests use
+    Hashing(HashingEmbedder),
+    /// The hashing embedder with a count of the texts it embedded
+    #[cfg(test)]
+    Counting(CountingEmbedder),
+}
+
+impl NotesEmbedder {
+    /// The hashing embedder with the dimension of the default model
+    pub(crate) fn hashing() -> Self {
+        Self::Hashing(HashingEmbedder::new(HASHING_DIMENSION))
+    }
+
+    /// The hashing embedder along with a counter of the texts it embeds
+    #[cfg(test)]
+    pub(crate) fn counting() -> (Self, CountingEmbedder) {
+        Self::counting_with_cache(None)
+    }
+
+    /// The counting embedder with its embeddings cached in `cache_dir` like the BERT model
+    #[cfg(test)]
+    pub(crate) fn counting_with_cache(
+        cache_dir: Option<std::path::PathBuf>,
+    ) -> (Self, CountingEmbedder) {
+        let counting = CountingEmbedder {
+            hashing: HashingEmbedder::new(HASHING_DIMENSION),
+            embedded: Default::default(),
+            cache_dir,
+        };
+        (Self::Counting(counting.clone()), counting)
+    }
+
+    /// The embedder of a source that doesn't load the model until it embeds text. The hashing embedder is returned
+    /// as it is because it has nothing to load
+    pub(crate) fn lazy(source: ModelSource) -> Self {
+        match source {
+            ModelSource::Hashing => Self::hashing(),
+            source => Self::Lazy(source),
+        }
+    }
+
+    /// Returns false if the embedder would have to load the model before it embeds anything
+    pub(crate) fn is_loaded(&self) -> bool {
+        !matches!(self, Self::Lazy(_))
+    }
+
+    /// The id of the model. Embeddings from models with different ids are never mixed
+    pub(crate) fn model_id(&self) -> String {
+        match self {
+            Self::Bert { id, .. } => id.clone(),
+            Self::Lazy(source) => source.id(),
+            Self::Hashing(_) => ModelSource::Hashing.id(),
+            #[cfg(test)]
+            Self::Counting(_) => "counting".to_string(),
+        }
+    }
+}
+
+impl Embedder for NotesEmbedder {
+    type VectorSpace = BertSpace;
+
+    fn embed_for(
+        &self,
+        input: EmbeddingInput,
+    ) -> impl Future<Output = anyhow::Result<Embedding<BertSpace>>> + Send {
+        async move {
+            match self {
+                Self::Bert { model, .. } => model.embed_for(input).await,
+                Self::Lazy(source) => bert_model(source).await?.embed_for(input).await,
+                Self::Hashing(hashing) => Ok(hashing.embed_text(&input.text)),
+                #[cfg(test)]
+                Self::Counting(counting) => Ok(counting
+                    .embed_texts(std::iter::once(input.text.as_str()))
+                    .remove(0)),
+            }
+        }
+    }
+
+    fn embed_vec_for(
+        &self,
+        inputs: Vec<EmbeddingInput>,
+    ) -> impl Future<Output = anyhow::Result<Vec<Embedding<BertSpace>>>> + Send {
+        async move {
+            match self {
+                Self::Bert { model, .. } => model.embed_vec_for(inputs).await,
+                Self::Lazy(source) => bert_model(source).await?.embed_vec_for(inputs).await,
+                Self::Hashing(hashing) => Ok(inputs
+                    .iter()
+                    .map(|input| hashing.embed_text(&input.text))
+                    .collect()),
+                #[cfg(test)]
+                Self::Counting(counting) => {
+                    Ok(counting.embed_texts(inputs.iter().map(|input| input.text.as_str())))
+                }
+            }
+        }
+    }
+}
+
+/// Get the embedder of a model source. Workspaces use the source in their manifest unless another embedder was passed
+/// to `load_workspace_with`. The BERT model of each source is loaded the first time it is used.
+pub(crate) async fn embedder(source: &ModelSource) -> anyhow::Result<NotesEmbedder> {
+    match source {
+        ModelSource::Hashing => {
+            set_model_status(ModelStatus::Ready);
+            Ok(NotesEmbedder::hashing())
+        }
+        ModelSource::Download | ModelSource::Local(_) => Ok(NotesEmbedder::Bert {
+            model: bert_model(source).await?,
+            id: source.id(),
+        }),
+    }
+}
+
+/// Get the BERT model of a source, loading it the first time the source is used
+async fn bert_model(source: &ModelSource) -> anyhow::Result<Arc<CachedEmbeddingModel<Bert>>> {
+    if let ModelSource::Local(dir) = source {
+        // The directory may have changed since the source was recorded, so check its files before loading
+        ModelSource::resolve(Some(dir.clone()), true)?;
+    }
+    bert(source).await
+}
+#[tokio::test]
+async fn test_hashing_embedder() {
+    let embedder = NotesEmbedder::hashing();
+    let integrals = embedder
+        .embed("Integrals are the area under a curve.")
+        .await
+        .unwrap();
+    assert_eq!(integrals.to_vec().len(), HASHING_DIMENSION);
+
+    // The same words always have the same embedding
+    let again = embedder
+        .embed("integrals ARE the area, under a curve")
+        .await
+        .unwrap();
+    assert_eq!(integrals.to_vec(), again.to_vec());
+
+    // Texts that share words are closer than texts that don't
+    let area = embedder.embed("the area under a curve").await.unwrap();
+    let cats = embedder.embed("Cats sleep all day").await.unwrap();
+    assert!(integrals.cosine_similarity(&area) > integrals.cosine_similarity(&cats));
+
+    // Text without words still has a direction
+    let empty = embedder.embed("").await.unwrap();
+    assert!(empty.to_vec().iter().any(|value| *value != 0.0));
+}
@@ -0,0 +1,364 @@
+/*!
+# Prologue Comments
+## Name of Code Artifact: Persistent Embedding Cache
+
+## Brief Description: This code keeps the embeddings BERT computed on disk so they survive restarts. Each embedding is stored in a file named after a hash of the text, in a directory for the embedding model that created it. Saving notes and training the classifier look up the cache before running the model.
+## Programmer’s Name: agent
+
+## Date Created: 2026-10-17
+## Dates Revised and Description of Revisions:
+## -> 2026-10-17: Added the on-disk embedding cache.
+## -> 2026-10-17: Embed with the configured embedder. The hashing embedder is never cached.
+## -> 2026-10-17: Embed with the embedder of the workspace that is passed in.
+## -> 2026-10-17: Keyed the cache by the id of the embedder and removed the least recently used embeddings once the cache of a model grows past a size limit.
+
+## Preconditions:
+- The cache directory must be writable to add embeddings. Lookups work without write access.
+
+## Acceptable and Unacceptable Input Values/Types:
+- Any text can be embedded. Cache files that can't be read or have the wrong size are treated as missing.
+
+## Postconditions:
+
+- After `embed_batch_cached` returns, the embedding of every text is in the cache.
+
+## Return Values/Types:
+
+- `embed_batch_cached` returns one embedding per text, or an error if the model can't be loaded or fails.
+
+## Side Effects:
+-  Files are written to `LINKNOTES_CACHE_DIR` if it is set, or to `linknotes/embeddings` in the user cache directory.
+
+## Invariants:
+
+- Embeddings from different models are never mixed because each model has its own directory.
+- The cache of each model is pruned back under `MAX_CACHE_BYTES` after every `PRUNE_INTERVAL` embeddings are added.
+
+## Known Faults:
+
+- Embeddings are only marked as used once a day, so the least recently used order is approximate. Deleting the cache directory is always safe.
+
+*/
+
+use kalosm::language::{BertSpace, EmbedderExt, Embedding};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::OnceLock;
+use std::time::{Duration, SystemTime};
+
+#[cfg(test)]
+use pretty_assertions::assert_eq;
+
+use crate::embedder::NotesEmbedder;
+
+/// The environment variable that overrides the directory of the embedding cache
+const CACHE_DIR_VAR: &str = "LINKNOTES_CACHE_DIR";
+
+/// The largest size in bytes the cache of one model is allowed to grow to before the least recently used embeddings
+/// are removed
+const MAX_CACHE_BYTES: u64 = 512 * 1024 * 1024;
+
+/// The number of embeddings added to the caches between prunes. Pruning reads every file in the cache, so it doesn't
+/// run after every batch
+const PRUNE_INTERVAL: usize = 4096;
+
+/// How old the last use of a cached embedding must be before a lookup marks it as used again. Marking an embedding
+/// writes to the file system, so it doesn't happen on every lookup
+const TOUCH_INTERVAL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Hash a piece of text. The hash is stable between runs, so it can be stored in the database and used as a file name
+pub(crate) fn text_hash(text: &str) -> String {
+    use sha2::{Digest, Sha256};
+    Sha256::digest(text.as_bytes())
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+/// A directory of embeddings created by one embedding model, keyed by the hash of the text.
+pub(crate) struct EmbeddingCache {
+    root: PathBuf,
+    max_bytes: u64,
+}
+
+impl EmbeddingCache {
+    /// Open the cache for a model in a cache directory. The directory is created when the first embedding is added
+    pub(crate) fn new(cache_dir: &Path, model_id: &str) -> Self {
+        Self {
+            root: cache_dir.join(model_id),
+            max_bytes: MAX_CACHE_BYTES,
+        }
+    }
+
+    /// The file the embedding of a text with the hash is stored in. Files are split into subdirectories by the first
+    /// two characters of the hash so no directory gets too large
+    fn path(&self, hash: &str) -> PathBuf {
+        let shard = hash.get(..2).unwrap_or(hash);
+        self.root.join(shard).join(hash)
+    }
+
+    /// Get the embedding of the text with the hash if it is in the cache
+    pub(crate) fn get(&self, hash: &str) -> Option<Vec<f32>> {
+        let path = self.path(hash);
+        let bytes = std::fs::read(&path).ok()?;
+        if bytes.is_empty() || bytes.len() % 4 != 0 {
+            return None;
+        }
+        // The modification time of a file is the last time it was used, so pruning removes the least recently used
+        // embeddings first
+        if let Err(err) = touch(&path) {
+            tracing::warn!("Failed to mark cached embedding as used: {}", err);
+        }
+        Some(
+            bytes
+                .chunks_exact(4)
+                .map(|bytes| f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+                .collect(),
+        )
+    }
+
+    /// Add the embedding of the text with the hash to the cache
+    pub(crate) fn insert(&self, hash: &str, embedding: &[f32]) -> std::io::Result<()> {
+        let path = self.path(hash);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let bytes: Vec<u8> = embedding
+            .iter()
+            .flat_map(|value| value.to_le_bytes())
+            .collect();
+        // Write to a temporary file first so other processes never read a half written embedding
+        let temp = path.with_extension(format!("tmp{}", std::process::id()));
+        std::fs::write(&temp, bytes)?;
+        std::fs::rename(&temp, &path)
+    }
+
+    /// Remove the least recently used embeddings until the cache is no larger than its size limit
+    pub(crate) fn prune(&self) -> std::io::Result<()> {
+        let mut files = Vec::new();
+        let mut total = 0;
+        let shards = match std::fs::read_dir(&self.root) {
+            Ok(shards) => shards,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+            Err(err) => return Err(err),
+        };
+        for shard in shards {
+            let shard = shard?;
+            if !shard.file_type()?.is_dir() {
+                continue;
+            }
+            for file in std::fs::read_dir(shard.path())? {
+                let file = file?;
+                let metadata = file.metadata()?;
+                if !metadata.is_file() {
+                    continue;
+                }
+                let used = metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+                total += metadata.len();
+                files.push((used, metadata.len(), file.path()));
+            }
+        }
+        if total <= self.max_bytes {
+            return Ok(());
+        }
+
+        files.sort();
+        for (_, size, path) in files {
+            if total <= self.max_bytes {
+                break;
+            }
+            match std::fs::remove_file(&path) {
+                Ok(()) => total -= size,
+                // Another process may have pruned the file already
+                Err(err) if err.kind() == std::io::ErrorKind::NotFound => total -= size,
+                Err(err) => return Err(err),
+            }
+        }
+        tracing::info!("Pruned the embedding cache in {}", self.root.display());
+        Ok(())
+    }
+}
+
+/// Mark a cached file as used now if it wasn't used recently
+fn touch(path: &Path) -> std::io::Result<()> {
+    let modified = std::fs::metadata(path)?.modified()?;
+    let now = SystemTime::now();
+    if now.duration_since(modified).unwrap_or_default() < TOUCH_INTERVAL {
+        return Ok(());
+    }
+    std::fs::File::options()
+        .write(true)
+        .open(path)?
+        .set_modified(now)
+}
+
+/// The directory the embedding caches of every model are stored in, or `None` if there is no cache directory
+fn cache_dir() -> Option<&'static Path> {
+    static CACHE_DIR: OnceLock<Option<PathBuf>> = OnceLock::new();
+    CACHE_DIR
+        .get_or_init(|| {
+            std::env::var_os(CACHE_DIR_VAR)
+                .map(PathBuf::from)
+                .or_else(|| dirs::cache_dir().map(|dir| dir.join("linknotes").join("embeddings")))
+        })
+        .as_deref()
+}
+
+/// The embedding cache of an embedder keyed by its model id, or `None` if its embeddings aren't cached
+fn embedding_cache(embedder: &NotesEmbedder) -> Option<EmbeddingCache> {
+    match embedder {
+        NotesEmbedder::Bert { .. } | NotesEmbedder::Lazy(_) => {
+            Some(EmbeddingCache::new(cache_dir()?, &embedder.model_id()))
+        }
+        // The hashing embedder is faster than reading the cache
+        NotesEmbedder::Hashing(_) => None,
+        #[cfg(test)]
+        NotesEmbedder::Counting(counting) => counting
+            .cache_dir
+            .as_deref()
+            .map(|cache_dir| EmbeddingCache::new(cache_dir, &embedder.model_id())),
+    }
+}
+
+/// Embed a batch of texts with `embedder`. Texts that are already in the embedding cache are read from disk and the
+/// model only runs on the rest.
+pub(crate) async fn embed_batch_cached(
+    embedder: &NotesEmbedder,
+    texts: &[&str],
+) -> anyhow::Result<Vec<Embedding<BertSpace>>> {
+    let cache = embedding_cache(embedder);
+    let hashes: Vec<String> = texts.iter().map(|text| text_hash(text)).collect();
+    let mut embeddings: Vec<Option<Embedding<BertSpace>>> = hashes
+        .iter()
+        .map(|hash| {
+            cache
+                .as_ref()
+                .and_then(|cache| cache.get(hash))
+                .map(Embedding::from)
+        })
+        .collect();
+
+    let missing: Vec<usize> = (0..texts.len())
+        .filter(|&index| embeddings[index].is_none())
+        .collect();
+    if !missing.is_empty() {
+        tracing::info!("Embedding {} texts that are not cached", missing.len());
+        let new_embeddings = embedder
+            .embed_batch(missing.iter().map(|&index| texts[index]))
+            .await?;
+        let added = missing.len();
+        for (index, embedding) in missing.into_iter().zip(new_embeddings) {
+            if let Some(cache) = &cache {
+                if let Err(err) = cache.insert(&hashes[index], &embedding.to_vec()) {
+                    tracing::warn!("Failed to cache embedding: {}", err);
+                }
+            }
+            embeddings[index] = Some(embedding);
+        }
+        if let Some(cache) = &cache {
+            static ADDED_SINCE_PRUNE: AtomicUsize = AtomicUsize::new(0);
+            if ADDED_SINCE_PRUNE.fetch_add(added, Ordering::Relaxed) + added >= PRUNE_INTERVAL {
+                ADDED_SINCE_PRUNE.store(0, Ordering::Relaxed);
+                if let Err(err) = cache.prune() {
+                    tracing::warn!("Failed to prune the embedding cache: {}", err);
+                }
+            }
+        }
+    }
+
+    embeddings
+        .into_iter()
+        .collect::<Option<Vec<_>>>()
+        .ok_or_else(|| anyhow::anyhow!("the model didn't return an embedding for every text"))
+}
+
+#[test]
+fn test_embedding_cache() {
+    let cache_dir = std::env::temp_dir().join("testing-embedding-cache");
+    _ = std::fs::remove_dir_all(&cache_dir);
+
+    let cache = EmbeddingCache::new(&cache_dir, "model-a");
+    let hash = text_hash("Integrals are the area under a curve.");
+    assert_eq!(cache.get(&hash), None);
+    cache.insert(&hash, &[1.0, -2.5, 3.25]).unwrap();
+    assert_eq!(cache.get(&hash), Some(vec![1.0, -2.5, 3.25]));
+
+    // Embeddings from another model are kept separately
+    let other_model = EmbeddingCache::new(&cache_dir, "model-b");
+    assert_eq!(other_model.get(&hash), None);
+
+    // Files with the wrong size are treated as missing
+    std::fs::write(cache.path(&hash), [0, 1, 2]).unwrap();
+    assert_eq!(cache.get(&hash), None);
+
+    _ = std::fs::remove_dir_all(&cache_dir);
+}
+
+#[test]
+fn test_prune_embedding_cache() {
+    let cache_dir = std::env::temp_dir().join("testing-prune-embedding-cache");
+    _ = std::fs::remove_dir_all(&cache_dir);
+
+    // Room for two embeddings of three values
+    let cache = EmbeddingCache {
+        max_bytes: 24,
+        ..EmbeddingCache::new(&cache_dir, "model-a")
+    };
+    let hashes: Vec<String> = ["first", "second", "third"]
+        .iter()
+        .map(|text| text_hash(text))
+        .collect();
+    let now = SystemTime::now();
+    for (age, hash) in [3, 2, 1].into_iter().zip(&hashes) {
+        cache.insert(hash, &[1.0, 2.0, 3.0]).unwrap();
+        std::fs::File::options()
+            .write(true)
+            .open(cache.path(hash))
+            .unwrap()
+            .set_modified(now - TOUCH_INTERVAL * age)
+            .unwrap();
+    }
+
+    // Using the oldest embedding makes the second one the least recently used
+    assert!(cache.get(&hashes[0]).is_some());
+    cache.prune().unwrap();
+    assert!(cache.get(&hashes[0]).is_some());
+    assert_eq!(cache.get(&hashes[1]), None);
+    assert!(cache.get(&hashes[2]).is_some());
+
+    _ = std::fs::remove_dir_all(&cache_dir);
+}
+
+#[tokio::test]
+async fn test_embed_batch_cached() {
+    let cache_dir = std::env::temp_dir().join("testing-embed-batch-cached");
+    _ = std::fs::remove_dir_all(&cache_dir);
+
+    let (embedder, counting) = NotesEmbedder::counting_with_cache(Some(cache_dir.clone()));
+    let texts = [
+        "Integrals are the area under a curve.",
+        "Derivatives are slopes.",
+    ];
+    let first = embed_batch_cached(&embedder, &texts).await.unwrap();
+    assert_eq!(counting.embedded(), 2);
+
+    // The second call reads every embedding from the cache
+    let second = embed_batch_cached(&embedder, &texts).await.unwrap();
+    assert_eq!(counting.embedded(), 2);
+    assert_eq!(
+        first
+            .iter()
+            .map(|embedding| embedding.to_vec())
+            .collect::<Vec<_>>(),
+        second
+            .iter()
+            .map(|embedding| embedding.to_vec())
+            .collect::<Vec<_>>()
+    );
+
+    // The cache is keyed by the id of the embedder
+    let cache = EmbeddingCache::new(&cache_dir, &embedder.model_id());
+    assert!(cache.get(&text_hash(texts[0])).is_some());
+
+    _ = std::fs::remove_dir_all(&cache_dir);
+}
@@ -0,0 +1,447 @@
+/*!
+# Prologue Comments
+## Name of Code Artifact: Embedding Model Migration
+
+## Brief Description: This code records which embedding model every workspace was indexed with in the `workspace.toml` manifest. Embeddings from different models can't be compared, so searching a workspace that was indexed with another model than the one currently loaded returns `LinknotesError::EmbeddingModelMismatch`. The `set_embedding_model` command chooses the model a workspace uses, and the `migrate_embedding_model` command re-embeds every note with the current model and records the new model when it finishes.
+## Programmer’s Name: agent
+
+## Date Created: 2026-10-17
+## Dates Revised and Description of Revisions:
+## -> 2026-10-17: Added the embedding model record and the migrate_embedding_model command.
+## -> 2026-10-17: Moved the embedding model record into the workspace manifest.
+## -> 2026-10-17: Compared the record with the model of the embedder the workspace was loaded with.
+## -> 2026-10-17: Added the set_embedding_model command that chooses the model source of a workspace.
+
+## Preconditions:
+- The workspace must be loaded. Migrating also requires a writable workspace.
+
+## Acceptable and Unacceptable Input Values/Types:
+- Each tauri command takes an object with types defined by the type definition in the code. Arguments are represented as
+fields in that object.
+- A workspace without a recorded model is treated as a workspace indexed with snowflake-arctic-embed-small, the only model older versions used.
+
+## Postconditions:
+
+- After `migrate_embedding_model` returns, every note is embedded with the current model and the workspace can be searched again.
+
+## Return Values/Types:
+
+- `embedding_model_status` and `set_embedding_model` return an `EmbeddingModelStatus`.
+- `migrate_embedding_model` returns the `ReindexReport` of the rebuild, or a `LinknotesError` on failure.
+
+## Side Effects:
+-  The embedding model and the model source are written to `workspace.toml` in the workspace root.
+-  An `embedding-migration-progress` event is emitted after every batch of notes is re-embedded.
+
+## Invariants:
+
+- The record only names the new model after every note was re-embedded. An interrupted migration keeps the workspace blocked until it is run again.
+
+## Known Faults:
+
+- Notes saved while the workspace is waiting to be migrated are embedded with the current model, so they may show up in results from the old model until the migration finishes.
+
+*/
+
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use tauri::{AppHandle, Emitter};
+
+#[cfg(test)]
+use pretty_assertions::assert_eq;
+
+use crate::embedder::NotesEmbedder;
+use crate::embedding_cache::embed_batch_cached;
+use crate::error::LinknotesError;
+use crate::manifest::{read_manifest, update_manifest};
+use crate::model::ModelSource;
+use crate::note::DOCUMENT_NAME_TABLE;
+use crate::reindex::{reindex, ReindexProgress, ReindexReport};
+use crate::search::MetaId;
+use crate::workspace::{get_workspace_ref, WorkspaceId};
+use crate::ContextualDocumentTable;
+
+/// The event emitted after every batch of notes is re-embedded
+pub const EMBEDDING_MIGRATION_PROGRESS_EVENT: &str = "embedding-migration-progress";
+/// The text embedded to measure the dimension of the model. It is cached after the first time
+const DIMENSION_PROBE: &str = "linknotes";
+
+/// The embedding model a workspace was indexed with.
+///
+/// # Fields
+///
+/// * `id` - The id of the model. See [`ModelSource::id`].
+/// * `dimension` - The length of the embeddings the model creates, or `None` if it is not known.
+/// * `migrating_to` - The id of the model the workspace is being migrated to if a migration started but didn't finish.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EmbeddingModelInfo {
+    pub id: String,
+    #[serde(default)]
+    pub dimension: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub migrating_to: Option<String>,
+}
+
+impl EmbeddingModelInfo {
+    /// The model of workspaces created before the model was recorded
+    fn legacy() -> Self {
+        Self {
+            id: ModelSource::Download.id(),
+            dimension: None,
+            migrating_to: None,
+        }
+    }
+}
+
+/// The result of comparing the model a workspace was indexed with and the current model.
+///
+/// # Fields
+///
+/// * `indexed` - The model the workspace was indexed with.
+/// * `current` - The id of the model that is currently configured.
+/// * `migration_required` - True if the workspace can't be searched until it is migrated.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct EmbeddingModelStatus {
+    pub indexed: EmbeddingModelInfo,
+    pub current: String,
+    pub migration_required: bool,
+}
+
+/// Read the embedding model in the manifest of a workspace root. Returns `None` if no model is recorded or the manifest
+/// can't be read
+pub(crate) fn read_embedding_model(root: &Path) -> Option<EmbeddingModelInfo> {
+    read_manifest(root).ok().flatten()?.embedding_model
+}
+
+/// Write the embedding model to the manifest of a workspace root
+pub(crate) fn write_embedding_model(
+    root: &Path,
+    model: &EmbeddingModelInfo,
+) -> Result<(), LinknotesError> {
+    update_manifest(root, |manifest| {
+        manifest.embedding_model = Some(model.clone())
+    })
+}
+
+/// The id and dimension of the model of an embedder
+async fn current_embedding_model(
+    embedder: &NotesEmbedder,
+) -> Result<EmbeddingModelInfo, LinknotesError> {
+    let id = embedder.model_id();
+    let probe = embed_batch_cached(embedder, &[DIMENSION_PROBE])
+        .await
+        .map_err(LinknotesError::embedding_model)?;
+    Ok(EmbeddingModelInfo {
+        id,
+        dimension: probe.first().map(|embedding| embedding.to_vec().len()),
+        migrating_to: None,
+    })
+}
+
+/// Record the embedding model of a workspace that doesn't have a record yet. A workspace without notes is indexed with
+/// the current model. A workspace with notes was indexed before models were recorded, so it gets the legacy model. The
+/// dimension of a model that isn't loaded yet is left unknown so opening a workspace never loads the model.
+pub(crate) async fn record_embedding_model(
+    root: &Path,
+    embedder: &NotesEmbedder,
+    document_table: &ContextualDocumentTable,
+) -> Result<(), LinknotesError> {
+    if read_embedding_model(root).is_some() {
+        return Ok(());
+    }
+    let mut notes = document_table
+        .table()
+        .db()
+        .query("SELECT meta::id(id) as id FROM type::table($paths)")
+        .bind(("paths", DOCUMENT_NAME_TABLE))
+        .await?;
+    let notes: Vec<MetaId> = notes.take(0)?;
+    let model = if !notes.is_empty() {
+        EmbeddingModelInfo::legacy()
+    } else if embedder.is_loaded() {
+        current_embedding_model(embedder).await?
+    } else {
+        EmbeddingModelInfo {
+            id: embedder.model_id(),
+            dimension: None,
+            migrating_to: None,
+        }
+    };
+    write_embedding_model(root, &model)
+}
+
+/// Compare the model a workspace was indexed with to the id of the model it uses now
+fn embedding_model_status_at(root: &Path, current: String) -> EmbeddingModelStatus {
+    let indexed = read_embedding_model(root).unwrap_or_else(EmbeddingModelInfo::legacy);
+    let migration_required = indexed.id != current || indexed.migrating_to.is_some();
+    EmbeddingModelStatus {
+        indexed,
+        current,
+        migration_required,
+    }
+}
+
+/// Returns `LinknotesError::EmbeddingModelMismatch` if the workspace at the root was indexed with another model than the
+/// current model. Searches call this before embedding the query.
+pub(crate) fn check_embedding_model(
+    root: &Path,
+    embedder: &NotesEmbedder,
+) -> Result<(), LinknotesError> {
+    let status = embedding_model_status_at(root, embedder.model_id());
+    if status.migration_required {
+        return Err(LinknotesError::EmbeddingModelMismatch {
+            indexed: status.indexed.id,
+            current: status.current,
+        });
+    }
+    Ok(())
+}
+
+/// Get the model a workspace was indexed with and if it needs to be migrated before it can be searched.
+///
+/// # Errors
+///
+/// This function will return an error if:
+/// - The workspace is not loaded
+/// - The embedding model is not configured correctly
+#[tauri::command]
+pub async fn embedding_model_status(
+    workspace_id: WorkspaceId,
+) -> Result<EmbeddingModelStatus, LinknotesError> {
+    tracing::info!("embedding_model_status called with id: {:?}", workspace_id);
+    let workspace = get_workspace_ref(workspace_id)?;
+    workspace.document_table().await?;
+    Ok(embedding_model_status_at(
+        &workspace.location,
+        workspace.model_id()?,
+    ))
+}
+
+/// Choose the model a workspace embeds notes with. The source is recorded in the manifest of the workspace, so other
+/// workspaces keep their model. If the workspace was indexed with another model, it must be migrated with
+/// `migrate_embedding_model` before it can be searched again. The model is loaded the next time it is used.
+///
+/// # Errors
+///
+/// This function will return an error if:
+/// - The workspace is not loaded or is read-only
+/// - The source is a local directory without every model file
+/// - The manifest can't be written
+#[tauri::command]
+pub fn set_embedding_model(
+    workspace_id: WorkspaceId,
+    source: ModelSource,
+) -> Result<EmbeddingModelStatus, LinknotesError> {
+    tracing::info!(
+        "set_embedding_model called with id: {:?}, source: {:?}",
+        workspace_id,
+        source
+    );
+    let workspace = get_workspace_ref(workspace_id)?;
+    workspace.check_writable()?;
+    if let ModelSource::Local(dir) = &source {
+        ModelSource::resolve(Some(dir.clone()), true)?;
+    }
+    let current = source.id();
+    update_manifest(&workspace.location, |manifest| {
+        manifest.model_source = Some(source)
+    })?;
+    // The classifier was trained on embeddings of the old model
+    workspace.retrain_classifier();
+    Ok(embedding_model_status_at(&workspace.location, current))
+}
+
+/// Re-embed every note in a workspace with the current model. `on_progress` is called after every batch of notes is
+/// re-embedded. Workspaces that are already indexed with the current model are left alone.
+///
+/// # Errors
+///
+/// This function will return an error if:
+/// - The workspace is not loaded or is read-only
+/// - The embedding model can't be loaded
+/// - The workspace can't be rebuilt
+pub(crate) async fn migrate(
+    workspace_id: WorkspaceId,
+    on_progress: impl Fn(ReindexProgress),
+) -> Result<ReindexReport, LinknotesError> {
+    let (root, embedder) = {
+        let workspace = get_workspace_ref(workspace_id)?;
+        workspace.check_writable()?;
+        workspace.document_table().await?;
+        (workspace.location.clone(), workspace.embedder().await?)
+    };
+    let status = embedding_model_status_at(&root, embedder.model_id());
+    if !status.migration_required {
+        return Ok(ReindexReport::default());
+    }
+    let current = current_embedding_model(&embedder).await?;
+    tracing::info!(
+        "Migrating the workspace from {} to {}",
+        status.indexed.id,
+        current.id
+    );
+
+    // Remember the migration started so the workspace stays blocked if it is interrupted
+    write_embedding_model(
+        &root,
+        &EmbeddingModelInfo {
+            migrating_to: Some(current.id.clone()),
+            ..status.indexed
+        },
+    )?;
+    let report = reindex(workspace_id, on_progress).await?;
+    write_embedding_model(&root, &current)?;
+    Ok(report)
+}
+
+/// Re-embed every note in a workspace with the current embedding model. Use this after the embedding model changed.
+/// The workspace emits an `embedding-migration-progress` event with a [`ReindexProgress`] payload after every batch of
+/// notes.
+///
+/// # Errors
+///
+/// This function will return an error if:
+/// - The workspace is not loaded or is read-only
+/// - The embedding model can't be loaded
+/// - The workspace can't be rebuilt
+#[tauri::command]
+pub async fn migrate_embedding_model(
+    app: AppHandle,
+    workspace_id: WorkspaceId,
+) -> Result<ReindexReport, LinknotesError> {
+    tracing::info!("migrate_embedding_model called with id: {:?}", workspace_id);
+    migrate(workspace_id, |progress| {
+        if let Err(err) = app.emit(EMBEDDING_MIGRATION_PROGRESS_EVENT, progress) {
+            tracing::error!(
+                "Failed to emit {}: {}",
+                EMBEDDING_MIGRATION_PROGRESS_EVENT,
+                err
+            );
+        }
+    })
+    .await
+}
+
+#[tokio::test]
+async fn test_embedding_model_migration() {
+    use crate::embedder::NotesEmbedder;
+    use crate::note::save_note;
+    use crate::query::TagFilter;
+    use crate::search::search;
+    use crate::workspace::{delete_workspace, load_workspace_with, unload_workspace};
+
+    let workspace_path = std::env::temp_dir().join("testing-embedding-model-migration");
+    _ = std::fs::remove_dir_all(&workspace_path);
+    let workspace =
+        load_workspace_with(workspace_path.clone(), Some(NotesEmbedder::hashing())).unwrap();
+    save_note(
+        None,
+        "Integrals".to_string(),
+        "Integrals are the area under a curve.".to_string(),
+        workspace,
+    )
+    .await
+    .unwrap();
+
+    // New workspaces are indexed with the current model
+    let status = embedding_model_status(workspace).await.unwrap();
+    assert_eq!(status.indexed.id, status.current);
+    assert!(status.indexed.dimension.is_some());
+    assert!(!status.migration_required);
+    let current = status.indexed;
+
+    // A workspace indexed with another model can't be searched until it is migrated
+    let other_model = EmbeddingModelInfo {
+        id: "another-model".to_string(),
+        dimension: Some(3),
+        migrating_to: None,
+    };
+    write_embedding_model(&workspace_path, &other_model).unwrap();
+    assert!(matches!(
+        search(
+            "area".to_string(),
+            TagFilter::default(),
+            1,
+            None,
+            None,
+            workspace
+        )
+        .await,
+        Err(LinknotesError::EmbeddingModelMismatch { .. })
+    ));
+    assert!(
+        embedding_model_status(workspace)
+            .await
+            .unwrap()
+            .migration_required
+    );
+
+    let report = migrate(workspace, |_| {}).await.unwrap();
+    assert_eq!(report.indexed.len(), 1);
+    assert_eq!(read_embedding_model(&workspace_path), Some(current));
+    let results = search(
+        "area".to_string(),
+        TagFilter::default(),
+        1,
+        None,
+        None,
+        workspace,
+    )
+    .await
+    .unwrap();
+    assert_eq!(results[0].title, "Integrals");
+
+    // Migrating again does nothing
+    let report = migrate(workspace, |_| {}).await.unwrap();
+    assert!(report.indexed.is_empty());
+
+    delete_workspace(workspace).unwrap();
+    unload_workspace(workspace).unwrap();
+}
+
+#[tokio::test]
+async fn test_set_embedding_model() {
+    use crate::manifest::get_workspace_manifest;
+    use crate::workspace::{delete_workspace, load_workspace_with, unload_workspace};
+
+    let workspace_path = std::env::temp_dir().join("testing-set-embedding-model");
+    let model_dir = std::env::temp_dir().join("testing-set-embedding-model-local");
+    _ = std::fs::remove_dir_all(&workspace_path);
+    _ = std::fs::remove_dir_all(&model_dir);
+    // The workspace uses the model in its manifest, so choose the hashing embedder before anything loads BERT
+    let workspace = load_workspace_with(workspace_path.clone(), None).unwrap();
+    set_embedding_model(workspace, ModelSource::Hashing).unwrap();
+    let status = embedding_model_status(workspace).await.unwrap();
+    assert_eq!(status.current, ModelSource::Hashing.id());
+    assert_eq!(status.indexed.id, ModelSource::Hashing.id());
+    assert!(!status.migration_required);
+    assert_eq!(
+        get_workspace_manifest(workspace)
+            .unwrap()
+            .unwrap()
+            .model_source,
+        Some(ModelSource::Hashing)
+    );
+
+    // Local models must have every file
+    std::fs::create_dir_all(&model_dir).unwrap();
+    assert!(matches!(
+        set_embedding_model(workspace, ModelSource::Local(model_dir.clone())),
+        Err(LinknotesError::EmbeddingModelUnavailable(_))
+    ));
+    for file in ["model.safetensors", "tokenizer.json", "config.json"] {
+        std::fs::write(model_dir.join(file), "").unwrap();
+    }
+
+    // Switching to another model requires a migration until the workspace switches back
+    let local = ModelSource::Local(model_dir.clone());
+    let status = set_embedding_model(workspace, local.clone()).unwrap();
+    assert_eq!(status.current, local.id());
+    assert!(status.migration_required);
+    let status = set_embedding_model(workspace, ModelSource::Hashing).unwrap();
+    assert!(!status.migration_required);
+
+    delete_workspace(workspace).unwrap();
+    unload_workspace(workspace).unwrap();
+    _ = std::fs::remove_dir_all(&model_dir);
+}
@@ -0,0 +1,194 @@
+/*!
+# Prologue Comments
+## Name of Code Artifact: Command Errors
+
+## Brief Description: This code defines the error type returned by every tauri command. Each error serializes with a machine readable `kind` and a human readable `message` so the frontend can react to each case differently.
+## Programmer’s Name: agent
+
+## Date Created: 2026-10-17
+## Dates Revised and Description of Revisions:
+## -> 2026-10-17: Replaced the per command error types and string errors with `LinknotesError`.
+## -> 2026-10-17: Added `EmbeddingModelMismatch`.
+## -> 2026-10-17: Added `WorkspaceTooNew`.
+## -> 2026-10-17: Added `InvalidQuery` with the range of the mistake in the query.
+## -> 2026-10-17: Added `SnapshotFailed`.
+## -> 2026-10-17: Made the pid of `WorkspaceLocked` optional for lock files that can't be read.
+## -> 2026-10-17: Removed `TitleAlreadyExists`. Notes can share a title.
+
+## Preconditions:
+- None
+
+## Acceptable and Unacceptable Input Values/Types:
+- Any error from the database, filesystem, embedding model or classifier can be converted into a `LinknotesError`.
+
+## Postconditions:
+
+- Commands return an error instead of panicking when an operation fails.
+
+## Return Values/Types:
+
+- Errors serialize as `{ "kind": "NoteNotFound", "message": "the note does not exist" }`.
+- `InvalidQuery` errors also have the UTF-16 `range` of the mistake in the query.
+
+## Side Effects:
+-  None
+
+## Invariants:
+
+- The `kind` of an error is the name of the variant and never changes for a given failure.
+
+## Known Faults:
+
+- Errors from the database and embedding model are stored as messages, so the original error type is lost.
+
+*/
+
+use serde::ser::SerializeStruct;
+use serde::{Serialize, Serializer};
+use std::fmt::Display;
+
+use crate::query::QueryError;
+use crate::workspace::NoteTitleError;
+
+/// The error returned by every tauri command.
+#[derive(thiserror::Error, Debug, Clone, PartialEq)]
+pub enum LinknotesError {
+    /// The workspace id does not point to a workspace that is currently loaded
+    #[error("the workspace is not loaded")]
+    WorkspaceNotLoaded,
+    /// Another process has the workspace open. The workspace can still be opened in read-only mode. The pid is `None`
+    /// if the lock file can't be read
+    #[error("the workspace is open in another process{}", lock_owner(.pid))]
+    WorkspaceLocked { pid: Option<u32> },
+    /// The workspace was created by a newer version of the app with a schema version this version can't read
+    #[error("the workspace has schema version {version}, but this version of the app only supports up to {supported}")]
+    WorkspaceTooNew { version: u32, supported: u32 },
+    /// The workspace was opened in read-only mode, so it can't be changed
+    #[error("the workspace is read-only")]
+    WorkspaceReadOnly,
+    /// The database of a read-only workspace couldn't be copied because the copy failed or the process that has the
+    /// workspace open kept writing to it. Loading the workspace again may succeed
+    #[error("the workspace couldn't be copied for read-only access: {0}")]
+    SnapshotFailed(String),
+    /// No note in the workspace has the id or title
+    #[error("the note does not exist")]
+    NoteNotFound,
+    /// The title can't be used as the title of a note
+    #[error("invalid title: {0}")]
+    InvalidTitle(#[from] NoteTitleError),
+    /// An argument passed to the command is invalid
+    #[error("invalid input: {0}")]
+    InvalidInput(String),
+    /// The search query can't be parsed
+    #[error("invalid query: {0}")]
+    InvalidQuery(#[from] QueryError),
+    /// The embedding model could not be loaded or failed to embed text
+    #[error("the embedding model is unavailable: {0}")]
+    EmbeddingModelUnavailable(String),
+    /// The workspace was indexed with another embedding model and must be migrated before it can be searched
+    #[error("the workspace was indexed with {indexed:?} but the current model is {current:?}")]
+    EmbeddingModelMismatch { indexed: String, current: String },
+    /// Reading from or writing to the workspace database failed
+    #[error("storage error: {0}")]
+    Storage(String),
+    /// Reading from or writing to the notes on disk failed
+    #[error("io error: {0}")]
+    Io(String),
+    /// Training or running the tag classifier failed
+    #[error("classifier error: {0}")]
+    Classifier(String),
+}
+
+/// Describe the process that holds the lock of a workspace if it is known
+fn lock_owner(pid: &Option<u32>) -> String {
+    pid.map(|pid| format!(" ({pid})")).unwrap_or_default()
+}
+
+impl LinknotesError {
+    /// Create a storage error from any database error
+    pub(crate) fn storage(err: impl Display) -> Self {
+        Self::Storage(err.to_string())
+    }
+
+    /// Create an embedding model error from any error loading or running the model
+    pub(crate) fn embedding_model(err: impl Display) -> Self {
+        Self::EmbeddingModelUnavailable(err.to_string())
+    }
+
+    /// Create a classifier error from any error training or running the classifier
+    pub(crate) fn classifier(err: impl Display) -> Self {
+        Self::Classifier(err.to_string())
+    }
+
+    /// The machine readable name of the error
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::WorkspaceNotLoaded => "WorkspaceNotLoaded",
+            Self::WorkspaceLocked { .. } => "WorkspaceLocked",
+            Self::WorkspaceTooNew { .. } => "WorkspaceTooNew",
+            Self::WorkspaceReadOnly => "WorkspaceReadOnly",
+            Self::SnapshotFailed(_) => "SnapshotFailed",
+            Self::NoteNotFound => "NoteNotFound",
+            Self::InvalidTitle(_) => "InvalidTitle",
+            Self::InvalidInput(_) => "InvalidInput",
+            Self::InvalidQuery(_) => "InvalidQuery",
+            Self::EmbeddingModelUnavailable(_) => "EmbeddingModelUnavailable",
+            Self::EmbeddingModelMismatch { .. } => "EmbeddingModelMismatch",
+            Self::Storage(_) => "Storage",
+            Self::Io(_) => "Io",
+            Self::Classifier(_) => "Classifier",
+        }
+    }
+}
+
+impl From<surrealdb::Error> for LinknotesError {
+    fn from(err: surrealdb::Error) -> Self {
+        Self::storage(err)
+    }
+}
+
+impl From<std::io::Error> for LinknotesError {
+    fn from(err: std::io::Error) -> Self {
+        Self::Io(err.to_string())
+    }
+}
+
+impl Serialize for LinknotesError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut error = serializer.serialize_struct("LinknotesError", 3)?;
+        error.serialize_field("kind", self.kind())?;
+        error.serialize_field("message", &self.to_string())?;
+        match self {
+            Self::InvalidQuery(err) => error.serialize_field("range", &err.range)?,
+            _ => error.skip_field("range")?,
+        }
+        error.end()
+    }
+}
+
+#[test]
+fn test_serialize_errors() {
+    let errors = [
+        (LinknotesError::WorkspaceNotLoaded, "WorkspaceNotLoaded"),
+        (LinknotesError::NoteNotFound, "NoteNotFound"),
+        (NoteTitleError::Empty.into(), "InvalidTitle"),
+        (
+            std::io::Error::from(std::io::ErrorKind::NotFound).into(),
+            "Io",
+        ),
+        (LinknotesError::storage("disk full"), "Storage"),
+    ];
+    for (error, kind) in errors {
+        let json = serde_json::to_value(&error).unwrap();
+        assert_eq!(json["kind"], kind);
+        assert_eq!(json["message"], error.to_string());
+    }
+
+    let error = LinknotesError::from(QueryError {
+        message: "the quote is never closed".to_string(),
+        range: 2..7,
+    });
+    let json = serde_json::to_value(&error).unwrap();
+    assert_eq!(json["kind"], "InvalidQuery");
+    assert_eq!(json["range"], serde_json::json!({ "start": 2, "end": 7 }));
+}
@@ -0,0 +1,822 @@
+/*!
+# Prologue Comments
+## Name of Code Artifact: Background Indexing Queue
+
+## Brief Description: This code embeds, classifies and stores saved notes in the background so saving a note doesn't wait on BERT. Each workspace has a queue of notes to index. Saving a note that is already waiting in the queue replaces the queued text instead of adding another job. Commands that read notes answer from the queued text without waiting. Search waits for the queue to finish first, so it always sees the latest saves.
+## Programmer’s Name: agent
+
+## Date Created: 2026-10-17
+## Dates Revised and Description of Revisions:
+## -> 2026-10-17: Added the background indexing queue.
+## -> 2026-10-17: Saved the notes that are still waiting when a workspace is unloaded and queued them again on the next load.
+## -> 2026-10-17: Answered commands that read notes from the queued text instead of waiting for the queue to finish.
+## -> 2026-10-17: Kept the file name of each queued note, because notes that share a title are stored in different files.
+## -> 2026-10-17: Added pausing the queue so notes saved while the index is rebuilt are indexed after the rebuild.
+
+## Preconditions:
+- The workspace must be loaded and writable to queue notes.
+
+## Acceptable and Unacceptable Input Values/Types:
+- Each tauri command takes an object with types defined by the type definition in the code. Arguments are represented as
+fields in that object.
+
+## Postconditions:
+
+- Every note that was queued is indexed with the text from the last time it was saved, unless the workspace is deleted first. Notes that are waiting when the workspace is unloaded are indexed after it is loaded again.
+
+## Return Values/Types:
+
+- `indexing_status` returns the `IndexingStatus` of the workspace, or `LinknotesError::WorkspaceNotLoaded`.
+
+## Side Effects:
+-  An `indexing-progress` event is emitted after every note is indexed and an `indexing-finished` event is emitted when the queue is empty.
+-  Pending notes are written to `pending-index.json` in the workspace root when the workspace is unloaded, and the file is removed when they are queued again. Pending notes are dropped when the workspace is deleted.
+
+## Invariants:
+
+- Only one note per workspace is indexed at a time, and each note is in the queue at most once.
+
+## Known Faults:
+
+- Notes that are still queued when the app exits are not indexed. Their files are already written, so `repair_workspace` picks them up.
+
+*/
+
+use kalosm::language::Document;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::path::Path;
+use std::sync::Arc;
+use tokio::sync::watch;
+
+#[cfg(test)]
+use pretty_assertions::assert_eq;
+
+use crate::emit;
+use crate::error::LinknotesError;
+use crate::metadata::{file_created_at, read_metadata};
+use crate::note::{index_written_note, ContextualDocument, NoteId};
+use crate::workspace::{get_workspace_ref, title_to_file_name, WorkspaceId};
+
+/// The event emitted after every note the queue indexes
+pub const INDEXING_PROGRESS_EVENT: &str = "indexing-progress";
+/// The event emitted when the queue is empty
+pub const INDEXING_FINISHED_EVENT: &str = "indexing-finished";
+/// The file in the workspace root the pending notes are saved to when the workspace is unloaded
+pub(crate) const PENDING_JOBS_FILE_NAME: &str = "pending-index.json";
+
+/// The state of the indexing queue of a workspace.
+///
+/// # Fields
+///
+/// * `pending` - The number of notes waiting to be indexed.
+/// * `indexing` - The note that is being indexed right now.
+/// * `indexed` - The number of notes indexed since the queue was last empty.
+/// * `failed` - The number of notes that failed to index since the queue was last empty.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct IndexingStatus {
+    pub pending: usize,
+    pub indexing: Option<NoteId>,
+    pub indexed: usize,
+    pub failed: usize,
+}
+
+impl IndexingStatus {
+    /// Check if there is nothing left to index
+    pub fn is_idle(&self) -> bool {
+        self.pending == 0 && self.indexing.is_none()
+    }
+}
+
+/// The payload of the `indexing-progress` event.
+///
+/// # Fields
+///
+/// * `workspace_id` - The workspace the note is in.
+/// * `note_id` - The note that was indexed.
+/// * `error` - The error if the note couldn't be indexed.
+/// * `status` - The state of the queue after the note was indexed.
+#[derive(Debug, Clone, Serialize)]
+pub struct IndexingProgress {
+    pub workspace_id: WorkspaceId,
+    pub note_id: NoteId,
+    pub error: Option<LinknotesError>,
+    pub status: IndexingStatus,
+}
+
+/// The payload of the `indexing-finished` event.
+///
+/// # Fields
+///
+/// * `workspace_id` - The workspace whose queue is empty.
+/// * `indexed` - The number of notes that were indexed.
+/// * `failed` - The number of notes that failed to index.
+#[derive(Debug, Clone, Serialize)]
+pub struct IndexingFinished {
+    pub workspace_id: WorkspaceId,
+    pub indexed: usize,
+    pub failed: usize,
+}
+
+/// The latest title and text of a queued note along with the file in the notes directory the text is written to
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct IndexJob {
+    pub(crate) title: String,
+    pub(crate) file_name: String,
+    pub(crate) text: String,
+}
+
+/// A note that was still waiting to be indexed when its workspace was unloaded. The text is read from the note file
+/// when the note is queued again, so only the id, title and file name are saved.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) struct PendingJob {
+    pub(crate) note_id: NoteId,
+    pub(crate) title: String,
+    /// Jobs saved before notes could share a title don't have a file name. They are stored in the file for the title.
+    #[serde(default)]
+    pub(crate) file_name: Option<String>,
+}
+
+/// The jobs of a queue along with the note that is running
+#[derive(Default)]
+struct QueueState {
+    order: VecDeque<NoteId>,
+    jobs: HashMap<NoteId, IndexJob>,
+    running: Option<(NoteId, IndexJob)>,
+    worker_running: bool,
+    cancelled: bool,
+    paused: usize,
+    indexed: usize,
+    failed: usize,
+}
+
+impl QueueState {
+    fn status(&self) -> IndexingStatus {
+        IndexingStatus {
+            pending: self.order.len(),
+            indexing: self.running.as_ref().map(|(id, _)| id.clone()),
+            indexed: self.indexed,
+            failed: self.failed,
+        }
+    }
+}
+
+/// The queue of notes waiting to be indexed in a workspace.
+pub(crate) struct IndexingQueue {
+    state: parking_lot::Mutex<QueueState>,
+    status: watch::Sender<IndexingStatus>,
+}
+
+impl IndexingQueue {
+    pub(crate) fn new() -> Self {
+        Self {
+            state: parking_lot::Mutex::new(QueueState::default()),
+            status: watch::Sender::new(IndexingStatus::default()),
+        }
+    }
+
+    /// Publish the current status to anything waiting on the queue
+    fn publish(&self, state: &QueueState) {
+        self.status.send_replace(state.status());
+    }
+
+    /// Add a note to the queue. If the note is already waiting, the queued text is replaced instead. Returns true if
+    /// a worker needs to be started for the queue.
+    fn push(&self, note_id: NoteId, job: IndexJob) -> bool {
+        let mut state = self.state.lock();
+        if state.cancelled {
+            return false;
+        }
+        if state.jobs.insert(note_id.clone(), job).is_none() {
+            state.order.push_back(note_id);
+        }
+        let start_worker = !state.worker_running && state.paused == 0;
+        if start_worker {
+            state.worker_running = true;
+            state.indexed = 0;
+            state.failed = 0;
+        }
+        self.publish(&state);
+        start_worker
+    }
+
+    /// Take the next note to index. Returns `None` and stops the worker if the queue is empty or paused
+    fn next_job(&self) -> Option<(NoteId, IndexJob)> {
+        let mut state = self.state.lock();
+        if state.paused > 0 {
+            state.running = None;
+            state.worker_running = false;
+            self.publish(&state);
+            return None;
+        }
+        let next = state.order.pop_front().and_then(|id| {
+            let job = state.jobs.remove(&id)?;
+            Some((id, job))
+        });
+        state.running = next.clone();
+        if next.is_none() {
+            state.worker_running = false;
+        }
+        self.publish(&state);
+        next
+    }
+
+    /// Record the result of the running note
+    fn finish_job(&self, succeeded: bool) -> IndexingStatus {
+        let mut state = self.state.lock();
+        state.running = None;
+        if succeeded {
+            state.indexed += 1;
+        } else {
+            state.failed += 1;
+        }
+        self.publish(&state);
+        state.status()
+    }
+
+    /// Drop every pending note and stop accepting new notes. The note that is running finishes on its own. Returns the
+    /// running and pending notes so they can be saved and queued again later. The running note is included because it
+    /// may fail once the workspace is unloaded.
+    pub(crate) fn cancel(&self) -> Vec<PendingJob> {
+        let mut state = self.state.lock();
+        state.cancelled = true;
+        let mut pending: Vec<PendingJob> = state
+            .running
+            .iter()
+            .map(|(note_id, job)| PendingJob {
+                note_id: note_id.clone(),
+                title: job.title.clone(),
+                file_name: Some(job.file_name.clone()),
+            })
+            .collect();
+        let order = std::mem::take(&mut state.order);
+        let mut jobs = std::mem::take(&mut state.jobs);
+        pending.extend(order.into_iter().filter_map(|note_id| {
+            let job = jobs.remove(&note_id)?;
+            Some(PendingJob {
+                note_id,
+                title: job.title,
+                file_name: Some(job.file_name),
+            })
+        }));
+        if !pending.is_empty() {
+            tracing::info!("Cancelled {} pending indexing jobs", pending.len());
+        }
+        self.publish(&state);
+        pending
+    }
+
+    /// Stop starting new notes until [`IndexingQueue::resume`] is called as many times as this was. Notes can still be
+    /// queued while the queue is paused. The note that is running finishes on its own.
+    fn pause(&self) {
+        self.state.lock().paused += 1;
+    }
+
+    /// Undo one call to [`IndexingQueue::pause`]. Returns true if the queue is no longer paused and a worker needs to
+    /// be started for the notes that were queued while it was paused.
+    fn resume(&self) -> bool {
+        let mut state = self.state.lock();
+        state.paused -= 1;
+        let start_worker = state.paused == 0
+            && !state.worker_running
+            && !state.cancelled
+            && !state.order.is_empty();
+        if start_worker {
+            state.worker_running = true;
+            state.indexed = 0;
+            state.failed = 0;
+        }
+        start_worker
+    }
+
+    /// Find a note that is queued or running with a title
+    pub(crate) fn find_by_title(&self, title: &str) -> Option<NoteId> {
+        self.queued_jobs()
+            .into_iter()
+            .find(|(_, job)| job.title == title)
+            .map(|(id, _)| id)
+    }
+
+    /// Get the title a note was last saved with if it is queued or running
+    pub(crate) fn title_of(&self, note_id: &NoteId) -> Option<String> {
+        self.queued_job(note_id).map(|job| job.title)
+    }
+
+    /// Get the title and text a note was last saved with if it is queued or running
+    pub(crate) fn queued_job(&self, note_id: &NoteId) -> Option<IndexJob> {
+        let state = self.state.lock();
+        state.jobs.get(note_id).cloned().or_else(|| {
+            state
+                .running
+                .as_ref()
+                .filter(|(id, _)| id == note_id)
+                .map(|(_, job)| job.clone())
+        })
+    }
+
+    /// Get every note that is queued or running. A note that is running and queued again is only returned once with
+    /// the queued text, which is the latest.
+    pub(crate) fn queued_jobs(&self) -> Vec<(NoteId, IndexJob)> {
+        let state = self.state.lock();
+        let running = state
+            .running
+            .iter()
+            .filter(|(id, _)| !state.jobs.contains_key(id))
+            .cloned();
+        let pending = state
+            .order
+            .iter()
+            .filter_map(|id| Some((id.clone(), state.jobs.get(id)?.clone())));
+        running.chain(pending).collect()
+    }
+
+    /// Drop a note from the queue if it is waiting. A note that is running is not stopped.
+    pub(crate) fn remove(&self, note_id: &NoteId) {
+        let mut state = self.state.lock();
+        if state.jobs.remove(note_id).is_some() {
+            state.order.retain(|id| id != note_id);
+            self.publish(&state);
+        }
+    }
+
+    /// Wait until a note is neither queued nor running. Other notes in the queue are not waited for.
+    pub(crate) async fn wait_for_note(&self, note_id: &NoteId) {
+        let mut status = self.status.subscribe();
+        // The status is published after every change to the queue, so check again every time it changes
+        while self.queued_job(note_id).is_some() {
+            if status.changed().await.is_err() {
+                return;
+            }
+        }
+    }
+
+    pub(crate) fn status(&self) -> IndexingStatus {
+        self.state.lock().status()
+    }
+
+    /// Wait until there is nothing left to index
+    pub(crate) async fn wait_idle(&self) {
+        // Cancelling the queue clears the pending notes, so a cancelled queue is idle once the running note finishes
+        let mut status = self.status.subscribe();
+        // The sender lives as long as the queue, so this only fails if the queue is dropped
+        _ = status.wait_for(IndexingStatus::is_idle).await;
+    }
+}
+
+/// Get the version of a queued note that commands return until the note is indexed. The title and text are the ones
+/// the note was last saved with, which are already written to the note file. The tags are kept from the indexed version
+/// of the note, or read from the metadata sidecar if the note was never indexed. So is the creation time, which is the
+/// time the note file was created for a note that isn't in the sidecar yet.
+pub(crate) fn queued_document(
+    root: &Path,
+    note_id: NoteId,
+    job: IndexJob,
+    indexed: Option<ContextualDocument>,
+) -> ContextualDocument {
+    let (tags, created_at) = match indexed {
+        Some(indexed) => (indexed.tags, indexed.created_at),
+        None => {
+            let metadata = read_metadata(root)
+                .remove(&job.file_name)
+                .filter(|metadata| metadata.id == note_id);
+            let created_at = metadata
+                .as_ref()
+                .and_then(|metadata| metadata.created_at)
+                .unwrap_or_else(|| file_created_at(&root.join("notes").join(&job.file_name)));
+            (
+                metadata.map(|metadata| metadata.tags).unwrap_or_default(),
+                created_at,
+            )
+        }
+    };
+    ContextualDocument {
+        note_id,
+        document: Document::from_parts(job.title, job.text),
+        tags,
+        created_at,
+    }
+}
+
+/// Queue a note to be indexed in the background. The file of the note must already be written.
+pub(crate) fn enqueue_note(
+    workspace_id: WorkspaceId,
+    note_id: NoteId,
+    job: IndexJob,
+) -> Result<(), LinknotesError> {
+    let queue = get_workspace_ref(workspace_id)?.indexing();
+    if queue.push(note_id, job) {
+        tauri::async_runtime::spawn(run_worker(workspace_id, queue));
+    }
+    Ok(())
+}
+
+/// Keeps the indexing queue of a workspace paused until it is dropped. See [`pause_indexing`].
+pub(crate) struct IndexingPause {
+    workspace_id: WorkspaceId,
+    queue: Arc<IndexingQueue>,
+}
+
+impl Drop for IndexingPause {
+    fn drop(&mut self) {
+        if self.queue.resume() {
+            tauri::async_runtime::spawn(run_worker(self.workspace_id, self.queue.clone()));
+        }
+    }
+}
+
+/// Pause the indexing queue of a workspace and wait for the note that is running to finish. Notes saved while the queue
+/// is paused are written and queued, but they are only indexed once the returned guard is dropped. Rebuilding the index
+/// pauses the queue so no note is stored between clearing the database and rebuilding it.
+///
+/// # Errors
+///
+/// Returns `LinknotesError::WorkspaceNotLoaded` if the workspace is not loaded.
+pub(crate) async fn pause_indexing(
+    workspace_id: WorkspaceId,
+) -> Result<IndexingPause, LinknotesError> {
+    let queue = get_workspace_ref(workspace_id)?.indexing();
+    queue.pause();
+    let pause = IndexingPause {
+        workspace_id,
+        queue: queue.clone(),
+    };
+    let mut status = queue.status.subscribe();
+    // The worker stops before its next note, so nothing runs once the running note is done
+    _ = status.wait_for(|status| status.indexing.is_none()).await;
+    Ok(pause)
+}
+
+/// Save the notes that were waiting to be indexed when a workspace was unloaded to the workspace root, so
+/// [`requeue_pending_jobs`] can queue them again when the workspace is loaded.
+///
+/// # Errors
+///
+/// Returns `LinknotesError::Io` if the file can't be written.
+pub(crate) fn save_pending_jobs(root: &Path, jobs: &[PendingJob]) -> Result<(), LinknotesError> {
+    if jobs.is_empty() {
+        return Ok(());
+    }
+    let contents =
+        serde_json::to_string_pretty(jobs).map_err(|err| LinknotesError::Io(err.to_string()))?;
+    // Write to a temporary file first so a crash never leaves a half written file
+    let temp = root.join(format!("{PENDING_JOBS_FILE_NAME}.tmp"));
+    std::fs::write(&temp, contents)?;
+    std::fs::rename(&temp, root.join(PENDING_JOBS_FILE_NAME))?;
+    Ok(())
+}
+
+/// Queue the notes that were saved by [`save_pending_jobs`] the last time a workspace was unloaded. The text of each
+/// note is read from its file, which was written before the note was queued the first time. Returns the number of
+/// notes that were queued.
+///
+/// # Errors
+///
+/// Returns `LinknotesError::WorkspaceNotLoaded` if the workspace is not loaded, or `LinknotesError::Io` if the file of
+/// pending notes can't be removed.
+pub(crate) fn requeue_pending_jobs(workspace_id: WorkspaceId) -> Result<usize, LinknotesError> {
+    let (path, notes_dir) = {
+        let workspace = get_workspace_ref(workspace_id)?;
+        (
+            workspace.location.join(PENDING_JOBS_FILE_NAME),
+            workspace.notes_dir()?,
+        )
+    };
+    let Ok(contents) = std::fs::read_to_string(&path) else {
+        return Ok(0);
+    };
+    let jobs: Vec<PendingJob> = serde_json::from_str(&contents).unwrap_or_else(|err| {
+        tracing::error!("Failed to read the pending notes in {:?}: {}", path, err);
+        Vec::new()
+    });
+
+    let mut queued = 0;
+    for PendingJob {
+        note_id,
+        title,
+        file_name,
+    } in jobs
+    {
+        let job = match file_name {
+            Some(file_name) => Ok(file_name),
+            None => title_to_file_name(&title).map_err(LinknotesError::from),
+        }
+        .and_then(|file_name| {
+            let text = std::fs::read_to_string(notes_dir.join(&file_name))?;
+            Ok(IndexJob {
+                title: title.clone(),
+                file_name,
+                text,
+            })
+        });
+        match job {
+            Ok(job) => {
+                enqueue_note(workspace_id, note_id, job)?;
+                queued += 1;
+            }
+            // The note was renamed or removed outside of the app. `repair_workspace` picks up the file if it still exists
+            Err(err) => tracing::warn!("Failed to queue pending note {:?}: {}", title, err),
+        }
+    }
+    std::fs::remove_file(&path)?;
+    tracing::info!(
+        "Queued {} notes that were pending when the workspace was unloaded",
+        queued
+    );
+    Ok(queued)
+}
+
+/// Index queued notes one at a time until the queue is empty
+async fn run_worker(workspace_id: WorkspaceId, queue: Arc<IndexingQueue>) {
+    while let Some((note_id, job)) = queue.next_job() {
+        let result = index_written_note(note_id.clone(), job, workspace_id).await;
+        if let Err(err) = &result {
+            tracing::error!("Failed to index note {}: {}", note_id, err);
+        }
+        let status = queue.finish_job(result.is_ok());
+        emit(
+            INDEXING_PROGRESS_EVENT,
+            IndexingProgress {
+                workspace_id,
+                note_id,
+                error: result.err(),
+                status,
+            },
+        );
+    }
+    let status = queue.status();
+    emit(
+        INDEXING_FINISHED_EVENT,
+        IndexingFinished {
+            workspace_id,
+            indexed: status.indexed,
+            failed: status.failed,
+        },
+    );
+}
+
+/// Wait until every note that was saved in a workspace is indexed. Search and the maintenance commands that rebuild
+/// the index call this first so they see the latest version of every note. Commands that only read notes use the
+/// queued version of the note instead of waiting.
+pub(crate) async fn wait_for_indexing(workspace_id: WorkspaceId) -> Result<(), LinknotesError> {
+    let queue = get_workspace_ref(workspace_id)?.indexing();
+    queue.wait_idle().await;
+    Ok(())
+}
+
+/// Get the state of the indexing queue of a workspace.
+///
+/// # Errors
+///
+/// Returns `LinknotesError::WorkspaceNotLoaded` if the workspace is not loaded.
+#[tauri::command]
+pub fn indexing_status(workspace_id: WorkspaceId) -> Result<IndexingStatus, LinknotesError> {
+    Ok(get_workspace_ref(workspace_id)?.indexing().status())
+}
+
+#[test]
+fn test_indexing_queue() {
+    let queue = IndexingQueue::new();
+    let integrals = NoteId::new();
+    let limits = NoteId::new();
+    let job = |text: &str| IndexJob {
+        title: "Integrals".to_string(),
+        file_name: "Integrals.txt".to_string(),
+        text: text.to_string(),
+    };
+
+    // Saving a queued note replaces the queued text
+    assert!(queue.push(integrals.clone(), job("first")));
+    assert!(!queue.push(
+        limits.clone(),
+        IndexJob {
+            title: "Limits".to_string(),
+            file_name: "Limits.txt".to_string(),
+            text: "limits".to_string(),
+        }
+    ));
+    assert!(!queue.push(integrals.clone(), job("second")));
+    assert_eq!(queue.status().pending, 2);
+    assert_eq!(queue.find_by_title("Limits"), Some(limits.clone()));
+
+    let (id, next) = queue.next_job().unwrap();
+    assert_eq!(id, integrals);
+    assert_eq!(next, job("second"));
+    assert_eq!(queue.title_of(&integrals), Some("Integrals".to_string()));
+    assert_eq!(queue.status().indexing, Some(integrals.clone()));
+    let status = queue.finish_job(true);
+    assert_eq!(status.indexed, 1);
+    assert_eq!(status.pending, 1);
+
+    // A paused queue keeps notes but doesn't index them until it is resumed
+    queue.pause();
+    assert!(queue.next_job().is_none());
+    assert!(!queue.push(integrals.clone(), job("paused")));
+    assert_eq!(queue.status().pending, 2);
+    assert!(queue.resume());
+    let (id, _) = queue.next_job().unwrap();
+    assert_eq!(id, limits);
+    queue.finish_job(true);
+
+    // Cancelling returns the pending notes and ignores new ones
+    assert_eq!(
+        queue.cancel(),
+        vec![PendingJob {
+            note_id: integrals.clone(),
+            title: "Integrals".to_string(),
+            file_name: Some("Integrals.txt".to_string()),
+        }]
+    );
+    assert!(queue.status().is_idle());
+    assert!(!queue.push(integrals, job("third")));
+    assert!(queue.next_job().is_none());
+}
+
+#[tokio::test]
+async fn test_save_note_in_background() {
+    use crate::embedder::NotesEmbedder;
+    use crate::note::{read_note, save_note};
+    use crate::workspace::{delete_workspace, load_workspace_with, unload_workspace};
+
+    _ = tracing_subscriber::fmt::try_init();
+
+    let temp = std::env::temp_dir();
+    let workspace_path = temp.join("testing-indexing-workspace");
+    _ = std::fs::remove_dir_all(&workspace_path);
+    let workspace = load_workspace_with(workspace_path, Some(NotesEmbedder::hashing())).unwrap();
+
+    // The file is written before the note is indexed
+    let id = save_note(
+        None,
+        "Integrals".to_string(),
+        "Integrals".to_string(),
+        workspace,
+    )
+    .await
+    .unwrap();
+    let path = get_workspace_ref(workspace)
+        .unwrap()
+        .notes_dir()
+        .unwrap()
+        .join(title_to_file_name("Integrals").unwrap());
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), "Integrals");
+
+    // Saving again before the note is indexed keeps the same id
+    for text in ["Integrals are", "Integrals are the area under a curve."] {
+        let saved = save_note(
+            Some(id.clone()),
+            "Integrals".to_string(),
+            text.to_string(),
+            workspace,
+        )
+        .await
+        .unwrap();
+        assert_eq!(saved, id);
+    }
+    assert!(
+        get_workspace_ref(workspace)
+            .unwrap()
+            .indexing()
+            .status()
+            .pending
+            <= 1
+    );
+
+    wait_for_indexing(workspace).await.unwrap();
+    let status = indexing_status(workspace).unwrap();
+    assert!(status.is_idle());
+    assert_eq!(status.failed, 0);
+    let note = read_note(id, workspace).await.unwrap();
+    assert_eq!(
+        note.document.body(),
+        "Integrals are the area under a curve."
+    );
+
+    delete_workspace(workspace).unwrap();
+    unload_workspace(workspace).unwrap();
+}
+
+#[tokio::test]
+async fn test_unload_with_pending_notes() {
+    use crate::embedder::NotesEmbedder;
+    use crate::note::{read_note, save_note};
+    use crate::workspace::{delete_workspace, load_workspace_with, unload_workspace};
+
+    _ = tracing_subscriber::fmt::try_init();
+
+    let temp = std::env::temp_dir();
+    let workspace_path = temp.join("testing-pending-indexing-workspace");
+    _ = std::fs::remove_dir_all(&workspace_path);
+    let workspace =
+        load_workspace_with(workspace_path.clone(), Some(NotesEmbedder::hashing())).unwrap();
+
+    // Unload the workspace while the notes are still waiting to be indexed
+    let mut notes = Vec::new();
+    for (title, text) in [
+        ("Integrals", "Integrals are the area under a curve."),
+        ("Limits", "Limits describe the value a function approaches."),
+    ] {
+        let id = save_note(None, title.to_string(), text.to_string(), workspace)
+            .await
+            .unwrap();
+        notes.push((id, text));
+    }
+    unload_workspace(workspace).unwrap();
+
+    // Loading the workspace again indexes the notes that were pending
+    let workspace =
+        load_workspace_with(workspace_path.clone(), Some(NotesEmbedder::hashing())).unwrap();
+    wait_for_indexing(workspace).await.unwrap();
+    for (id, text) in &notes {
+        let note = read_note(id.clone(), workspace).await.unwrap();
+        assert_eq!(note.document.body(), *text);
+    }
+    assert!(!workspace_path.join(PENDING_JOBS_FILE_NAME).exists());
+    unload_workspace(workspace).unwrap();
+
+    // Pending notes are read back from the workspace root when it is loaded
+    let id = NoteId::new();
+    let text = "Derivatives are the slope of a curve.";
+    std::fs::write(workspace_path.join("notes").join("Derivatives.txt"), text).unwrap();
+    save_pending_jobs(
+        &workspace_path,
+        &[PendingJob {
+            note_id: id.clone(),
+            title: "Derivatives".to_string(),
+            file_name: None,
+        }],
+    )
+    .unwrap();
+    let workspace =
+        load_workspace_with(workspace_path.clone(), Some(NotesEmbedder::hashing())).unwrap();
+    wait_for_indexing(workspace).await.unwrap();
+    let note = read_note(id, workspace).await.unwrap();
+    assert_eq!(note.document.body(), text);
+    assert!(!workspace_path.join(PENDING_JOBS_FILE_NAME).exists());
+
+    delete_workspace(workspace).unwrap();
+    unload_workspace(workspace).unwrap();
+}
+
+#[tokio::test]
+async fn test_read_queued_note() {
+    use crate::embedder::NotesEmbedder;
+    use crate::links::get_outgoing_links;
+    use crate::note::{get_note_id, get_tags, read_note, set_tags, Tag};
+    use crate::workspace::{
+        delete_workspace, files_in_workspace, load_workspace_with, unload_workspace,
+    };
+
+    _ = tracing_subscriber::fmt::try_init();
+
+    let temp = std::env::temp_dir();
+    let workspace_path = temp.join("testing-read-queued-workspace");
+    _ = std::fs::remove_dir_all(&workspace_path);
+    let workspace = load_workspace_with(workspace_path, Some(NotesEmbedder::hashing())).unwrap();
+
+    // Queue a note without starting a worker, so it stays queued for the whole test. Every command below would never
+    // return if it waited for the queue
+    let id = NoteId::new();
+    let text = "Integrals are the area under a curve. See [[Limits]].";
+    let queue = {
+        let workspace = get_workspace_ref(workspace).unwrap();
+        let path = workspace.notes_dir().unwrap().join("Integrals.txt");
+        std::fs::write(path, text).unwrap();
+        workspace.indexing()
+    };
+    assert!(queue.push(
+        id.clone(),
+        IndexJob {
+            title: "Integrals".to_string(),
+            file_name: "Integrals.txt".to_string(),
+            text: text.to_string(),
+        }
+    ));
+
+    assert_eq!(
+        get_note_id("Integrals".to_string(), workspace)
+            .await
+            .unwrap(),
+        id
+    );
+    let note = read_note(id.clone(), workspace).await.unwrap();
+    assert_eq!(note.document.title(), "Integrals");
+    assert_eq!(note.document.body(), text);
+    let files = files_in_workspace(workspace).await.unwrap();
+    assert_eq!(files.len(), 1);
+    assert_eq!(files[0].note_id, id);
+    let links = get_outgoing_links(id.clone(), workspace).await.unwrap();
+    assert_eq!(links.len(), 1);
+    assert_eq!(links[0].target, "Limits");
+
+    // Tags set before the note is indexed are kept in the metadata sidecar
+    let tags = vec![Tag {
+        name: "Calculus".to_string(),
+        manual: true,
+    }];
+    set_tags(id.clone(), tags.clone(), workspace).await.unwrap();
+    assert_eq!(get_tags(id, workspace).await.unwrap(), tags);
+    assert_eq!(queue.status().pending, 1);
+
+    delete_workspace(workspace).unwrap();
+    unload_workspace(workspace).unwrap();
+}
@@ -0,0 +1,209 @@
+/*!
+# Prologue Comments
+## Name of Code Artifact: Full-text Search
+
+## Brief Description: This code keeps a BM25 full-text index over the titles and bodies of notes in the workspace database. Searching the index finds exact words, identifiers, acronyms and course codes like "CS 4332" that embeddings often miss. Each hit is narrowed down to the sentence that contains the most query terms so it can be shown like a semantic search result.
+## Programmer’s Name: agent
+
+## Date Created: 2026-10-17
+## Dates Revised and Description of Revisions:
+## -> 2026-10-17: Added the full-text index and lexical search.
+## -> 2026-10-17: Added filters from the search query language.
+
+## Preconditions:
+- The document table of the workspace must be initialized.
+
+## Acceptable and Unacceptable Input Values/Types:
+- Any query text is accepted. Text is split into words at every change between letters, digits and other characters, and words are compared without case or accents.
+- A query without any words returns no results.
+
+## Postconditions:
+
+- Notes are in the full-text index as soon as their document is stored.
+
+## Return Values/Types:
+
+- `lexical_hits` returns the matching notes ordered by BM25 score, or a `LinknotesError::Storage` if the query fails.
+
+## Side Effects:
+-  The analyzer and indexes are defined in the workspace database the first time it is opened.
+
+## Invariants:
+
+- Only notes that contain every word of the query are returned.
+
+## Known Faults:
+
+- Words are not stemmed, so "integral" doesn't match "integrals".
+
+*/
+
+use kalosm::language::Document;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::ops::Range;
+
+#[cfg(test)]
+use pretty_assertions::assert_eq;
+
+use crate::classifier::chunk_text;
+use crate::error::LinknotesError;
+use crate::note::NoteId;
+use crate::query::CompiledQuery;
+use crate::ContextualDocumentTable;
+
+/// The name of the analyzer that splits notes into words for the full-text index
+const ANALYZER_NAME: &str = "note_text";
+
+/// A note that matched a full-text search.
+///
+/// # Fields
+///
+/// * `id` - The id of the document in the document table.
+/// * `note_id` - The id of the note.
+/// * `document` - The title and body of the note.
+/// * `score` - The BM25 score of the title and body. Higher is better.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct LexicalHit {
+    pub(crate) id: String,
+    pub(crate) note_id: NoteId,
+    pub(crate) document: Document,
+    pub(crate) score: f32,
+}
+
+/// Define the analyzer and the full-text indexes on the titles and bodies of the documents table. The indexes are only
+/// created if they don't exist, so existing workspaces are indexed the first time they are opened with this version.
+pub(crate) async fn define_full_text_index(
+    document_table: &ContextualDocumentTable,
+) -> Result<(), LinknotesError> {
+    // Index definitions can't take the table as a parameter. The table name is a constant, not user input
+    let table = document_table.table().table().to_string();
+    document_table
+        .table()
+        .db()
+        .query(format!(
+            "DEFINE ANALYZER IF NOT EXISTS {ANALYZER_NAME} TOKENIZERS class FILTERS lowercase, ascii"
+        ))
+        .query(format!("DEFINE INDEX IF NOT EXISTS note_body_text ON TABLE {table} FIELDS document.body SEARCH ANALYZER {ANALYZER_NAME} BM25"))
+        .query(format!("DEFINE INDEX IF NOT EXISTS note_title_text ON TABLE {table} FIELDS document.title SEARCH ANALYZER {ANALYZER_NAME} BM25"))
+        .await?
+        .check()?;
+    Ok(())
+}
+
+/// Split text into lowercase words the same way the analyzer does. A new word starts at every change between letters,
+/// digits and other characters, so "CS4332" is the words "cs" and "4332".
+pub(crate) fn query_terms(text: &str) -> Vec<String> {
+    #[derive(PartialEq)]
+    enum Class {
+        Letter,
+        Digit,
+        Other,
+    }
+    let class = |c: char| {
+        if c.is_alphabetic() {
+            Class::Letter
+        } else if c.is_numeric() {
+            Class::Digit
+        } else {
+            Class::Other
+        }
+    };
+
+    let mut terms: Vec<String> = Vec::new();
+    let mut current = String::new();
+    let mut current_class = Class::Other;
+    for c in text.chars() {
+        let next_class = class(c);
+        if next_class != current_class && !current.is_empty() {
+            terms.push(std::mem::take(&mut current));
+        }
+        if next_class != Class::Other {
+            current.extend(c.to_lowercase());
+        }
+        current_class = next_class;
+    }
+    if !current.is_empty() {
+        terms.push(current);
+    }
+    terms
+}
+
+/// Find the byte range of the sentence in a body that contains the most distinct query terms. Ties go to the first
+/// sentence. Returns `None` if the body is empty.
+pub(crate) fn best_sentence(body: &str, terms: &[String]) -> Option<Range<usize>> {
+    let sentences = chunk_text(body);
+    let mut best: Option<(usize, Range<usize>)> = None;
+    for sentence in sentences {
+        let words = query_terms(&body[sentence.clone()]);
+        let matches = terms.iter().filter(|term| words.contains(term)).count();
+        let better = match &best {
+            Some((best, _)) => matches > *best,
+            None => true,
+        };
+        if better {
+            best = Some((matches, sentence));
+        }
+    }
+    best.map(|(_, sentence)| sentence)
+}
+
+/// Search the full-text index for notes that contain every word of the query text and match the filter of the query.
+/// The scores of the title and body are added together.
+///
+/// # Errors
+///
+/// This function will return an error if the query fails.
+pub(crate) async fn lexical_hits(
+    document_table: &ContextualDocumentTable,
+    query: &CompiledQuery,
+    results: usize,
+) -> Result<Vec<LexicalHit>, LinknotesError> {
+    if query_terms(&query.text).is_empty() {
+        return Ok(Vec::new());
+    }
+    // The filter only contains parameters, so it is safe to add to the statement
+    let filter = query.and_filter();
+    let mut request = document_table
+        .table()
+        .db()
+        .query(format!("SELECT meta::id(id) as id, note_id, document, search::score(1) AS score FROM type::table($table) WHERE document.body @1@ $text{filter} ORDER BY score DESC LIMIT $results"))
+        .query(format!("SELECT meta::id(id) as id, note_id, document, search::score(1) AS score FROM type::table($table) WHERE document.title @1@ $text{filter} ORDER BY score DESC LIMIT $results"))
+        .bind(("table", document_table.table().table().to_string()))
+        .bind(("text", query.text.clone()))
+        .bind(("results", results));
+    for binding in &query.bindings {
+        request = request.bind(binding.clone());
+    }
+    let mut response = request.await?;
+    let body_hits: Vec<LexicalHit> = response.take(0)?;
+    let title_hits: Vec<LexicalHit> = response.take(1)?;
+
+    let mut hits: HashMap<String, LexicalHit> = HashMap::new();
+    for hit in body_hits.into_iter().chain(title_hits) {
+        match hits.get_mut(&hit.id) {
+            Some(existing) => existing.score += hit.score,
+            None => {
+                hits.insert(hit.id.clone(), hit);
+            }
+        }
+    }
+    let mut hits: Vec<LexicalHit> = hits.into_values().collect();
+    hits.sort_by(|a, b| b.score.total_cmp(&a.score));
+    hits.truncate(results);
+    Ok(hits)
+}
+
+#[test]
+fn test_query_terms() {
+    assert_eq!(query_terms("CS 4332"), vec!["cs", "4332"]);
+    assert_eq!(query_terms("CS4332: SIMD!"), vec!["cs", "4332", "simd"]);
+    assert_eq!(query_terms("  "), Vec::<String>::new());
+
+    let body = "Vectors are fast. SIMD runs one instruction on many values. Done.";
+    let sentence = best_sentence(body, &query_terms("simd values")).unwrap();
+    assert_eq!(
+        body[sentence].trim(),
+        "SIMD runs one instruction on many values."
+    );
+}
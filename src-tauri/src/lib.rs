@@ -15,6 +15,7 @@
 -> 2024-10-18: Integrated with Tauri for application development.
 -> 2024-12-08: Improved performance and fixed bugs.
 -> 2024-12-08: Finalized documentation and testing.
+-> 2026-10-17: Loaded one BERT model for each model source so workspaces can use different models.
 
 ## Preconditions:
 - A Tauri application context is required for the run function.
@@ -46,27 +47,43 @@
 
 ## Side Effects:
 - Workspace files may be created, modified, or deleted based on the function.
-- A global singleton (BERT) is initialized, consuming system resources.
+- A BERT model is loaded for each model source that is used, consuming system resources.
 
 ## Invariants:
 
-- Once initialized, the BERT model remains immutable.
+- Once initialized, the BERT model of a source remains immutable.
 - Workspace states should remain consistent after note operations.
 
 ## Known Faults:
 
 - Potential performance bottleneck during embedding generation if BERT initialization is delayed.
 - Edge cases with workspace paths or malformed input data may cause unexpected behavior.
-- Loading the first note takes a long time because the training model needs to run on the default training notes.
+- Loading the first note takes a long time because the training model needs to run on the default training notes. The frontend calls `warm_up` at startup so the model is usually loaded before the first note is saved.
 
 */
 
+use embedder::NotesEmbedder;
+use embedding_migration::{embedding_model_status, migrate_embedding_model, set_embedding_model};
+use indexing::indexing_status;
 use kalosm::language::*;
-use note::{get_tags, read_note, remove_note, save_note, set_tags, ContextualDocument};
+use links::{get_backlinks, get_outgoing_links};
+use manifest::get_workspace_manifest;
+use mentions::find_unlinked_mentions;
+use model::{loading_status, model_status, set_model_status, warm_up, ModelSource, ModelStatus};
+use note::{
+    get_note_id, get_tags, read_note, remove_note, rename_note, save_note, set_tags,
+    ContextualDocument,
+};
+use note_search::search_notes;
+use query::parse_search_query;
+use reindex::reindex_workspace;
+use repair::{check_workspace, repair_workspace};
 use search::{context_search, search};
+use watcher::{unwatch_workspace, watch_workspace};
 // The line `use std::{ num::NonZero, sync::{Arc, OnceLock} };` is importing specific items from the
 // `std` (standard library) module in Rust. Here's what each item does:
 use std::{
+    collections::BTreeMap, // BTreeMap keeps the loaded BERT models by the id of their source.
     num::NonZero, // NonZero is a type that represents a non-zero integer. It's used in the `bert` function to set the cache size.
     sync::{Arc, OnceLock}, // Arc is a type that provides shared ownership of a value. OnceLock is a type that ensures a value is only initialized once.
 };
@@ -78,7 +95,9 @@ use workspace::{
     delete_workspace,
     files_in_workspace,
     get_workspace_id,
+    is_workspace_read_only,
     load_workspace,
+    load_workspace_read_only,
     unload_workspace, // These functions are used to manage workspaces.
 };
 
@@ -86,49 +105,81 @@ use workspace::{
 use pretty_assertions::assert_eq;
 
 mod classifier;
+mod diversify;
+mod embedder;
+mod embedding_cache;
+mod embedding_migration;
+mod error;
+mod indexing;
+mod lexical;
+mod links;
+mod lock;
+mod manifest;
+mod mentions;
+mod metadata;
+mod model;
 mod note;
+mod note_search;
+mod query;
+mod reindex;
+mod repair;
 mod search;
+mod watcher;
 mod workspace;
 
-// The line `static BERT: OnceLock<anyhow::Result<Arc<CachedEmbeddingModel<Bert>>>> = OnceLock::new();`
-// is declaring a static variable named `BERT` of type `OnceLock` that holds a result of type
-// `anyhow::Result` containing an `Arc` (atomic reference counting) to a `CachedEmbeddingModel`
-// specialized with `Bert`.
-static BERT: OnceLock<anyhow::Result<Arc<CachedEmbeddingModel<Bert>>>> = OnceLock::new();
-// The line `static BERT_LOCK: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());` is
-// declaring a static variable named `BERT_LOCK` of type `tokio::sync::Mutex<()>`.
-static BERT_LOCK: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
-
-/// The function `bert` is an asynchronous Rust function that retrieves a cached embedding model of
-/// Bert, ensuring only one instance is created using a lock.
+/// The BERT models that were loaded, keyed by the id of their source. Workspaces with the same model source share one
+/// model. Each cell is only initialized once, so a model is never loaded twice and a model that failed stays failed.
+type LoadedBert = Result<Arc<CachedEmbeddingModel<Bert>>, String>;
+static BERT_MODELS: parking_lot::Mutex<BTreeMap<String, Arc<tokio::sync::OnceCell<LoadedBert>>>> =
+    parking_lot::Mutex::new(BTreeMap::new());
+
+/// The function `bert` is an asynchronous Rust function that retrieves the cached embedding model of
+/// Bert loaded from a source, loading it the first time the source is used.
 ///
 /// Returns:
 ///
-/// The function `bert()` returns a `Result` containing a reference to a static `Arc` pointing to a
-/// `CachedEmbeddingModel` of type `Bert`.
-async fn bert() -> anyhow::Result<&'static Arc<CachedEmbeddingModel<Bert>>> {
-    let _guard = BERT_LOCK.lock().await; // Acquire a lock on the BERT_LOCK mutex.
-    if BERT.get().is_none() {
-        // Check if the BERT static variable is uninitialized.
-        _ = BERT.set(
-            // Set the BERT static variable to a new value.
-            Bert::builder() // Create a new Bert model builder.
-                .with_source(BertSource::snowflake_arctic_embed_small()) // Set the source of the Bert model.
-                .build() // Build the Bert model.
+/// The function `bert()` returns a `Result` containing an `Arc` pointing to the `CachedEmbeddingModel` of
+/// type `Bert` for the source.
+async fn bert(source: &ModelSource) -> anyhow::Result<Arc<CachedEmbeddingModel<Bert>>> {
+    // Only the cell of the source is awaited, so loading one model doesn't block workspaces that use another
+    let cell = BERT_MODELS.lock().entry(source.id()).or_default().clone();
+    let model = cell
+        .get_or_init(|| async {
+            let model = Bert::builder() // Create a new Bert model builder.
+                .with_source(source.bert_source()) // Set the source of the Bert model.
+                .build_with_loading_handler(|progress| set_model_status(loading_status(progress))) // Build the Bert model and report the download and loading progress.
                 .await // Build the Bert model asynchronously.
-                .map(|e| Arc::new(e.cached(NonZero::new(2048).unwrap()))), // Cache the Bert model with a size of 2048.
-        );
+                .map(|e| Arc::new(e.cached(NonZero::new(2048).unwrap()))) // Cache the Bert model with a size of 2048.
+                .map_err(|err| err.to_string());
+            set_model_status(match &model {
+                Ok(_) => ModelStatus::Ready,
+                Err(message) => ModelStatus::Failed {
+                    message: message.clone(),
+                },
+            });
+            model
+        })
+        .await;
+    model.clone().map_err(|err| anyhow::anyhow!(err))
+}
+
+/// The handle of the running app. Work that runs in the background uses it to send events to the frontend
+static APP_HANDLE: OnceLock<tauri::AppHandle> = OnceLock::new();
+
+/// Send an event to the frontend. This does nothing if the app isn't running, like in tests.
+fn emit<S: serde::Serialize + Clone>(event: &str, payload: S) {
+    use tauri::Emitter;
+    if let Some(app) = APP_HANDLE.get() {
+        if let Err(err) = app.emit(event, payload) {
+            tracing::error!("Failed to emit {}: {}", event, err);
+        }
     }
-    BERT.get() // Return the value of the BERT static variable.
-        .unwrap() // Unwrap the value of the BERT static variable.
-        .as_ref() // Get a reference to the value of the BERT static variable.
-        .map_err(|err| anyhow::anyhow!(err)) // Map any error to an anyhow error.
 }
 
-// The line `type ContextualDocumentTable = DocumentTable<Db, ContextualDocument,
-// Arc<CachedEmbeddingModel<Bert>>, DefaultSentenceChunker>;` is defining a type alias in Rust.
+// The line `type ContextualDocumentTable = DocumentTable<Db, ContextualDocument, NotesEmbedder,
+// DefaultSentenceChunker>;` is defining a type alias in Rust. The embedder is BERT, or the hashing embedder in tests.
 type ContextualDocumentTable =
-    DocumentTable<Db, ContextualDocument, Arc<CachedEmbeddingModel<Bert>>, DefaultSentenceChunker>;
+    DocumentTable<Db, ContextualDocument, NotesEmbedder, DefaultSentenceChunker>;
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 // The function initializes a Tauri application with various plugins and handlers for workspace and
@@ -137,20 +188,46 @@ pub fn run() {
     tauri::Builder::default() // Create a new Tauri application builder with default settings.
         .plugin(tauri_plugin_shell::init()) // Initialize the Tauri shell plugin.
         .plugin(tauri_plugin_fs::init()) // Initialize the Tauri file system plugin.
+        .setup(|app| {
+            // Keep the handle so background work can send events
+            _ = APP_HANDLE.set(app.handle().clone());
+            Ok(())
+        })
         .invoke_handler(tauri::generate_handler![
             // Generate an invoke handler for the following functions.
             get_workspace_id,
+            get_note_id,
             save_note,
             set_tags,
             get_tags,
             remove_note,
+            rename_note,
             search,
+            search_notes,
+            parse_search_query,
             read_note,
             files_in_workspace,
             load_workspace,
+            load_workspace_read_only,
+            is_workspace_read_only,
+            get_workspace_manifest,
+            watch_workspace,
+            unwatch_workspace,
+            check_workspace,
+            repair_workspace,
+            reindex_workspace,
+            indexing_status,
+            model_status,
+            warm_up,
+            embedding_model_status,
+            migrate_embedding_model,
+            set_embedding_model,
             unload_workspace,
             delete_workspace,
-            context_search
+            context_search,
+            get_outgoing_links,
+            get_backlinks,
+            find_unlinked_mentions
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
@@ -160,38 +237,50 @@ pub fn run() {
 // searching, and asserting notes within a workspace.
 #[tokio::test]
 async fn test_notes() {
+    use crate::workspace::load_workspace_with;
     _ = tracing_subscriber::fmt::try_init();
 
     let temp = std::env::temp_dir();
     let workspace_path = temp.join("testing-notes-workspace");
     _ = std::fs::remove_dir_all(&workspace_path);
-    let workspace = load_workspace(workspace_path);
+    let workspace = load_workspace_with(workspace_path, Some(NotesEmbedder::hashing())).unwrap();
 
-    save_note(
+    let id = save_note(
+        None,
         "mynote".to_string(),
         "my note is here".to_string(),
         workspace,
     )
     .await
     .unwrap();
-    remove_note("mynote".to_string(), workspace).await;
+    remove_note(id, workspace).await.unwrap();
 
-    save_note(
+    let id = save_note(
+        None,
         "search-note".to_string(),
         "my note is here".to_string(),
         workspace,
     )
     .await
     .unwrap();
-    let results = crate::search::search("my note is here".to_string(), Vec::new(), 10, workspace)
-        .await
-        .unwrap();
+    let results = crate::search::search(
+        "my note is here".to_string(),
+        crate::query::TagFilter::default(),
+        10,
+        None,
+        None,
+        workspace,
+    )
+    .await
+    .unwrap();
     assert_eq!(results.len(), 1);
+    assert_eq!(results[0].id, id);
     assert_eq!(results[0].title, "search-note");
     assert_eq!(results[0].character_range, 0..15);
 
-    let notes = files_in_workspace(workspace).await;
+    let notes = files_in_workspace(workspace).await.unwrap();
     assert_eq!(notes.len(), 1);
+    assert_eq!(notes[0].note_id, id);
     assert_eq!(
         notes[0].document,
         Document::from_parts("search-note".to_string(), "my note is here".to_string())
@@ -200,6 +289,6 @@ async fn test_notes() {
     assert_eq!(notes[0].tags[0].manual, false);
     assert!(["Math", "Computer Science"].contains(&notes[0].tags[0].name.as_str()));
 
-    delete_workspace(workspace);
-    unload_workspace(workspace);
+    delete_workspace(workspace).unwrap();
+    unload_workspace(workspace).unwrap();
 }
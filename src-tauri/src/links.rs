@@ -0,0 +1,424 @@
+/*!
+# Prologue Comments
+## Name of Code Artifact: Wiki Link Graph
+
+## Brief Description: This code parses wiki style `[[Note Title]]` and `[[Note Title#heading]]` references out of note bodies and stores them as edges in the workspace database. It exposes commands to list the links going out of a note and the backlinks pointing into a note.
+## Programmer’s Name: agent
+
+## Date Created: 2026-10-17
+## Dates Revised and Description of Revisions:
+## -> 2026-10-17: Added link parsing and the link graph table.
+## -> 2026-10-17: Read the links of notes that are waiting to be indexed from the queued text instead of waiting for the queue.
+## -> 2026-10-17: Stored the links of notes saved before the link graph existed in a migration.
+
+## Preconditions:
+- Workspaces must be loaded before any link related operations are called.
+- Notes must be saved before their links are included in the link graph.
+
+## Acceptable and Unacceptable Input Values/Types:
+- Each tauri command takes an object with types defined by the type definition in the code. Arguments are represented as
+fields in that object.
+- References must open with `[[` and close with `]]` on the same line. References with an empty title are ignored.
+
+## Postconditions:
+
+- The link graph contains exactly the references in the last saved version of every note.
+
+## Return Values/Types:
+
+- Functions return Result types with success yielding the links and a `LinknotesError` on failure.
+
+## Side Effects:
+-  The link table in the workspace database is modified when a note is saved or removed.
+
+## Invariants:
+
+- Every link is stored with the id of the note it was found in and the character range of the reference in that note.
+
+## Known Faults:
+
+- Links are stored by title, so links to a note that does not exist (yet) are kept as dangling links.
+
+*/
+
+use serde::{Deserialize, Serialize};
+use std::ops::Range;
+use surrealdb::{engine::local::Db, Surreal};
+
+#[cfg(test)]
+use pretty_assertions::assert_eq;
+
+use crate::error::LinknotesError;
+use crate::indexing::IndexingQueue;
+use crate::note::{find_note_title, NoteId};
+use crate::workspace::{get_workspace_ref, WorkspaceId};
+use crate::ContextualDocumentTable;
+
+/// The table that stores one record for every `[[link]]` in a note
+pub(crate) const DOCUMENT_LINK_TABLE: &str = "document_links";
+
+/// A `[[link]]` from one note to another.
+///
+/// # Fields
+///
+/// * `source` - The id of the note the reference was found in.
+/// * `target` - The title of the note the reference points to.
+/// * `heading` - The heading inside the target note if the reference was written as `[[Note Title#heading]]`.
+/// * `character_range` - The character range of the whole reference (including the brackets) within the source note.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NoteLink {
+    pub source: NoteId,
+    pub target: String,
+    pub heading: Option<String>,
+    pub character_range: Range<usize>,
+}
+
+/// Find all of the `[[Note Title]]` and `[[Note Title#heading]]` references in some text.
+///
+/// # Arguments
+///
+/// * `source` - The id of the note the text belongs to.
+/// * `text` - The body of the note.
+///
+/// # Returns
+///
+/// A list of links in the order they appear in the text.
+pub(crate) fn parse_links(source: &NoteId, text: &str) -> Vec<NoteLink> {
+    let mut links = Vec::new();
+    let mut search_from = 0; // The byte index we should start looking for the next reference at
+    while let Some(open_offset) = text[search_from..].find("[[") {
+        let open = search_from + open_offset;
+        let inner_start = open + 2;
+        let rest = &text[inner_start..];
+        let Some(close_offset) = rest.find("]]") else {
+            break;
+        };
+        let inner = &rest[..close_offset];
+        // A reference can't span multiple lines or contain another reference. If it does, start looking again
+        // from the inner opening brackets
+        if inner.contains('\n') {
+            search_from = inner_start;
+            continue;
+        }
+        if let Some(nested_open) = inner.rfind("[[") {
+            search_from = inner_start + nested_open;
+            continue;
+        }
+        let close = inner_start + close_offset + 2;
+        search_from = close;
+
+        // Split the reference into the title and the optional heading
+        let (target, heading) = match inner.split_once('#') {
+            Some((target, heading)) => (target.trim(), Some(heading.trim())),
+            None => (inner.trim(), None),
+        };
+        if target.is_empty() {
+            continue;
+        }
+        let heading = heading
+            .filter(|heading| !heading.is_empty())
+            .map(|heading| heading.to_string());
+
+        let char_start = text[..open].chars().count();
+        let char_len = text[open..close].chars().count();
+        links.push(NoteLink {
+            source: source.clone(),
+            target: target.to_string(),
+            heading,
+            character_range: char_start..char_start + char_len,
+        });
+    }
+    links
+}
+
+/// Replace all of the links going out of a note with the links in the new body of the note.
+pub(crate) async fn update_links(
+    db: &Surreal<Db>,
+    source: &NoteId,
+    text: &str,
+) -> Result<(), LinknotesError> {
+    delete_outgoing_links(db, source).await?;
+    for link in parse_links(source, text) {
+        let _: Vec<NoteLink> = db.create(DOCUMENT_LINK_TABLE).content(link).await?;
+    }
+    Ok(())
+}
+
+/// Store the links of every note in the document table. Notes saved before the link graph existed have no links
+/// stored, so this replaces the links of every note with the links in its stored body.
+pub(crate) async fn backfill_links(
+    document_table: &ContextualDocumentTable,
+) -> Result<(), LinknotesError> {
+    #[derive(Deserialize)]
+    struct NoteBody {
+        note_id: NoteId,
+        body: String,
+    }
+
+    let db = document_table.table().db();
+    let mut notes = db
+        .query("SELECT note_id, document.body AS body FROM type::table($documents)")
+        .bind(("documents", document_table.table().table().to_string()))
+        .await?;
+    let notes: Vec<NoteBody> = notes.take(0)?;
+    tracing::info!("Storing the links of {} notes", notes.len());
+    for note in notes {
+        update_links(db, &note.note_id, &note.body).await?;
+    }
+    Ok(())
+}
+
+/// Remove all of the links going out of a note. This should be called when the note is removed.
+pub(crate) async fn delete_outgoing_links(
+    db: &Surreal<Db>,
+    source: &NoteId,
+) -> Result<(), LinknotesError> {
+    db.query("DELETE type::table($table) WHERE source = $source")
+        .bind(("table", DOCUMENT_LINK_TABLE))
+        .bind(("source", source.clone()))
+        .await?
+        .check()?;
+    Ok(())
+}
+
+/// Run a query that selects links with a value bound to `$value`, and sort them by the note they appear in and their
+/// position in that note
+async fn select_links(
+    db: &Surreal<Db>,
+    query: &str,
+    value: impl Serialize,
+) -> Result<Vec<NoteLink>, LinknotesError> {
+    let mut response = db
+        .query(query)
+        .bind(("table", DOCUMENT_LINK_TABLE))
+        .bind(("value", value))
+        .await?;
+    let mut links: Vec<NoteLink> = response.take(0)?;
+    links.sort_by(|a, b| {
+        a.source
+            .cmp(&b.source)
+            .then(a.character_range.start.cmp(&b.character_range.start))
+    });
+    Ok(links)
+}
+
+/// Get all of the links that point to a title. Notes that are waiting to be indexed don't have their links in the graph
+/// yet, so the links in their queued text are used instead of the stored links.
+pub(crate) async fn links_to(
+    db: &Surreal<Db>,
+    queue: &IndexingQueue,
+    title: &str,
+) -> Result<Vec<NoteLink>, LinknotesError> {
+    let queued = queue.queued_jobs();
+    let mut links = select_links(
+        db,
+        "SELECT * FROM type::table($table) WHERE target = $value",
+        title,
+    )
+    .await?;
+    links.retain(|link| !queued.iter().any(|(id, _)| id == &link.source));
+    for (source, job) in queued {
+        links.extend(
+            parse_links(&source, &job.text)
+                .into_iter()
+                .filter(|link| link.target == title),
+        );
+    }
+    links.sort_by(|a, b| {
+        a.source
+            .cmp(&b.source)
+            .then(a.character_range.start.cmp(&b.character_range.start))
+    });
+    Ok(links)
+}
+
+/// Get all of the links going out of a note.
+///
+/// # Arguments
+///
+/// * `id` - The id of the note to get the links from.
+/// * `workspace_id` - The ID of the workspace the note is in.
+///
+/// # Returns
+///
+/// A `Result` containing the links in the order they appear in the note, or a `LinknotesError` if the query fails.
+#[tauri::command]
+pub async fn get_outgoing_links(
+    id: NoteId,
+    workspace_id: WorkspaceId,
+) -> Result<Vec<NoteLink>, LinknotesError> {
+    tracing::info!("get_outgoing_links called with id {}", id);
+    let workspace = get_workspace_ref(workspace_id)?;
+    // The links of a note that is waiting to be indexed are read from the queued text
+    if let Some(job) = workspace.indexing().queued_job(&id) {
+        return Ok(parse_links(&id, &job.text));
+    }
+    let document_table = workspace.document_table().await?;
+    select_links(
+        document_table.table().db(),
+        "SELECT * FROM type::table($table) WHERE source = $value",
+        id,
+    )
+    .await
+}
+
+/// Get all of the links in other notes that point to a note.
+///
+/// # Arguments
+///
+/// * `id` - The id of the note the links point to.
+/// * `workspace_id` - The ID of the workspace the note is in.
+///
+/// # Returns
+///
+/// A `Result` containing the links grouped by the note they appear in, or a `LinknotesError` if the query fails. The `character_range` of each link is the range in the source note.
+#[tauri::command]
+pub async fn get_backlinks(
+    id: NoteId,
+    workspace_id: WorkspaceId,
+) -> Result<Vec<NoteLink>, LinknotesError> {
+    tracing::info!("get_backlinks called with id {}", id);
+    let workspace = get_workspace_ref(workspace_id)?;
+    let queue = workspace.indexing();
+    let document_table = workspace.document_table().await?;
+    let db = document_table.table().db();
+    // Links are written with the title of the note they point to. A note that is waiting to be indexed may not be in
+    // the database yet
+    let title = match queue.title_of(&id) {
+        Some(title) => title,
+        None => find_note_title(db, &id)
+            .await?
+            .ok_or(LinknotesError::NoteNotFound)?,
+    };
+    links_to(db, &queue, &title).await
+}
+
+#[test]
+fn test_parse_links() {
+    let text = "See [[Integrals]] and [[Limits#Epsilon delta]].\n[[broken\n]] [[]] [[ 日本 ]] [[outer [[inner]]";
+    let source = NoteId::new();
+    let links = parse_links(&source, text);
+    assert_eq!(
+        links,
+        vec![
+            NoteLink {
+                source: source.clone(),
+                target: "Integrals".to_string(),
+                heading: None,
+                character_range: 4..17,
+            },
+            NoteLink {
+                source: source.clone(),
+                target: "Limits".to_string(),
+                heading: Some("Epsilon delta".to_string()),
+                character_range: 22..46,
+            },
+            NoteLink {
+                source: source.clone(),
+                target: "日本".to_string(),
+                heading: None,
+                character_range: 65..73,
+            },
+            NoteLink {
+                source: source.clone(),
+                target: "inner".to_string(),
+                heading: None,
+                character_range: 82..91,
+            },
+        ]
+    );
+}
+
+#[tokio::test]
+async fn test_links() {
+    use crate::embedder::NotesEmbedder;
+    use crate::note::{remove_note, save_note};
+    use crate::workspace::{delete_workspace, load_workspace_with, unload_workspace};
+
+    _ = tracing_subscriber::fmt::try_init();
+
+    let temp = std::env::temp_dir();
+    let workspace_path = temp.join("testing-links-workspace");
+    _ = std::fs::remove_dir_all(&workspace_path);
+    let workspace =
+        load_workspace_with(workspace_path.clone(), Some(NotesEmbedder::hashing())).unwrap();
+
+    let calculus = save_note(
+        None,
+        "Calculus".to_string(),
+        "Calculus builds on [[Limits#Definition]] and [[Integrals]].".to_string(),
+        workspace,
+    )
+    .await
+    .unwrap();
+    let integrals = save_note(
+        None,
+        "Integrals".to_string(),
+        "Integrals are the inverse of derivatives. See [[Calculus]].".to_string(),
+        workspace,
+    )
+    .await
+    .unwrap();
+
+    let outgoing = get_outgoing_links(calculus.clone(), workspace)
+        .await
+        .unwrap();
+    assert_eq!(
+        outgoing
+            .iter()
+            .map(|link| (link.target.as_str(), link.heading.as_deref()))
+            .collect::<Vec<_>>(),
+        vec![("Limits", Some("Definition")), ("Integrals", None)]
+    );
+    assert_eq!(outgoing[1].character_range, 45..58);
+
+    let backlinks = get_backlinks(integrals.clone(), workspace).await.unwrap();
+    assert_eq!(backlinks.len(), 1);
+    assert_eq!(backlinks[0].source, calculus);
+
+    // Notes saved before the link graph existed get their links when the workspace is migrated
+    {
+        let workspace = get_workspace_ref(workspace).unwrap();
+        let document_table = workspace.document_table().await.unwrap();
+        document_table
+            .table()
+            .db()
+            .query("DELETE type::table($table)")
+            .bind(("table", DOCUMENT_LINK_TABLE))
+            .await
+            .unwrap()
+            .check()
+            .unwrap();
+    }
+    crate::manifest::update_manifest(&workspace_path, |manifest| manifest.schema_version = 4)
+        .unwrap();
+    unload_workspace(workspace).unwrap();
+    let workspace =
+        load_workspace_with(workspace_path.clone(), Some(NotesEmbedder::hashing())).unwrap();
+    assert_eq!(
+        get_outgoing_links(calculus.clone(), workspace)
+            .await
+            .unwrap(),
+        outgoing
+    );
+
+    // Saving a new version of the note replaces the old links
+    save_note(
+        Some(calculus.clone()),
+        "Calculus".to_string(),
+        "Calculus builds on limits.".to_string(),
+        workspace,
+    )
+    .await
+    .unwrap();
+    let backlinks = get_backlinks(integrals.clone(), workspace).await.unwrap();
+    assert!(backlinks.is_empty());
+
+    // Removing a note removes the links going out of it
+    remove_note(integrals, workspace).await.unwrap();
+    let backlinks = get_backlinks(calculus.clone(), workspace).await.unwrap();
+    assert!(backlinks.is_empty());
+
+    delete_workspace(workspace).unwrap();
+    unload_workspace(workspace).unwrap();
+}
@@ -0,0 +1,288 @@
+/*!
+# Prologue Comments
+## Name of Code Artifact: Workspace Lock File
+
+## Brief Description: This code keeps two processes from opening the same workspace database at the same time. The process that opens a workspace writes a lock file with its PID to the workspace root and refreshes a heartbeat in that file until the workspace is unloaded. Lock files with a heartbeat that stopped are left behind by a crash and are taken over.
+## Programmer’s Name: agent
+
+## Date Created: 2026-10-17
+## Dates Revised and Description of Revisions:
+## -> 2026-10-17: Added the advisory workspace lock file.
+## -> 2026-10-17: Wrote the lock file and heartbeats atomically and stopped treating lock files that can't be parsed as stale.
+
+## Preconditions:
+- The workspace root must be writable to take the lock.
+
+## Acceptable and Unacceptable Input Values/Types:
+- The workspace root can be any directory. It is created if it doesn't exist.
+- Lock files that can't be parsed are treated as live until they are `STALE_LOCK_AGE` old.
+
+## Postconditions:
+
+- While a `WorkspaceLock` is alive, the lock file contains the PID of this process and a heartbeat that is at most `HEARTBEAT_INTERVAL` old.
+
+## Return Values/Types:
+
+- `WorkspaceLock::acquire` returns the lock, or `LinknotesError::WorkspaceLocked` with the PID of the owner if another process holds a live lock. The PID is `None` if the lock file can't be parsed.
+
+## Side Effects:
+-  A `workspace.lock` file is created in the workspace root and removed when the lock is dropped. It is written to a temporary file next to it first, so other processes never see a partly written lock.
+-  A background thread rewrites the lock file every `HEARTBEAT_INTERVAL`.
+
+## Invariants:
+
+- A lock file is only removed by the process that owns it, or taken over after its heartbeat is older than `STALE_LOCK_AGE`.
+
+## Known Faults:
+
+- The lock is advisory. Processes that don't check the lock file can still open the database.
+- Two processes that find the same stale lock at the same time may both take it over.
+- Filesystems without hard links can't create the lock file.
+
+*/
+
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{channel, RecvTimeoutError, Sender};
+use std::thread::JoinHandle;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::error::LinknotesError;
+
+/// The name of the lock file in the workspace root
+pub(crate) const LOCK_FILE_NAME: &str = "workspace.lock";
+/// How often the owner of a lock refreshes the heartbeat
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(5);
+/// A lock with a heartbeat older than this was left behind by a process that crashed
+const STALE_LOCK_AGE: Duration = Duration::from_secs(30);
+
+/// The contents of the lock file.
+///
+/// # Fields
+///
+/// * `pid` - The id of the process that holds the lock.
+/// * `heartbeat` - The last time the owner refreshed the lock in milliseconds since the unix epoch.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) struct LockFile {
+    pub(crate) pid: u32,
+    pub(crate) heartbeat: u64,
+}
+
+impl LockFile {
+    /// A lock file owned by this process with a heartbeat of now
+    fn current() -> Self {
+        Self {
+            pid: std::process::id(),
+            heartbeat: now(),
+        }
+    }
+
+    /// Read the lock file in a workspace root. Returns `None` if the file doesn't exist or can't be parsed
+    pub(crate) fn read(root: &Path) -> Option<Self> {
+        let contents = std::fs::read_to_string(root.join(LOCK_FILE_NAME)).ok()?;
+        serde_json::from_str(&contents).ok()
+    }
+
+    /// Write the lock to a temporary file in a workspace root and return the path of that file. The name is unique to
+    /// this process so two processes never write to the same temporary file
+    fn write_temp(&self, root: &Path) -> std::io::Result<PathBuf> {
+        let contents = serde_json::to_string(self)?;
+        let temp = root.join(format!("{LOCK_FILE_NAME}.{}.tmp", std::process::id()));
+        std::fs::write(&temp, contents)?;
+        Ok(temp)
+    }
+
+    /// Replace the lock file in a workspace root. The file is renamed into place, so readers see either the old or
+    /// the new lock and never a partly written one
+    pub(crate) fn write(&self, root: &Path) -> std::io::Result<()> {
+        let temp = self.write_temp(root)?;
+        std::fs::rename(&temp, root.join(LOCK_FILE_NAME))
+    }
+
+    /// Create the lock file in a workspace root if it doesn't exist. The lock is written to a temporary file and then
+    /// hard linked to the lock file, which fails if the lock file already exists. Unlike creating the lock file and
+    /// writing to it, other processes never see an empty lock.
+    fn create(&self, root: &Path) -> std::io::Result<()> {
+        let temp = self.write_temp(root)?;
+        let linked = std::fs::hard_link(&temp, root.join(LOCK_FILE_NAME));
+        _ = std::fs::remove_file(&temp);
+        linked
+    }
+
+    /// Check if the owner stopped refreshing the lock
+    fn is_stale(&self) -> bool {
+        // A lock with our own pid can't be from a workspace that is open in this process. Workspaces are only opened
+        // once per process, so the lock was left behind
+        self.pid == std::process::id()
+            || now().saturating_sub(self.heartbeat) > STALE_LOCK_AGE.as_millis() as u64
+    }
+}
+
+/// Check if a lock file that can't be parsed was left behind. The file may have been written by another version of
+/// the app, so it is only stale once it hasn't been modified for `STALE_LOCK_AGE`. Returns `None` if the file doesn't
+/// exist anymore.
+fn is_unreadable_lock_stale(lock_path: &Path) -> Option<bool> {
+    let metadata = std::fs::metadata(lock_path).ok()?;
+    Some(match metadata.modified() {
+        Ok(modified) => modified.elapsed().is_ok_and(|age| age > STALE_LOCK_AGE),
+        // Without a modification time the lock could never be taken over, so it is treated as stale
+        Err(_) => true,
+    })
+}
+
+/// The current time in milliseconds since the unix epoch
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|time| time.as_millis() as u64)
+        .unwrap_or_default()
+}
+
+/// An advisory lock on a workspace directory. The lock is released when this is dropped.
+pub(crate) struct WorkspaceLock {
+    root: PathBuf,
+    stop_heartbeat: Option<Sender<()>>,
+    heartbeat: Option<JoinHandle<()>>,
+}
+
+impl WorkspaceLock {
+    /// Take the lock on a workspace root. If another live process holds the lock, this returns
+    /// `LinknotesError::WorkspaceLocked` with the pid of that process.
+    pub(crate) fn acquire(root: &Path) -> Result<Self, LinknotesError> {
+        std::fs::create_dir_all(root)?;
+        let lock_path = root.join(LOCK_FILE_NAME);
+        loop {
+            // Create the lock file only if it doesn't exist so two processes can't both create it
+            match LockFile::current().create(root) {
+                Ok(()) => break,
+                Err(err) if err.kind() == std::io::ErrorKind::AlreadyExists => {
+                    match LockFile::read(root) {
+                        Some(owner) if !owner.is_stale() => {
+                            return Err(LinknotesError::WorkspaceLocked {
+                                pid: Some(owner.pid),
+                            });
+                        }
+                        Some(owner) => {
+                            tracing::warn!("Taking over stale workspace lock {:?}", owner);
+                        }
+                        None => match is_unreadable_lock_stale(&lock_path) {
+                            // The lock was removed after we tried to create it. Try again
+                            None => continue,
+                            Some(false) => {
+                                return Err(LinknotesError::WorkspaceLocked { pid: None });
+                            }
+                            Some(true) => {
+                                tracing::warn!("Taking over a workspace lock that can't be read")
+                            }
+                        },
+                    }
+                    match std::fs::remove_file(&lock_path) {
+                        Err(err) if err.kind() != std::io::ErrorKind::NotFound => {
+                            return Err(err.into())
+                        }
+                        _ => {}
+                    }
+                }
+                Err(err) => return Err(err.into()),
+            }
+        }
+
+        // Keep the heartbeat fresh until the lock is dropped
+        let (stop_heartbeat, stopped) = channel();
+        let heartbeat_root = root.to_path_buf();
+        let heartbeat = std::thread::spawn(move || loop {
+            match stopped.recv_timeout(HEARTBEAT_INTERVAL) {
+                Err(RecvTimeoutError::Timeout) => {
+                    // The workspace may have been deleted, or another process may have taken over the lock while this
+                    // process was suspended. Don't recreate or overwrite the lock in either case
+                    let owner = LockFile::read(&heartbeat_root);
+                    if owner.is_some_and(|owner| owner.pid == std::process::id()) {
+                        _ = LockFile::current().write(&heartbeat_root);
+                    }
+                }
+                _ => break,
+            }
+        });
+
+        Ok(Self {
+            root: root.to_path_buf(),
+            stop_heartbeat: Some(stop_heartbeat),
+            heartbeat: Some(heartbeat),
+        })
+    }
+}
+
+impl Drop for WorkspaceLock {
+    fn drop(&mut self) {
+        // Dropping the sender stops the heartbeat thread
+        self.stop_heartbeat.take();
+        if let Some(heartbeat) = self.heartbeat.take() {
+            _ = heartbeat.join();
+        }
+        // Only remove the lock if another process didn't take it over
+        if LockFile::read(&self.root).is_some_and(|owner| owner.pid == std::process::id()) {
+            _ = std::fs::remove_file(self.root.join(LOCK_FILE_NAME));
+        }
+    }
+}
+
+#[test]
+fn test_workspace_lock() {
+    let root = std::env::temp_dir().join("testing-workspace-lock");
+    _ = std::fs::remove_dir_all(&root);
+
+    // A lock from another live process can't be taken
+    let other_process = LockFile {
+        pid: std::process::id().wrapping_add(1),
+        heartbeat: now(),
+    };
+    std::fs::create_dir_all(&root).unwrap();
+    other_process.write(&root).unwrap();
+    assert_eq!(
+        WorkspaceLock::acquire(&root).err(),
+        Some(LinknotesError::WorkspaceLocked {
+            pid: Some(other_process.pid)
+        })
+    );
+
+    // A lock that stopped getting heartbeats is taken over
+    LockFile {
+        heartbeat: now() - 2 * STALE_LOCK_AGE.as_millis() as u64,
+        ..other_process
+    }
+    .write(&root)
+    .unwrap();
+    let lock = WorkspaceLock::acquire(&root).unwrap();
+    assert_eq!(LockFile::read(&root).unwrap().pid, std::process::id());
+
+    // Dropping the lock removes the lock file
+    drop(lock);
+    assert!(!root.join(LOCK_FILE_NAME).exists());
+
+    // An empty lock file may still be written by another process, so it isn't taken over until it is old
+    std::fs::write(root.join(LOCK_FILE_NAME), "").unwrap();
+    assert_eq!(
+        WorkspaceLock::acquire(&root).err(),
+        Some(LinknotesError::WorkspaceLocked { pid: None })
+    );
+    std::fs::File::options()
+        .write(true)
+        .open(root.join(LOCK_FILE_NAME))
+        .unwrap()
+        .set_modified(SystemTime::now() - 2 * STALE_LOCK_AGE)
+        .unwrap();
+    let lock = WorkspaceLock::acquire(&root).unwrap();
+    assert_eq!(LockFile::read(&root).unwrap().pid, std::process::id());
+    drop(lock);
+
+    // Only the lock file is left behind while the lock is held, not the temporary file it was written to
+    let lock = WorkspaceLock::acquire(&root).unwrap();
+    let files: Vec<_> = std::fs::read_dir(&root)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name())
+        .collect();
+    assert_eq!(files, vec![std::ffi::OsString::from(LOCK_FILE_NAME)]);
+    drop(lock);
+
+    _ = std::fs::remove_dir_all(&root);
+}
@@ -0,0 +1,376 @@
+/*!
+# Prologue Comments
+## Name of Code Artifact: Workspace Manifest
+
+## Brief Description: This code keeps a `workspace.toml` manifest in the workspace root. The manifest holds the schema version of the workspace, when it was created, the model source it embeds notes with, the embedding model it was indexed with and the chunker settings. When the notes of a workspace are opened, migrations upgrade workspaces with an older schema version, and workspaces created by a newer version of the app are refused instead of being silently broken.
+## Programmer’s Name: agent
+
+## Date Created: 2026-10-17
+## Dates Revised and Description of Revisions:
+## -> 2026-10-17: Added the workspace manifest and the migrations from the layout without a manifest.
+## -> 2026-10-17: Recorded the model source of the workspace. New workspaces record the source configured for the app.
+## -> 2026-10-17: Registered the note id, segment hash and creation time migrations of the database as schema versions 2 to 4.
+## -> 2026-10-17: Added schema version 5, which stores the links of notes saved before the link graph existed.
+## -> 2026-10-17: Added schema version 6, which stores the lowercase file name of every note.
+
+## Preconditions:
+- The workspace root must be writable to create or migrate the manifest.
+
+## Acceptable and Unacceptable Input Values/Types:
+- A workspace without a manifest is treated as schema version 0, the layout before manifests existed.
+- A manifest with a schema version newer than `SCHEMA_VERSION` is rejected with `LinknotesError::WorkspaceTooNew`.
+- A manifest that can't be parsed is rejected with `LinknotesError::Io`.
+
+## Postconditions:
+
+- After a writable workspace is loaded, its manifest exists. After its notes are opened, it has the current schema version.
+
+## Return Values/Types:
+
+- `get_workspace_manifest` returns the `WorkspaceManifest` of a workspace, `None` if a read-only workspace doesn't have one yet, or a `LinknotesError` on failure.
+
+## Side Effects:
+-  A `workspace.toml` file is written to the workspace root when a workspace is loaded.
+-  Migrations rewrite the records in the database and the metadata sidecar of the workspace.
+
+## Invariants:
+
+- Migrations run in order and the schema version is written after every migration, so an interrupted upgrade continues where it stopped.
+
+## Known Faults:
+
+- Only the snapshot of a workspace opened in read-only mode is migrated, so the migrations run again every time it is opened.
+
+*/
+
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+#[cfg(test)]
+use pretty_assertions::assert_eq;
+
+use crate::embedding_migration::EmbeddingModelInfo;
+use crate::error::LinknotesError;
+use crate::links::backfill_links;
+use crate::model::ModelSource;
+use crate::note::{
+    backfill_created_at, migrate_file_keys, migrate_note_ids, migrate_segment_hashes,
+};
+use crate::workspace::{get_workspace_ref, Workspace, WorkspaceId};
+use crate::ContextualDocumentTable;
+
+/// The name of the manifest in the workspace root
+pub(crate) const MANIFEST_FILE_NAME: &str = "workspace.toml";
+/// The schema version of workspaces this version of the app creates. Version 0 is a workspace from before manifests
+/// existed and version 1 is the first version with a manifest. Every later version has a migration in [`MIGRATIONS`]
+pub(crate) const SCHEMA_VERSION: u32 = 6;
+/// Files and directories that only exist in a workspace that was already used
+const WORKSPACE_CONTENTS: &[&str] = &["notes", "notes.db", "documents", "notes-metadata.json"];
+
+/// Only one update to a manifest runs at a time so concurrent updates don't lose each other's changes
+static MANIFEST_LOCK: parking_lot::Mutex<()> = parking_lot::Mutex::new(());
+
+/// How notes are split into the chunks that are embedded.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChunkerKind {
+    /// Notes are split into sentences
+    #[default]
+    Sentence,
+}
+
+/// The chunker settings a workspace was indexed with.
+///
+/// # Fields
+///
+/// * `kind` - How notes are split into chunks.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ChunkerSettings {
+    pub kind: ChunkerKind,
+}
+
+/// The contents of `workspace.toml`.
+///
+/// # Fields
+///
+/// * `schema_version` - The version of the layout of the workspace. See [`SCHEMA_VERSION`].
+/// * `created_at` - When the workspace was created in milliseconds since the unix epoch.
+/// * `embedding_model` - The embedding model the workspace was indexed with, or `None` until it is first indexed.
+/// * `model_source` - Where the model the workspace embeds notes with is loaded from, or `None` to use the source
+///   configured for the app.
+/// * `chunker` - The chunker settings the workspace was indexed with.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WorkspaceManifest {
+    pub schema_version: u32,
+    pub created_at: u64,
+    #[serde(default)]
+    pub chunker: ChunkerSettings,
+    #[serde(default)]
+    pub embedding_model: Option<EmbeddingModelInfo>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model_source: Option<ModelSource>,
+}
+
+impl WorkspaceManifest {
+    /// The manifest of a workspace created now with a schema version
+    fn new(schema_version: u32) -> Self {
+        Self {
+            schema_version,
+            created_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|duration| duration.as_millis() as u64)
+                .unwrap_or_default(),
+            chunker: ChunkerSettings::default(),
+            embedding_model: None,
+            model_source: None,
+        }
+    }
+}
+
+/// Only the schema version of a manifest. Manifests are checked with this first so a manifest from a newer version of
+/// the app is reported as too new instead of failing to parse
+#[derive(Deserialize)]
+struct ManifestVersion {
+    schema_version: u32,
+}
+
+/// A step that upgrades a workspace from the schema version before `to` to `to`.
+struct Migration {
+    to: u32,
+    description: &'static str,
+    step: MigrationStep,
+}
+
+/// The changes a migration makes to the database of a workspace
+#[derive(Debug, Clone, Copy)]
+enum MigrationStep {
+    /// Give every note saved before notes had ids a stable id. See [`migrate_note_ids`]
+    NoteIds,
+    /// Hash the text of every segment saved before segments had hashes. See [`migrate_segment_hashes`]
+    SegmentHashes,
+    /// Record when every note saved before creation times existed was created. See [`backfill_created_at`]
+    CreatedAt,
+    /// Store the links of every note saved before the link graph existed. See [`backfill_links`]
+    Links,
+    /// Store the lowercase file name of every note saved before location records had one. See [`migrate_file_keys`]
+    FileKeys,
+}
+
+/// Every migration in order. Add a migration here and bump [`SCHEMA_VERSION`] whenever the layout of a workspace, the
+/// stored documents or the table names change.
+const MIGRATIONS: &[Migration] = &[
+    Migration {
+        to: 2,
+        description: "give every note a stable id",
+        step: MigrationStep::NoteIds,
+    },
+    Migration {
+        to: 3,
+        description: "hash the text of every stored segment",
+        step: MigrationStep::SegmentHashes,
+    },
+    Migration {
+        to: 4,
+        description: "record when every note was created",
+        step: MigrationStep::CreatedAt,
+    },
+    Migration {
+        to: 5,
+        description: "store the links of every note",
+        step: MigrationStep::Links,
+    },
+    Migration {
+        to: 6,
+        description: "store the lowercase file name of every note",
+        step: MigrationStep::FileKeys,
+    },
+];
+
+/// Read the manifest in a workspace root. Returns `None` if the workspace doesn't have a manifest.
+///
+/// # Errors
+///
+/// This function will return an error if:
+/// - The manifest was written by a newer version of the app
+/// - The manifest can't be read or parsed
+pub(crate) fn read_manifest(root: &Path) -> Result<Option<WorkspaceManifest>, LinknotesError> {
+    let contents = match std::fs::read_to_string(root.join(MANIFEST_FILE_NAME)) {
+        Ok(contents) => contents,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(err) => return Err(err.into()),
+    };
+    let invalid = |err: toml::de::Error| {
+        LinknotesError::Io(format!("{MANIFEST_FILE_NAME} is invalid: {err}"))
+    };
+    let version: ManifestVersion = toml::from_str(&contents).map_err(invalid)?;
+    if version.schema_version > SCHEMA_VERSION {
+        return Err(LinknotesError::WorkspaceTooNew {
+            version: version.schema_version,
+            supported: SCHEMA_VERSION,
+        });
+    }
+    toml::from_str(&contents).map(Some).map_err(invalid)
+}
+
+/// Write the manifest in a workspace root
+fn write_manifest(root: &Path, manifest: &WorkspaceManifest) -> Result<(), LinknotesError> {
+    let contents =
+        toml::to_string_pretty(manifest).map_err(|err| LinknotesError::Io(err.to_string()))?;
+    // Write to a temporary file first so a crash never leaves a half written manifest
+    let temp = root.join(format!("{MANIFEST_FILE_NAME}.tmp"));
+    std::fs::write(&temp, contents)?;
+    std::fs::rename(&temp, root.join(MANIFEST_FILE_NAME))?;
+    Ok(())
+}
+
+/// Change the manifest in a workspace root. The manifest is only written if `update` changed it
+pub(crate) fn update_manifest(
+    root: &Path,
+    update: impl FnOnce(&mut WorkspaceManifest),
+) -> Result<(), LinknotesError> {
+    let _guard = MANIFEST_LOCK.lock();
+    let mut manifest =
+        read_manifest(root)?.unwrap_or_else(|| WorkspaceManifest::new(SCHEMA_VERSION));
+    let before = manifest.clone();
+    update(&mut manifest);
+    if manifest == before {
+        return Ok(());
+    }
+    write_manifest(root, &manifest)
+}
+
+/// Read the manifest of a workspace that is being loaded, creating it if the workspace doesn't have one. Workspaces
+/// with notes but no manifest get schema version 0 so [`migrate_workspace`] upgrades them. Read-only workspaces are
+/// checked but never changed.
+///
+/// # Errors
+///
+/// This function will return an error if:
+/// - The workspace was created by a newer version of the app
+/// - The manifest can't be read, parsed or written
+pub(crate) fn open_manifest(
+    root: &Path,
+    read_only: bool,
+) -> Result<WorkspaceManifest, LinknotesError> {
+    let _guard = MANIFEST_LOCK.lock();
+    let manifest = match read_manifest(root)? {
+        Some(manifest) => manifest,
+        None => {
+            // A workspace with notes but no manifest was created before manifests existed
+            let legacy = WORKSPACE_CONTENTS
+                .iter()
+                .any(|name| root.join(name).exists());
+            let mut manifest = WorkspaceManifest::new(if legacy { 0 } else { SCHEMA_VERSION });
+            // New workspaces keep the model they were created with even if the app is configured with another one later
+            if !legacy {
+                manifest.model_source = ModelSource::from_env().ok();
+            }
+            manifest
+        }
+    };
+    if !read_only && !root.join(MANIFEST_FILE_NAME).exists() {
+        write_manifest(root, &manifest)?;
+    }
+    Ok(manifest)
+}
+
+/// Run every migration newer than the schema version of a workspace on its database. The schema version in the
+/// manifest of a writable workspace is updated after every migration, so an interrupted upgrade continues where it
+/// stopped the next time the workspace is opened. Read-only workspaces only migrate their snapshot.
+///
+/// # Errors
+///
+/// This function will return an error if:
+/// - The manifest can't be read, parsed or written
+/// - A migration fails
+pub(crate) async fn migrate_workspace(
+    document_table: &ContextualDocumentTable,
+    workspace: &Workspace,
+) -> Result<(), LinknotesError> {
+    let root = &workspace.location;
+    let schema_version = open_manifest(root, workspace.is_read_only())?.schema_version;
+    for migration in MIGRATIONS {
+        if migration.to <= schema_version {
+            continue;
+        }
+        tracing::info!(
+            "Migrating workspace {:?} to schema version {}: {}",
+            root,
+            migration.to,
+            migration.description
+        );
+        match migration.step {
+            MigrationStep::NoteIds => migrate_note_ids(document_table).await?,
+            MigrationStep::SegmentHashes => migrate_segment_hashes(document_table).await?,
+            MigrationStep::CreatedAt => backfill_created_at(document_table, workspace).await?,
+            MigrationStep::Links => backfill_links(document_table).await?,
+            MigrationStep::FileKeys => migrate_file_keys(document_table).await?,
+        }
+        if !workspace.is_read_only() {
+            update_manifest(root, |manifest| manifest.schema_version = migration.to)?;
+        }
+    }
+    Ok(())
+}
+
+/// Get the manifest of a workspace with its schema version, creation time, model source, embedding model and chunker
+/// settings. Returns `None` if the workspace was opened read-only and doesn't have a manifest yet.
+///
+/// # Errors
+///
+/// This function will return an error if:
+/// - The workspace is not loaded
+/// - The manifest can't be read or parsed
+#[tauri::command]
+pub fn get_workspace_manifest(
+    workspace_id: WorkspaceId,
+) -> Result<Option<WorkspaceManifest>, LinknotesError> {
+    tracing::info!("get_workspace_manifest called with id: {:?}", workspace_id);
+    let root = get_workspace_ref(workspace_id)?.location.clone();
+    read_manifest(&root)
+}
+
+#[test]
+fn test_workspace_manifest() {
+    let root = std::env::temp_dir().join("testing-workspace-manifest");
+    _ = std::fs::remove_dir_all(&root);
+    std::fs::create_dir_all(&root).unwrap();
+
+    // New workspaces get the current schema version
+    let manifest = open_manifest(&root, false).unwrap();
+    assert_eq!(manifest.schema_version, SCHEMA_VERSION);
+    assert_eq!(manifest.chunker.kind, ChunkerKind::Sentence);
+    assert_eq!(read_manifest(&root).unwrap(), Some(manifest.clone()));
+    // Opening the workspace again keeps the creation time
+    assert_eq!(open_manifest(&root, false).unwrap(), manifest);
+
+    // Workspaces from a newer version of the app are refused
+    std::fs::write(
+        root.join(MANIFEST_FILE_NAME),
+        "schema_version = 99\nsome_new_field = true\n",
+    )
+    .unwrap();
+    assert_eq!(
+        open_manifest(&root, false),
+        Err(LinknotesError::WorkspaceTooNew {
+            version: 99,
+            supported: SCHEMA_VERSION
+        })
+    );
+    assert!(open_manifest(&root, true).is_err());
+
+    // Workspaces with notes but no manifest start at schema version 0 so every migration runs
+    std::fs::remove_file(root.join(MANIFEST_FILE_NAME)).unwrap();
+    std::fs::create_dir_all(root.join("notes")).unwrap();
+    // Read-only workspaces are left alone
+    assert_eq!(open_manifest(&root, true).unwrap().schema_version, 0);
+    assert!(!root.join(MANIFEST_FILE_NAME).exists());
+
+    let manifest = open_manifest(&root, false).unwrap();
+    assert_eq!(manifest.schema_version, 0);
+    assert_eq!(manifest.model_source, None);
+    assert_eq!(read_manifest(&root).unwrap(), Some(manifest));
+
+    _ = std::fs::remove_dir_all(&root);
+}
@@ -0,0 +1,450 @@
+/*!
+# Prologue Comments
+## Name of Code Artifact: Unlinked Mention Detection
+
+## Brief Description: This code finds places in other notes that talk about a note without linking to it. It finds plain text mentions of the note title or aliases (exact and fuzzy matches), and sentences that are very close to the note in embedding space. Each candidate can be turned into a `[[link]]` by the frontend.
+## Programmer’s Name: agent
+
+## Date Created: 2026-10-17
+## Dates Revised and Description of Revisions:
+## -> 2026-10-17: Added text and embedding based mention detection.
+## -> 2026-10-17: Searched notes that are waiting to be indexed with their queued text instead of waiting for the queue.
+## -> 2026-10-17: Skipped notes that already link to the note and returned each mention only once.
+
+## Preconditions:
+- Workspaces must be loaded before any mention related operations are called.
+- The note must be saved before mentions of it can be found.
+
+## Acceptable and Unacceptable Input Values/Types:
+- Each tauri command takes an object with types defined by the type definition in the code. Arguments are represented as
+fields in that object.
+- Empty aliases are ignored.
+
+## Postconditions:
+
+- Candidates never come from the note itself or from notes that already link to it.
+- No two candidates have the same note and character range.
+
+## Return Values/Types:
+
+- Functions return Result types with success yielding the candidates and an error message as a `String` on failure.
+
+## Side Effects:
+-  None. The workspace is only read.
+
+## Invariants:
+
+- Exact mentions have a distance of 0. Fuzzy mentions have the normalized edit distance. Semantic mentions have the embedding distance.
+
+## Known Faults:
+
+- Fuzzy matching works on whole words, so a title that is split or joined differently in the other note is not found.
+
+*/
+
+use kalosm::language::*;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::ops::Range;
+use surrealdb::sql::Id;
+
+#[cfg(test)]
+use pretty_assertions::assert_eq;
+
+use crate::classifier::chunk_text;
+use crate::error::LinknotesError;
+use crate::links::{links_to, parse_links, NoteLink};
+use crate::note::NoteId;
+use crate::search::MetaId;
+use crate::workspace::{get_workspace_ref, WorkspaceId};
+
+/// The largest normalized edit distance between a title and some text that still counts as a fuzzy mention
+const MAX_FUZZY_DISTANCE: f32 = 0.25;
+/// Titles shorter than this many characters are only matched exactly. Short fuzzy matches are almost always noise
+const MIN_FUZZY_LENGTH: usize = 4;
+
+/// How a mention was found
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum MentionKind {
+    /// The title or an alias appears in the text (ignoring case)
+    Exact,
+    /// Text that is a few edits away from the title or an alias appears in the text
+    Fuzzy,
+    /// A sentence that is very close to the note in embedding space
+    Semantic,
+}
+
+/// A place in another note that could be turned into a `[[link]]`.
+///
+/// # Fields
+///
+/// * `id` - The id of the note the mention was found in.
+/// * `title` - The title of the note the mention was found in.
+/// * `character_range` - The character range of the mention within that note.
+/// * `distance` - How far the mention is from the title or the note. Lower is better.
+/// * `kind` - How the mention was found.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MentionCandidate {
+    pub id: NoteId,
+    pub title: String,
+    pub character_range: Range<usize>,
+    pub distance: f32,
+    pub kind: MentionKind,
+}
+
+/// A word in some text with its character range
+struct Word {
+    text: String,
+    character_range: Range<usize>,
+}
+
+/// Split text into lowercase words with their character ranges
+fn words(text: &str) -> Vec<Word> {
+    let mut words = Vec::new();
+    let mut current: Option<Word> = None;
+    for (char_index, c) in text.chars().enumerate() {
+        if c.is_alphanumeric() {
+            let word = current.get_or_insert_with(|| Word {
+                text: String::new(),
+                character_range: char_index..char_index,
+            });
+            word.text.extend(c.to_lowercase());
+            word.character_range.end = char_index + 1;
+        } else if let Some(word) = current.take() {
+            words.push(word);
+        }
+    }
+    words.extend(current);
+    words
+}
+
+/// The number of single character insertions, deletions or substitutions it takes to turn one string into another
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous_row: Vec<usize> = (0..=b.len()).collect();
+    for (i, a_char) in a.chars().enumerate() {
+        let mut current_row = vec![i + 1; b.len() + 1];
+        for (j, b_char) in b.iter().enumerate() {
+            let substitution = previous_row[j] + usize::from(a_char != *b_char);
+            current_row[j + 1] = substitution
+                .min(previous_row[j + 1] + 1)
+                .min(current_row[j] + 1);
+        }
+        previous_row = current_row;
+    }
+    previous_row[b.len()]
+}
+
+/// Find plain text mentions of any of the names in some text. Mentions that overlap one of the `links` are skipped.
+///
+/// # Arguments
+///
+/// * `id` - The id of the note the text belongs to.
+/// * `title` - The title of the note the text belongs to.
+/// * `text` - The body of the note.
+/// * `names` - The title and aliases of the note we are looking for mentions of.
+/// * `links` - The links that already exist in the text.
+///
+/// # Returns
+///
+/// A list of candidates in the order they appear in the text. If multiple names match the same words, only the closest
+/// match is returned.
+fn find_text_mentions(
+    id: &NoteId,
+    title: &str,
+    text: &str,
+    names: &[String],
+    links: &[NoteLink],
+) -> Vec<MentionCandidate> {
+    let text_words = words(text);
+    let mut candidates: Vec<MentionCandidate> = Vec::new();
+    for name in names {
+        let name_words = words(name);
+        if name_words.is_empty() || name_words.len() > text_words.len() {
+            continue;
+        }
+        let name = name_words
+            .iter()
+            .map(|word| word.text.as_str())
+            .collect::<Vec<_>>()
+            .join(" ");
+        let name_length = name.chars().count();
+        for window in text_words.windows(name_words.len()) {
+            let character_range =
+                window[0].character_range.start..window[window.len() - 1].character_range.end;
+            let overlaps_link = links.iter().any(|link| {
+                link.character_range.start < character_range.end
+                    && character_range.start < link.character_range.end
+            });
+            if overlaps_link {
+                continue;
+            }
+            let window_text = window
+                .iter()
+                .map(|word| word.text.as_str())
+                .collect::<Vec<_>>()
+                .join(" ");
+            let (kind, distance) = if window_text == name {
+                (MentionKind::Exact, 0.)
+            } else if name_length >= MIN_FUZZY_LENGTH {
+                let distance = edit_distance(&window_text, &name) as f32 / name_length as f32;
+                if distance > MAX_FUZZY_DISTANCE {
+                    continue;
+                }
+                (MentionKind::Fuzzy, distance)
+            } else {
+                continue;
+            };
+            // If another name already matched these words, keep the closer match
+            match candidates
+                .iter_mut()
+                .find(|candidate| candidate.character_range == character_range)
+            {
+                Some(existing) if existing.distance <= distance => {}
+                Some(existing) => {
+                    existing.distance = distance;
+                    existing.kind = kind;
+                }
+                None => candidates.push(MentionCandidate {
+                    id: id.clone(),
+                    title: title.to_string(),
+                    character_range,
+                    distance,
+                    kind,
+                }),
+            }
+        }
+    }
+    candidates.sort_by_key(|candidate| candidate.character_range.start);
+    candidates
+}
+
+/// Find places in other notes that mention a note without linking to it.
+///
+/// Notes that already link to the note are skipped. In the other notes, this function looks for two kinds of mentions:
+/// - Plain text mentions of the title or one of the aliases. Both exact (ignoring case) and fuzzy matches are returned.
+/// - Sentences that are within `max_distance` of one of the sentences in the note in embedding space.
+///
+/// A range of a note that is found both ways is only returned as a text mention.
+///
+/// # Arguments
+///
+/// * `id` - The id of the note to find mentions of.
+/// * `aliases` - Other names the note goes by.
+/// * `max_distance` - The largest embedding distance a sentence can have to count as a semantic mention.
+/// * `results` - The number of nearest sentences to look at for each sentence in the note.
+/// * `workspace_id` - The ID of the workspace to search in.
+///
+/// # Returns
+///
+/// A `Result` containing the text mentions followed by the semantic mentions sorted by distance, or an error message as
+/// a `String` if the search fails.
+///
+/// # Errors
+///
+/// This function will return an error if:
+/// - The note is not saved in the workspace
+/// - The workspace does not exist
+/// - the query execution fails
+/// - if there is an issue downloading the bert model
+#[tauri::command]
+pub async fn find_unlinked_mentions(
+    id: NoteId,
+    aliases: Vec<String>,
+    max_distance: f32,
+    results: usize,
+    workspace_id: WorkspaceId,
+) -> Result<Vec<MentionCandidate>, LinknotesError> {
+    tracing::info!(
+        "find_unlinked_mentions called with id {} and aliases {:?}",
+        id,
+        aliases
+    );
+    let workspace = get_workspace_ref(workspace_id)?;
+    let document_table = workspace.document_table().await?;
+    let db = document_table.table().db();
+
+    // Notes that are waiting to be indexed are searched with the text they were last saved with
+    let notes = workspace.current_files().await?;
+    let note = notes
+        .iter()
+        .find(|note| note.note_id == id)
+        .ok_or(LinknotesError::NoteNotFound)?;
+    let title = note.document.title().to_string();
+
+    // Notes that already link to the note don't need another link
+    let mut linked_notes: Vec<NoteId> = links_to(db, &workspace.indexing(), &title)
+        .await?
+        .into_iter()
+        .map(|link| link.source)
+        .collect();
+    linked_notes.push(id.clone());
+    linked_notes.sort();
+    linked_notes.dedup();
+
+    // Find the plain text mentions in every other note
+    let names: Vec<String> = std::iter::once(title.clone())
+        .chain(aliases.into_iter().filter(|alias| !alias.trim().is_empty()))
+        .collect();
+    let mut candidates = Vec::new();
+    for other in notes
+        .iter()
+        .filter(|other| !linked_notes.contains(&other.note_id))
+    {
+        let other_title = other.document.title();
+        let other_body = other.document.body();
+        let links = parse_links(&other.note_id, other_body);
+        candidates.extend(find_text_mentions(
+            &other.note_id,
+            other_title,
+            other_body,
+            &names,
+            &links,
+        ));
+    }
+
+    // Then find sentences close to the note in the notes that don't link to it yet
+    let mut unlinked_document_ids = db
+        .query("SELECT meta::id(id) as id FROM type::table($table) WHERE note_id NOTINSIDE $linked")
+        .bind(("table", document_table.table().table().to_string()))
+        .bind(("linked", linked_notes))
+        .await?;
+    let unlinked_document_ids: Vec<MetaId> = unlinked_document_ids.take(0)?;
+    let unlinked_document_ids: Vec<Id> = unlinked_document_ids
+        .into_iter()
+        .map(|id| Id::String(id.id))
+        .collect();
+
+    if !unlinked_document_ids.is_empty() {
+        let body = note.document.body();
+        let sentences = chunk_text(body);
+        let embedder = workspace.embedder().await?;
+        let embeddings = embedder
+            .embed_batch(sentences.iter().map(|sentence| &body[sentence.clone()]))
+            .await
+            .map_err(LinknotesError::embedding_model)?;
+
+        // Keep the closest hit for every sentence in the other notes
+        let mut semantic: HashMap<(NoteId, Range<usize>), (String, f32)> = HashMap::new();
+        for embedding in embeddings {
+            let nearest = document_table
+                .search(embedding)
+                .with_results(results)
+                .with_filter(unlinked_document_ids.clone().into_iter())
+                .await
+                .map_err(LinknotesError::storage)?;
+            for result in nearest {
+                if result.distance > max_distance {
+                    continue;
+                }
+                let other_body = result.record.document.body();
+                let char_start = other_body[0..result.byte_range.start].chars().count();
+                let char_len = other_body[result.byte_range.clone()].chars().count();
+                let key = (
+                    result.record.note_id.clone(),
+                    char_start..char_start + char_len,
+                );
+                let (_, distance) = semantic
+                    .entry(key)
+                    .or_insert((result.record.document.title().to_string(), result.distance));
+                *distance = distance.min(result.distance);
+            }
+        }
+
+        let mut semantic: Vec<_> = semantic
+            .into_iter()
+            .map(
+                |((id, character_range), (title, distance))| MentionCandidate {
+                    id,
+                    title,
+                    character_range,
+                    distance,
+                    kind: MentionKind::Semantic,
+                },
+            )
+            .collect();
+        semantic.sort_by(|a, b| a.distance.total_cmp(&b.distance));
+        candidates.extend(semantic);
+    }
+
+    // Every candidate links the same note, so a mention is the same if it has the same note and range
+    let mut seen = HashSet::new();
+    candidates
+        .retain(|candidate| seen.insert((candidate.id.clone(), candidate.character_range.clone())));
+
+    Ok(candidates)
+}
+
+#[test]
+fn test_find_text_mentions() {
+    let text = "Linear algebra is used everywhere. See [[Linear Algebra]]. Liner algebra again, and linear-algebra.";
+    let id = NoteId::new();
+    let links = parse_links(&id, text);
+    let names = vec!["Linear Algebra".to_string(), "LA".to_string()];
+    let mentions = find_text_mentions(&id, "source", text, &names, &links);
+    assert_eq!(
+        mentions
+            .iter()
+            .map(|mention| (
+                text.chars()
+                    .skip(mention.character_range.start)
+                    .take(mention.character_range.len())
+                    .collect::<String>(),
+                mention.kind
+            ))
+            .collect::<Vec<_>>(),
+        vec![
+            ("Linear algebra".to_string(), MentionKind::Exact),
+            ("Liner algebra".to_string(), MentionKind::Fuzzy),
+            ("linear-algebra".to_string(), MentionKind::Exact),
+        ]
+    );
+    assert_eq!(edit_distance("kitten", "sitting"), 3);
+}
+
+#[tokio::test]
+async fn test_find_unlinked_mentions() {
+    use crate::embedder::NotesEmbedder;
+    use crate::indexing::wait_for_indexing;
+    use crate::note::save_note;
+    use crate::workspace::{delete_workspace, load_workspace_with, unload_workspace};
+
+    _ = tracing_subscriber::fmt::try_init();
+
+    let workspace_path = std::env::temp_dir().join("testing-unlinked-mentions");
+    _ = std::fs::remove_dir_all(&workspace_path);
+    let workspace =
+        load_workspace_with(workspace_path.clone(), Some(NotesEmbedder::hashing())).unwrap();
+    let mut ids = Vec::new();
+    for (title, text) in [
+        ("Integrals", "Integrals"),
+        // The sentence is both a text mention and the closest sentence to the note
+        ("Mentioning", "Integrals"),
+        // A note that already links to the note doesn't need another link
+        ("Linking", "Integrals are in [[Integrals]]."),
+    ] {
+        let id = save_note(None, title.to_string(), text.to_string(), workspace)
+            .await
+            .unwrap();
+        ids.push(id);
+    }
+    let [integrals, mentioning, _] = ids.try_into().unwrap();
+    wait_for_indexing(workspace).await.unwrap();
+
+    let mentions = find_unlinked_mentions(integrals, Vec::new(), f32::MAX, 10, workspace)
+        .await
+        .unwrap();
+    assert_eq!(
+        mentions,
+        vec![MentionCandidate {
+            id: mentioning,
+            title: "Mentioning".to_string(),
+            character_range: 0..9,
+            distance: 0.,
+            kind: MentionKind::Exact,
+        }]
+    );
+
+    delete_workspace(workspace).unwrap();
+    unload_workspace(workspace).unwrap();
+}
@@ -0,0 +1,162 @@
+/*!
+# Prologue Comments
+## Name of Code Artifact: Note Metadata Sidecar
+
+## Brief Description: This code keeps a `notes-metadata.json` file in the workspace root with the id, manual tags and creation time of every note, keyed by the file name of the note. The database can be rebuilt from the notes directory, but the ids, manual tags and creation times only exist in the database, so this file lets a rebuild keep them.
+## Programmer’s Name: agent
+
+## Date Created: 2026-10-17
+## Dates Revised and Description of Revisions:
+## -> 2026-10-17: Added the metadata sidecar for rebuilding the index.
+## -> 2026-10-17: Added the creation time of every note.
+
+## Preconditions:
+- The workspace root must be writable to update the metadata.
+
+## Acceptable and Unacceptable Input Values/Types:
+- A metadata file that doesn't exist or can't be parsed is treated as empty.
+
+## Postconditions:
+
+- After a note is saved, tagged, renamed or removed, the metadata file matches the database for that note.
+
+## Return Values/Types:
+
+- Updates return a `LinknotesError::Io` if the metadata file can't be written.
+
+## Side Effects:
+-  A `notes-metadata.json` file is written to the workspace root.
+
+## Invariants:
+
+- Only manual tags are stored. Automatic tags are recomputed by the classifier.
+
+## Known Faults:
+
+- Notes saved before the metadata file existed aren't in it until they are saved or tagged again.
+
+*/
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+#[cfg(test)]
+use pretty_assertions::assert_eq;
+
+use crate::error::LinknotesError;
+use crate::note::{NoteId, Tag};
+
+/// The name of the metadata file in the workspace root
+pub(crate) const METADATA_FILE_NAME: &str = "notes-metadata.json";
+
+/// Only one update to a metadata file runs at a time so concurrent saves don't lose each other's changes
+static METADATA_LOCK: parking_lot::Mutex<()> = parking_lot::Mutex::new(());
+
+/// The metadata of a note that isn't stored in the note file.
+///
+/// # Fields
+///
+/// * `id` - The stable id of the note.
+/// * `tags` - The tags the user added to the note.
+/// * `created_at` - When the note was created in milliseconds since the unix epoch, or `None` for notes stored before
+///   creation times were recorded.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) struct NoteMetadata {
+    pub(crate) id: NoteId,
+    #[serde(default)]
+    pub(crate) tags: Vec<Tag>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) created_at: Option<u64>,
+}
+
+/// The metadata of every note in a workspace keyed by the file name of the note
+pub(crate) type NotesMetadata = BTreeMap<String, NoteMetadata>;
+
+/// Read the metadata file in a workspace root. Returns an empty map if the file doesn't exist or can't be parsed
+pub(crate) fn read_metadata(root: &Path) -> NotesMetadata {
+    std::fs::read_to_string(root.join(METADATA_FILE_NAME))
+        .ok()
+        .and_then(|contents| serde_json::from_str(&contents).ok())
+        .unwrap_or_default()
+}
+
+/// Change the metadata file in a workspace root. The file is only written if `update` changed the metadata
+pub(crate) fn update_metadata(
+    root: &Path,
+    update: impl FnOnce(&mut NotesMetadata),
+) -> Result<(), LinknotesError> {
+    let _guard = METADATA_LOCK.lock();
+    let mut metadata = read_metadata(root);
+    let before = metadata.clone();
+    update(&mut metadata);
+    if metadata == before {
+        return Ok(());
+    }
+    let contents = serde_json::to_string_pretty(&metadata)
+        .map_err(|err| LinknotesError::Io(err.to_string()))?;
+    // Write to a temporary file first so a crash never leaves a half written metadata file
+    let temp = root.join(format!("{METADATA_FILE_NAME}.tmp"));
+    std::fs::write(&temp, contents)?;
+    std::fs::rename(&temp, root.join(METADATA_FILE_NAME))?;
+    Ok(())
+}
+
+/// The time a file was created in milliseconds since the unix epoch. Filesystems without creation times use the time the
+/// file was last changed, and the current time is used if the file can't be read.
+pub(crate) fn file_created_at(path: &Path) -> u64 {
+    let time = std::fs::metadata(path)
+        .and_then(|metadata| metadata.created().or_else(|_| metadata.modified()))
+        .unwrap_or_else(|_| SystemTime::now());
+    time.duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_millis() as u64)
+        .unwrap_or_default()
+}
+
+/// Get the key of a note file in the metadata
+pub(crate) fn metadata_key(path: &Path) -> Option<String> {
+    path.file_name()
+        .and_then(|name| name.to_str())
+        .map(str::to_string)
+}
+
+#[test]
+fn test_update_metadata() {
+    let root = std::env::temp_dir().join("testing-notes-metadata");
+    _ = std::fs::remove_dir_all(&root);
+    std::fs::create_dir_all(&root).unwrap();
+    assert!(read_metadata(&root).is_empty());
+
+    let id = NoteId::new();
+    let tags = vec![Tag {
+        name: "math".to_string(),
+        manual: true,
+    }];
+    update_metadata(&root, |metadata| {
+        metadata.insert(
+            "Integrals.txt".to_string(),
+            NoteMetadata {
+                id: id.clone(),
+                tags: tags.clone(),
+                created_at: Some(1_727_740_800_000),
+            },
+        );
+    })
+    .unwrap();
+    let metadata = read_metadata(&root);
+    assert_eq!(
+        metadata["Integrals.txt"],
+        NoteMetadata {
+            id,
+            tags,
+            created_at: Some(1_727_740_800_000)
+        }
+    );
+
+    // A file that can't be parsed is treated as empty
+    std::fs::write(root.join(METADATA_FILE_NAME), "not json").unwrap();
+    assert!(read_metadata(&root).is_empty());
+
+    _ = std::fs::remove_dir_all(&root);
+}
@@ -0,0 +1,295 @@
+/*!
+# Prologue Comments
+## Name of Code Artifact: Embedding Model Status
+
+## Brief Description: This code chooses where the BERT model is loaded from and tracks its state. The model is downloaded by default, or loaded from the safetensors, tokenizer and config files in `LINKNOTES_MODEL_DIR`. Each workspace records its model source in its manifest, and workspaces with the same source share one loaded model. While the model downloads and loads, a `model-loading` event reports the progress, and the final event reports if the model is ready or failed to load. The frontend can ask for the current state with `model_status` and start loading the model early with `warm_up`.
+## Programmer’s Name: agent
+
+## Date Created: 2026-10-17
+## Dates Revised and Description of Revisions:
+## -> 2026-10-17: Added the model_status and warm_up commands and the model-loading event.
+## -> 2026-10-17: Added loading the model from a local directory and offline mode.
+## -> 2026-10-17: Added the hashing embedder as a model source.
+## -> 2026-10-17: Stopped tests from switching to the hashing embedder. Tests pass it to their workspaces instead.
+## -> 2026-10-17: Made the model source serializable so workspaces can record it in their manifest.
+
+## Preconditions:
+- None
+
+## Acceptable and Unacceptable Input Values/Types:
+- The commands take no arguments.
+- `LINKNOTES_MODEL_DIR` must be a directory with `model.safetensors`, `tokenizer.json` and `config.json`.
+- `LINKNOTES_OFFLINE` turns on offline mode when it is set to anything other than an empty string, `0` or `false`.
+- `LINKNOTES_EMBEDDER` set to `hashing` uses the hashing embedder instead of BERT.
+
+## Postconditions:
+
+- After the model is loaded, `model_status` returns `Ready` or `Failed` for the rest of the process.
+
+## Return Values/Types:
+
+- `model_status` and `warm_up` return the current `ModelStatus`.
+
+## Side Effects:
+-  `warm_up` starts downloading and loading the model in the background. Nothing is downloaded in offline mode.
+-  A `model-loading` event is emitted every time the status changes.
+
+## Invariants:
+
+- The status only moves forward: `NotLoaded`, then `Downloading` or `Loading`, then `Ready` or `Failed`.
+
+## Known Faults:
+
+- A model that failed to load is not retried until the app restarts.
+- Local models are loaded with the pooling of snowflake-arctic-embed-small.
+
+*/
+
+use kalosm::language::{BertSource, FileSource, ModelLoadingProgress};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+#[cfg(test)]
+use pretty_assertions::assert_eq;
+
+use crate::embedder::{embedder, HASHING_DIMENSION};
+use crate::embedding_cache::text_hash;
+use crate::emit;
+use crate::error::LinknotesError;
+
+/// The event emitted every time the status of the model changes
+pub const MODEL_LOADING_EVENT: &str = "model-loading";
+
+/// The environment variable with the directory of a local embedding model
+const MODEL_DIR_VAR: &str = "LINKNOTES_MODEL_DIR";
+/// The environment variable that chooses between BERT and the hashing embedder
+const EMBEDDER_VAR: &str = "LINKNOTES_EMBEDDER";
+/// The environment variable that turns on offline mode
+const OFFLINE_VAR: &str = "LINKNOTES_OFFLINE";
+/// The id of the model that is downloaded when no local model is configured
+const DEFAULT_MODEL_ID: &str = "snowflake-arctic-embed-small";
+/// The weights, tokenizer and config a local model directory must contain
+const MODEL_FILES: [&str; 3] = ["model.safetensors", "tokenizer.json", "config.json"];
+
+/// Where the embedding model is loaded from. It serializes as `{ "kind": "local", "path": "/models/arctic" }`, or
+/// without a path for the other kinds.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", content = "path", rename_all = "lowercase")]
+pub enum ModelSource {
+    /// Download snowflake-arctic-embed-small from Hugging Face, or use the copy downloaded earlier
+    Download,
+    /// Load the model files in a local directory
+    Local(PathBuf),
+    /// Use the deterministic hashing embedder instead of BERT
+    Hashing,
+}
+
+impl ModelSource {
+    /// Read the model source from `LINKNOTES_EMBEDDER`, `LINKNOTES_MODEL_DIR` and `LINKNOTES_OFFLINE`.
+    pub(crate) fn from_env() -> Result<Self, LinknotesError> {
+        if let Ok("hashing") = std::env::var(EMBEDDER_VAR).as_deref().map(str::trim) {
+            return Ok(Self::Hashing);
+        }
+        Self::bert_from_env()
+    }
+
+    /// Read where the BERT model is loaded from with `LINKNOTES_MODEL_DIR` and `LINKNOTES_OFFLINE`. This ignores
+    /// `LINKNOTES_EMBEDDER`.
+    pub(crate) fn bert_from_env() -> Result<Self, LinknotesError> {
+        let model_dir = std::env::var_os(MODEL_DIR_VAR)
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from);
+        let offline = std::env::var(OFFLINE_VAR)
+            .is_ok_and(|value| !matches!(value.trim(), "" | "0" | "false"));
+        Self::resolve(model_dir, offline)
+    }
+
+    /// Choose the model source. A local directory must contain every model file. Without a local directory the
+    /// model is downloaded, unless offline mode is on.
+    pub(crate) fn resolve(
+        model_dir: Option<PathBuf>,
+        offline: bool,
+    ) -> Result<Self, LinknotesError> {
+        let Some(model_dir) = model_dir else {
+            if offline {
+                return Err(LinknotesError::embedding_model(format!(
+                    "offline mode is on, so the model can't be downloaded. Set {MODEL_DIR_VAR} to a directory with {}",
+                    MODEL_FILES.join(", ")
+                )));
+            }
+            return Ok(Self::Download);
+        };
+        let missing: Vec<&str> = MODEL_FILES
+            .into_iter()
+            .filter(|file| !model_dir.join(file).is_file())
+            .collect();
+        if !missing.is_empty() {
+            return Err(LinknotesError::embedding_model(format!(
+                "the model directory {} is missing {}",
+                model_dir.display(),
+                missing.join(", ")
+            )));
+        }
+        Ok(Self::Local(model_dir))
+    }
+
+    /// A stable id for the model. Embeddings from models with different ids are never mixed
+    pub(crate) fn id(&self) -> String {
+        match self {
+            Self::Download => DEFAULT_MODEL_ID.to_string(),
+            Self::Hashing => format!("hashing-{HASHING_DIMENSION}"),
+            Self::Local(dir) => {
+                let dir = std::fs::canonicalize(dir).unwrap_or_else(|_| dir.clone());
+                format!("local-{}", &text_hash(&dir.to_string_lossy())[..16])
+            }
+        }
+    }
+
+    /// The source kalosm loads the model from. The hashing embedder doesn't use BERT, so it gets the default source
+    pub(crate) fn bert_source(&self) -> BertSource {
+        let source = BertSource::snowflake_arctic_embed_small();
+        match self {
+            Self::Download | Self::Hashing => source,
+            Self::Local(dir) => {
+                let file = |name: &str| FileSource::Local(dir.join(name));
+                source
+                    .with_model(file("model.safetensors"))
+                    .with_tokenizer(file("tokenizer.json"))
+                    .with_config(file("config.json"))
+            }
+        }
+    }
+}
+
+/// The state of the embedding model.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "state")]
+pub enum ModelStatus {
+    /// Nothing has used the model yet
+    NotLoaded,
+    /// A file of the model is being downloaded. `progress` is between 0 and 1
+    Downloading { file: String, progress: f32 },
+    /// The model is being loaded into memory. `progress` is between 0 and 1
+    Loading { progress: f32 },
+    /// The model is ready to embed text
+    Ready,
+    /// The model could not be loaded
+    Failed { message: String },
+}
+
+static MODEL_STATUS: parking_lot::Mutex<ModelStatus> =
+    parking_lot::Mutex::new(ModelStatus::NotLoaded);
+
+/// Change the status of the model and tell the frontend. Progress updates are only sent when the progress changes
+/// by at least a percent so the frontend isn't flooded with events.
+pub(crate) fn set_model_status(status: ModelStatus) {
+    let mut current = MODEL_STATUS.lock();
+    // The file being loaded and the progress rounded down to a percent
+    let step = |status: &ModelStatus| match status {
+        ModelStatus::Downloading { file, progress } => {
+            Some((Some(file.clone()), (progress * 100.0) as u32))
+        }
+        ModelStatus::Loading { progress } => Some((None, (progress * 100.0) as u32)),
+        _ => None,
+    };
+    let same_step = step(&current).is_some() && step(&current) == step(&status);
+    if *current == status || same_step {
+        return;
+    }
+    *current = status.clone();
+    drop(current);
+    emit(MODEL_LOADING_EVENT, status);
+}
+
+/// Convert the loading progress reported by kalosm into a status
+pub(crate) fn loading_status(progress: ModelLoadingProgress) -> ModelStatus {
+    let fraction = progress.progress();
+    match progress {
+        ModelLoadingProgress::Downloading { source, .. } => ModelStatus::Downloading {
+            file: source,
+            progress: fraction,
+        },
+        ModelLoadingProgress::Loading { .. } => ModelStatus::Loading { progress: fraction },
+    }
+}
+
+/// Get the current state of the embedding model.
+#[tauri::command]
+pub fn model_status() -> ModelStatus {
+    MODEL_STATUS.lock().clone()
+}
+
+/// Start downloading and loading the embedding model configured for the app in the background so the first save or
+/// search doesn't wait for it. Progress is reported with the `model-loading` event. Calling this after the model started
+/// loading does nothing.
+#[tauri::command]
+pub fn warm_up() -> ModelStatus {
+    tracing::info!("warm_up called");
+    if model_status() == ModelStatus::NotLoaded {
+        tauri::async_runtime::spawn(async {
+            let embedder = match ModelSource::from_env() {
+                Ok(source) => embedder(&source).await,
+                Err(err) => Err(err.into()),
+            };
+            if let Err(err) = embedder {
+                tracing::error!("Failed to load the embedding model: {}", err);
+            }
+        });
+    }
+    model_status()
+}
+
+#[test]
+fn test_model_source() {
+    let model_dir = std::env::temp_dir().join("testing-local-model");
+    _ = std::fs::remove_dir_all(&model_dir);
+    std::fs::create_dir_all(&model_dir).unwrap();
+
+    assert_eq!(ModelSource::resolve(None, false), Ok(ModelSource::Download));
+    // Offline mode never downloads the model
+    assert!(matches!(
+        ModelSource::resolve(None, true),
+        Err(LinknotesError::EmbeddingModelUnavailable(_))
+    ));
+    // Every file must be in the model directory
+    std::fs::write(model_dir.join("config.json"), "{}").unwrap();
+    let Err(LinknotesError::EmbeddingModelUnavailable(message)) =
+        ModelSource::resolve(Some(model_dir.clone()), true)
+    else {
+        panic!("a model directory without weights was accepted");
+    };
+    assert!(message.contains("model.safetensors, tokenizer.json"));
+
+    std::fs::write(model_dir.join("model.safetensors"), "").unwrap();
+    std::fs::write(model_dir.join("tokenizer.json"), "{}").unwrap();
+    let source = ModelSource::resolve(Some(model_dir.clone()), true).unwrap();
+    assert_eq!(source, ModelSource::Local(model_dir.clone()));
+    assert_ne!(source.id(), ModelSource::Download.id());
+
+    // Sources are stored in workspace manifests
+    let json = serde_json::to_value(&source).unwrap();
+    assert_eq!(json["kind"], "local");
+    assert_eq!(serde_json::from_value::<ModelSource>(json).unwrap(), source);
+    assert_eq!(
+        serde_json::to_value(ModelSource::Hashing).unwrap(),
+        serde_json::json!({ "kind": "hashing" })
+    );
+
+    _ = std::fs::remove_dir_all(&model_dir);
+}
+
+#[tokio::test]
+async fn test_model_status() {
+    let json = serde_json::to_value(ModelStatus::Downloading {
+        file: "model.safetensors".to_string(),
+        progress: 0.5,
+    })
+    .unwrap();
+    assert_eq!(json["state"], "Downloading");
+    assert_eq!(json["progress"], 0.5);
+
+    // Warming up a model that is already loaded does nothing
+    set_model_status(ModelStatus::Ready);
+    assert_eq!(model_status(), ModelStatus::Ready);
+    assert_eq!(warm_up(), ModelStatus::Ready);
+}
@@ -12,6 +12,19 @@ This code manages contextual documents within a Tauri-based workspace. It includ
 ## Dates the Code Was Revised:
 December 10, 2024: Fixed document retrieval bug in get_tags. (Author: Evan)
 December 12, 2024: Enhanced exception handling in save_note. (Author: Evan)
+October 17, 2026: Split embedding out of index_note so notes can be embedded in batches, and kept ids and manual tags in the metadata sidecar.
+October 17, 2026: Reused the stored embeddings of sentences that didn't change when a note is saved.
+October 17, 2026: Moved embedding saved notes to the background indexing queue.
+October 17, 2026: Exposed the stored sentence embeddings of notes for diversifying search results.
+October 17, 2026: Stored the file name of notes instead of the absolute path so moved and copied workspaces keep working.
+October 17, 2026: Stopped storing a copy of every sentence embedding in the location record. Unchanged sentences are read from the embedding cache.
+October 17, 2026: Returned the notes whose links were rewritten by rename_note.
+October 17, 2026: Read notes that are waiting to be indexed from the indexing queue instead of waiting for the queue.
+October 17, 2026: Reused the embeddings the document table stores for unchanged sentences instead of embedding every sentence again.
+October 17, 2026: Let notes share a title. Saving without an id always creates a new note, and notes whose title is already used are stored in a file with their id.
+October 17, 2026: Stored the creation time of every note in the document and the metadata sidecar, and backfilled it for notes stored before it existed.
+October 17, 2026: Hashed the segments stored before segments had hashes in a migration.
+October 17, 2026: Stored the lowercase file name of every note so colliding file names are found with an index instead of reading every location record.
 
 ## Preconditions:
 
@@ -21,7 +34,7 @@ December 12, 2024: Enhanced exception handling in save_note. (Author: Evan)
 
 ### Acceptable and Unacceptable Input Values or Types:
 
-- Title (String): Non-empty, unique title for each document.
+- Title (String): Non-empty title. Several notes can share a title.
 - Tags (Vec<Tag>): Each tag must have a valid name and manual flag which should be true if it was created by the user.
 - Text (String): Any non-empty string.
 - Workspace ID (WorkspaceId): A workspace that was created by [`crate::workspace::load_workspace`].
@@ -41,14 +54,14 @@ Classifier-based tagging is applied automatically.
 ## Return Values or Types, and Their Meanings:
 
 Ok(()): Operation succeeded without errors.
-Err(DocumentDoesNotExistError): Document with the specified title was not found.
-Result<Vec<Tag>, DocumentDoesNotExistError>: Returns a list of tags or indicates the document does not exist.
+Err(LinknotesError::NoteNotFound): Document with the specified id was not found.
+Result<Vec<Tag>, LinknotesError>: Returns a list of tags or indicates why they could not be read.
 
 ### Error and Exception Condition Values or Types That Can Occur, and Their Meanings:
 
-DocumentDoesNotExistError: Document lookup failed.
-String: Returned for general errors like database issues or classifier failures.
-I/O errors (std::io::Error): Issues with file operations.
+LinknotesError::NoteNotFound: Document lookup failed.
+LinknotesError::Storage / Classifier / EmbeddingModelUnavailable: Database issues, classifier failures or embedding failures.
+LinknotesError::Io: Issues with file operations.
 
 ## Side Effects:
 
@@ -65,41 +78,122 @@ Automatic tags are always appended to existing manual tags.
 ## Any Known Faults:
 
 Limited error recovery mechanisms for file I/O operations.
-Potential bottlenecks during embedding generation with BERT.
+Potential bottlenecks during embedding generation with BERT. Unchanged sentences keep the embeddings stored in the document table, but the whole document is still re-inserted on every change.
 */
 
 use kalosm::language::*;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::fs;
 use std::ops::Range;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use surrealdb::engine::local::Db;
 use surrealdb::sql::Id;
+use surrealdb::Surreal;
 
 #[cfg(test)]
 use pretty_assertions::assert_eq; // Import the `assert_eq` macro for comparing values.
 
-use crate::bert; // Import the `bert` function from the root module.
 use crate::classifier::chunk_text; // Import the `chunk_text` function from the `classifier` module.
-use crate::workspace::{get_workspace_ref, WorkspaceId}; // Import the `get_workspace_ref` and `WorkspaceId` types from the `workspace` module.
+use crate::embedder::NotesEmbedder; // Import the embedder notes are embedded with.
+use crate::embedding_cache::{embed_batch_cached, text_hash}; // Import the helpers that embed text through the on-disk embedding cache.
+use crate::error::LinknotesError;
+use crate::indexing::{enqueue_note, queued_document, IndexJob}; // Import the background indexing queue.
+use crate::links::{delete_outgoing_links, parse_links, update_links, DOCUMENT_LINK_TABLE}; // Import the functions that keep the link graph in sync with the notes.
+use crate::metadata::{
+    file_created_at, metadata_key, read_metadata, update_metadata, NoteMetadata,
+}; // Import the functions that keep the metadata sidecar in sync with the notes.
+use crate::workspace::{
+    get_workspace_ref, title_to_file_name, title_to_unique_file_name, Workspace, WorkspaceId,
+}; // Import the `get_workspace_ref` and `WorkspaceId` types from the `workspace` module.
+use crate::ContextualDocumentTable; // Import the document table type from the root module.
+
+/// A stable identifier for a note. Unlike the title, the id never changes after the note is created, so it is safe to
+/// keep around in the frontend while the note is renamed. Ids are ULIDs which sort by creation time.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct NoteId(String);
+
+impl NoteId {
+    /// Create a new unique note id
+    pub(crate) fn new() -> Self {
+        Self(ulid::Ulid::new().to_string())
+    }
+
+    /// Get the id as a string
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl std::fmt::Display for NoteId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
 
 #[derive(Serialize, Deserialize)] // Implement the `Serialize` and `Deserialize` traits for the `ContextualDocumentLocation` struct.
-struct ContextualDocumentLocation {
-    // Define the `ContextualDocumentLocation` struct.
-    document_id: Id,        // An `Id` field to store the document ID.
-    pub location: PathBuf,  // A `PathBuf` field to store the document location.
-    segments: Vec<Segment>, // A `Vec<Segment>` field to store the document segments.
+pub(crate) struct ContextualDocumentLocation {
+    // Define the `ContextualDocumentLocation` struct. The record id is the `NoteId` of the note.
+    pub(crate) title: String, // A `String` field to store the current title of the note. The file name is an escaped version of the title, so this is the source of truth for the title.
+    pub(crate) document_id: Id, // An `Id` field to store the document ID.
+    pub location: PathBuf, // The file name of the note in the notes directory. Use `path` to get the full path.
+    pub(crate) segments: Vec<Segment>, // A `Vec<Segment>` field to store the document segments.
+    #[serde(default)]
+    pub(crate) file_key: String, // The lowercase file name of the note. Notes with the same key would share a file on a case insensitive filesystem.
+}
+
+impl ContextualDocumentLocation {
+    /// Create the location record of a note stored in a file
+    pub(crate) fn new(
+        title: String,
+        document_id: Id,
+        location: PathBuf,
+        segments: Vec<Segment>,
+    ) -> Self {
+        Self {
+            file_key: lowercase_file_name(&location).unwrap_or_default(),
+            title,
+            document_id,
+            location,
+            segments,
+        }
+    }
+
+    /// Get the file the note is stored in. Only the file name is stored so the workspace keeps working after it is
+    /// moved or copied. Records written before that store an absolute path, so only the file name of that path is used.
+    pub(crate) fn path(&self, notes_dir: &Path) -> PathBuf {
+        notes_dir.join(
+            self.location
+                .file_name()
+                .unwrap_or(self.location.as_os_str()),
+        )
+    }
+}
+
+/// Get the lowercase file name of a path. Two files with the same lowercase name are the same file on case insensitive
+/// filesystems.
+fn lowercase_file_name(path: &Path) -> Option<String> {
+    path.file_name()
+        .map(|name| name.to_string_lossy().to_lowercase())
 }
 
 /// A struct representing a document with its associated tags.
 ///
 /// # Fields
 ///
+/// * `note_id` - The stable id of the note. This can't be called `id` because surrealdb reserves that field for the record id.
 /// * `document` - The main document content.
 /// * `tags` - A list of tags associated with the document.
+/// * `created_at` - When the note was created in milliseconds since the unix epoch.
 #[derive(Debug, PartialEq, Serialize, Deserialize)]
 pub struct ContextualDocument {
+    pub note_id: NoteId,
     pub document: Document,
     pub tags: Vec<Tag>,
+    // Documents stored before creation times existed are backfilled when the workspace is opened
+    #[serde(default)]
+    pub created_at: u64,
 }
 
 impl AsRef<Document> for ContextualDocument {
@@ -130,92 +224,528 @@ impl PartialOrd for Tag {
     }
 }
 
-const DOCUMENT_NAME_TABLE: &str = "document_paths";
+pub(crate) const DOCUMENT_NAME_TABLE: &str = "document_paths";
 
-#[derive(Serialize, Deserialize, PartialEq)] // Implement the `Serialize` and `Deserialize` traits for the `Segment` struct. These traits let us save and load the struct to and from a database.
-struct Segment {
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)] // Implement the `Serialize` and `Deserialize` traits for the `Segment` struct. These traits let us save and load the struct to and from a database.
+pub(crate) struct Segment {
     source_char_range: Range<usize>, // A `Range<usize>` field to store the character range of the segment.
+    #[serde(default)]
+    hash: Option<String>, // A hash of the text of the segment. Segments saved before hashes were added get one from `migrate_segment_hashes`.
+}
+
+/// Get the segments that are stored for the sentences of a note
+fn segments(body: &str, sentences: &[Range<usize>]) -> Vec<Segment> {
+    sentences
+        .iter()
+        .map(|byte_range| Segment {
+            source_char_range: byte_range.clone(),
+            hash: Some(text_hash(&body[byte_range.clone()])),
+        })
+        .collect()
+}
+
+/// Get the segments that would be stored if a note with this text was saved
+pub(crate) fn text_segments(text: &str) -> Vec<Segment> {
+    segments(text, &chunk_text(text))
+}
+
+/// A chunk of a document as the document table stores it. kalosm keeps the ids of the embeddings of each chunk in the
+/// `chunks` field of the document record, next to the fields of the `ContextualDocument`, and the embeddings themselves
+/// in the vector database of the table.
+#[derive(Deserialize)]
+struct StoredChunk {
+    byte_range: Range<usize>,
+    embedding_ids: Vec<EmbeddingId>,
+}
+
+/// Read the embeddings the document table stores for the chunks of a document, keyed by the byte range of the chunk.
+/// This is the only place that depends on how kalosm lays out the chunks of a document.
+///
+/// # Errors
+///
+/// Returns `LinknotesError::Storage` if the chunks or their embeddings can't be read.
+pub(crate) async fn stored_chunk_embeddings(
+    document_table: &ContextualDocumentTable,
+    document_id: &Id,
+) -> Result<HashMap<Range<usize>, Embedding<BertSpace>>, LinknotesError> {
+    let mut response = document_table
+        .table()
+        .db()
+        .query("SELECT VALUE chunks FROM type::thing($documents, $document_id)")
+        .bind(("documents", document_table.table().table().to_string()))
+        .bind(("document_id", document_id.clone()))
+        .await?;
+    let chunks: Option<Vec<StoredChunk>> = response.take(0)?;
+    let vector_db = document_table.table().vector_db();
+    let mut embeddings = HashMap::new();
+    for chunk in chunks.unwrap_or_default() {
+        // Notes are chunked into sentences with one embedding each
+        let Some(embedding_id) = chunk.embedding_ids.first() else {
+            continue;
+        };
+        let embedding = vector_db
+            .get_embedding(*embedding_id)
+            .map_err(LinknotesError::storage)?;
+        embeddings.insert(chunk.byte_range, embedding);
+    }
+    Ok(embeddings)
+}
+
+/// Find the location record of a note by its id
+async fn select_location(
+    db: &Surreal<Db>,
+    id: &NoteId,
+) -> Result<Option<ContextualDocumentLocation>, LinknotesError> {
+    Ok(db.select((DOCUMENT_NAME_TABLE, id.as_str())).await?)
+}
+
+/// Get every location record in the workspace along with the id of the note it belongs to
+pub(crate) async fn select_locations(
+    db: &Surreal<Db>,
+) -> Result<Vec<(NoteId, ContextualDocumentLocation)>, LinknotesError> {
+    #[derive(Deserialize)]
+    struct LocationRecord {
+        note_id: NoteId,
+        title: String,
+        document_id: Id,
+        location: PathBuf,
+        segments: Vec<Segment>,
+    }
+
+    let mut response = db
+        .query("SELECT meta::id(id) AS note_id, title, document_id, location, segments FROM type::table($paths)")
+        .bind(("paths", DOCUMENT_NAME_TABLE))
+        .await?;
+    let records: Vec<LocationRecord> = response.take(0)?;
+    Ok(records
+        .into_iter()
+        .map(|record| {
+            let location = ContextualDocumentLocation::new(
+                record.title,
+                record.document_id,
+                record.location,
+                record.segments,
+            );
+            (record.note_id, location)
+        })
+        .collect())
+}
+
+/// Find the ids of the notes with a title. Several notes can share a title, so the ids are sorted from the oldest
+/// note to the newest.
+pub(crate) async fn find_note_ids(
+    db: &Surreal<Db>,
+    title: &str,
+) -> Result<Vec<NoteId>, LinknotesError> {
+    let mut response = db
+        .query("SELECT VALUE meta::id(id) FROM type::table($paths) WHERE title = $title")
+        .bind(("paths", DOCUMENT_NAME_TABLE))
+        .bind(("title", title.to_string()))
+        .await?;
+    let ids: Vec<String> = response.take(0)?;
+    let mut ids: Vec<NoteId> = ids.into_iter().map(NoteId).collect();
+    ids.sort();
+    Ok(ids)
 }
 
-/// DocumentDoesNotExistError is an error type that indicates that a document does not exist.
-#[derive(thiserror::Error, Debug, Serialize, Deserialize)]
-#[error("document does not exist")]
-pub struct DocumentDoesNotExistError;
+/// Index the lowercase file names of the location records so notes whose files would collide are found without reading
+/// every record. The index is only created if it doesn't exist.
+pub(crate) async fn define_location_index(db: &Surreal<Db>) -> Result<(), LinknotesError> {
+    // Index definitions can't take the table as a parameter. The table name is a constant, not user input
+    db.query(format!(
+        "DEFINE INDEX IF NOT EXISTS note_file_key ON TABLE {DOCUMENT_NAME_TABLE} FIELDS file_key"
+    ))
+    .await?
+    .check()?;
+    Ok(())
+}
 
-// Set the tags for a note with the given a title in the workspace
+/// The id, title and file of a note
+#[derive(Deserialize)]
+struct NoteFile {
+    id: NoteId,
+    title: String,
+    location: PathBuf,
+}
+
+/// Get the id, title and file of every note in the workspace
+async fn note_files(db: &Surreal<Db>) -> Result<Vec<NoteFile>, LinknotesError> {
+    let mut response = db
+        .query("SELECT meta::id(id) AS id, title, location FROM type::table($paths)")
+        .bind(("paths", DOCUMENT_NAME_TABLE))
+        .await?;
+    Ok(response.take(0)?)
+}
+
+/// Find a note other than `id` that is stored in a file with the same name as `path` on a case insensitive filesystem.
+/// Returns the title of that note if it exists.
+async fn colliding_note(
+    db: &Surreal<Db>,
+    id: &NoteId,
+    path: &Path,
+) -> Result<Option<String>, LinknotesError> {
+    let Some(file_key) = lowercase_file_name(path) else {
+        return Ok(None);
+    };
+    let mut response = db
+        .query("SELECT VALUE title FROM type::table($paths) WHERE file_key = $file_key AND meta::id(id) != $id LIMIT 1")
+        .bind(("paths", DOCUMENT_NAME_TABLE))
+        .bind(("file_key", file_key))
+        .bind(("id", id.clone()))
+        .await?;
+    let titles: Vec<String> = response.take(0)?;
+    Ok(titles.into_iter().next())
+}
+
+/// Find the note that is stored in the file with the same name as `path`. Every note is stored directly in the notes
+/// directory, so only the file names are compared. That also finds notes whose record has an absolute path from before
+/// only file names were stored, or a path in the directory the workspace was moved from. Returns the id and title of
+/// the note if it exists.
+pub(crate) async fn find_note_by_file(
+    db: &Surreal<Db>,
+    path: &Path,
+) -> Result<Option<(NoteId, String)>, LinknotesError> {
+    let file_name = path.file_name();
+    Ok(note_files(db)
+        .await?
+        .into_iter()
+        .find(|file| file.location.file_name() == file_name)
+        .map(|file| (file.id, file.title)))
+}
+
+/// Find the current title of the note with an id if it exists
+pub(crate) async fn find_note_title(
+    db: &Surreal<Db>,
+    id: &NoteId,
+) -> Result<Option<String>, LinknotesError> {
+    Ok(select_location(db, id)
+        .await?
+        .map(|location| location.title))
+}
+
+/// A location record written before notes had ids. The record id of these records was the title of the note.
+#[derive(Deserialize)]
+struct LegacyDocumentLocation {
+    title: String,
+    document_id: Id,
+    location: PathBuf,
+    segments: Vec<Segment>,
+}
+
+/// Give every note in a workspace created before notes had ids a new `NoteId`. This moves the location record from
+/// the title to the new id, stores the id with the document and points the outgoing links at the id. Each note is
+/// migrated in its own transaction, so an interrupted migration continues where it left off the next time the
+/// workspace is opened.
+pub(crate) async fn migrate_note_ids(
+    document_table: &ContextualDocumentTable,
+) -> Result<(), LinknotesError> {
+    let db = document_table.table().db();
+    let mut legacy = db
+        .query("SELECT meta::id(id) AS title, document_id, location, segments FROM type::table($paths) WHERE title = NONE")
+        .bind(("paths", DOCUMENT_NAME_TABLE))
+        .await?;
+    let legacy: Vec<LegacyDocumentLocation> = legacy.take(0)?;
+    for legacy in legacy {
+        let note_id = NoteId::new();
+        tracing::info!("Migrating note {:?} to id {}", legacy.title, note_id);
+        let location = ContextualDocumentLocation::new(
+            legacy.title.clone(),
+            legacy.document_id.clone(),
+            legacy
+                .location
+                .file_name()
+                .map(PathBuf::from)
+                .unwrap_or(legacy.location),
+            legacy.segments,
+        );
+        db.query("BEGIN TRANSACTION")
+            .query("UPDATE type::thing($documents, $document_id) SET note_id = $note_id")
+            .query("CREATE type::thing($paths, $note_id) CONTENT $location")
+            .query("DELETE type::thing($paths, $title)")
+            .query("UPDATE type::table($links) SET source = $note_id WHERE source = $title")
+            .query("COMMIT TRANSACTION")
+            .bind(("documents", document_table.table().table().to_string()))
+            .bind(("document_id", legacy.document_id))
+            .bind(("paths", DOCUMENT_NAME_TABLE))
+            .bind(("links", DOCUMENT_LINK_TABLE))
+            .bind(("note_id", note_id))
+            .bind(("title", legacy.title))
+            .bind(("location", location))
+            .await?
+            .check()?;
+    }
+    Ok(())
+}
+
+/// Hash the text of every segment saved before segments had hashes, so the next save of the note reuses the embeddings
+/// of its unchanged sentences. The hash is computed from the body of the stored document, which the byte ranges of the
+/// segments point into.
+pub(crate) async fn migrate_segment_hashes(
+    document_table: &ContextualDocumentTable,
+) -> Result<(), LinknotesError> {
+    let db = document_table.table().db();
+    for (note_id, mut location) in select_locations(db).await? {
+        if location
+            .segments
+            .iter()
+            .all(|segment| segment.hash.is_some())
+        {
+            continue;
+        }
+        let note: ContextualDocument = document_table
+            .select(location.document_id.clone())
+            .await
+            .map_err(LinknotesError::storage)?;
+        let body = note.document.body();
+        for segment in &mut location.segments {
+            // A segment outside of the body can't be matched with a sentence, so it stays without a hash
+            segment.hash = body.get(segment.source_char_range.clone()).map(text_hash);
+        }
+        db.query("UPDATE type::thing($paths, $note_id) SET segments = $segments")
+            .bind(("paths", DOCUMENT_NAME_TABLE))
+            .bind(("note_id", note_id))
+            .bind(("segments", location.segments))
+            .await?
+            .check()?;
+    }
+    Ok(())
+}
+
+/// Store the lowercase file name of every location record saved before records had one, so [`colliding_note`] can find
+/// them.
+pub(crate) async fn migrate_file_keys(
+    document_table: &ContextualDocumentTable,
+) -> Result<(), LinknotesError> {
+    let db = document_table.table().db();
+    for (note_id, location) in select_locations(db).await? {
+        db.query("UPDATE type::thing($paths, $note_id) SET file_key = $file_key")
+            .bind(("paths", DOCUMENT_NAME_TABLE))
+            .bind(("note_id", note_id))
+            .bind(("file_key", location.file_key))
+            .await?
+            .check()?;
+    }
+    Ok(())
+}
+
+/// A document stored before notes recorded when they were created
+#[derive(Deserialize)]
+struct DocumentWithoutCreationTime {
+    id: String,
+    note_id: NoteId,
+}
+
+/// Give every note stored before creation times existed the creation time kept in the metadata sidecar, or the time its
+/// file was created. Filesystems without creation times use the time the file was last changed. The metadata sidecar of
+/// a writable workspace is updated too, so a rebuild keeps the time.
+pub(crate) async fn backfill_created_at(
+    document_table: &ContextualDocumentTable,
+    workspace: &Workspace,
+) -> Result<(), LinknotesError> {
+    let db = document_table.table().db();
+    let mut documents = db
+        .query("SELECT meta::id(id) AS id, note_id FROM type::table($documents) WHERE created_at = NONE")
+        .bind(("documents", document_table.table().table().to_string()))
+        .await?;
+    let documents: Vec<DocumentWithoutCreationTime> = documents.take(0)?;
+    if documents.is_empty() {
+        return Ok(());
+    }
+    tracing::info!("Backfilling the creation time of {} notes", documents.len());
+    let notes_dir = workspace.location.join("notes");
+    let metadata = read_metadata(&workspace.location);
+    let mut backfilled = Vec::new();
+    for document in documents {
+        // Documents without a location record are picked up by repair_workspace
+        let Some(location) = select_location(db, &document.note_id).await? else {
+            continue;
+        };
+        let key = metadata_key(&location.location);
+        let created_at = key
+            .as_ref()
+            .and_then(|key| metadata.get(key))
+            .filter(|metadata| metadata.id == document.note_id)
+            .and_then(|metadata| metadata.created_at)
+            .unwrap_or_else(|| file_created_at(&location.path(&notes_dir)));
+        db.query("UPDATE type::thing($documents, $id) SET created_at = $created_at")
+            .bind(("documents", document_table.table().table().to_string()))
+            .bind(("id", document.id))
+            .bind(("created_at", created_at))
+            .await?
+            .check()?;
+        if let Some(key) = key {
+            backfilled.push((key, document.note_id, created_at));
+        }
+    }
+    if workspace.is_read_only() {
+        return Ok(());
+    }
+    update_metadata(&workspace.location, |metadata| {
+        for (key, note_id, created_at) in backfilled {
+            if let Some(note) = metadata.get_mut(&key).filter(|note| note.id == note_id) {
+                note.created_at.get_or_insert(created_at);
+            }
+        }
+    })
+}
+
+/// Get the id of the note with a title. This is only kept for callers that only know the title of a note, like
+/// links. Several notes can share a title, so the oldest of them is returned. Keep the id [`save_note`] returns instead
+/// of looking the note up by title again.
+///
+/// # Errors
+///
+/// Returns `LinknotesError::NoteNotFound` if no note in the workspace has the title.
+#[tauri::command]
+pub async fn get_note_id(
+    title: String,
+    workspace_id: WorkspaceId,
+) -> Result<NoteId, LinknotesError> {
+    tracing::info!("get_note_id called with title {:?}", title);
+    let workspace = get_workspace_ref(workspace_id)?;
+    let document_table = workspace.document_table().await?;
+    let db = document_table.table().db();
+    // A note that is waiting to be indexed may not be in the database yet
+    let queued = workspace.indexing().find_by_title(&title);
+    find_note_ids(db, &title)
+        .await?
+        .into_iter()
+        .chain(queued)
+        .min()
+        .ok_or(LinknotesError::NoteNotFound)
+}
+
+// Set the tags for a note with the given id in the workspace
 #[tauri::command]
 pub async fn set_tags(
-    title: String,             // Define the `title` parameter as a `String`.
+    id: NoteId,                // Define the `id` parameter as a `NoteId`.
     mut tags: Vec<Tag>,        // Define the `tags` parameter as a mutable `Vec<Tag>`.
     workspace_id: WorkspaceId, // Define the `workspace_id` parameter as a `WorkspaceId`.
-) -> Result<(), DocumentDoesNotExistError> {
-    tracing::info!("set_tags called with title {:?} and tags {:?}", title, tags);
-    let workspace = get_workspace_ref(workspace_id); // Get a reference to the workspace using the `workspace_id`.
+) -> Result<(), LinknotesError> {
+    tracing::info!("set_tags called with id {} and tags {:?}", id, tags);
+    let workspace = get_workspace_ref(workspace_id)?; // Get a reference to the workspace using the `workspace_id`.
+    workspace.check_writable()?; // Read-only workspaces can't be changed.
     workspace.retrain_classifier(); // Retrain the classifier for the workspace.
-    let document_table = workspace.document_table().await.unwrap(); // Get the document table for the workspace.
+    let document_table = workspace.document_table().await?; // Get the document table for the workspace.
     let db = document_table.table().db(); // Get the database connection for the document table.
     let table_name = document_table.table().table(); // Get the table name for the document table.
-    let location: ContextualDocumentLocation = db // Retrieve the document location based on the title.
-        .select((DOCUMENT_NAME_TABLE, title))
-        .await
-        .unwrap() // Unwrap the result.
-        .ok_or(DocumentDoesNotExistError)?;
+    let Some(location) = select_location(db, &id).await? else {
+        // A note that was never indexed keeps its tags in the metadata sidecar until it is stored
+        let job = workspace
+            .indexing()
+            .queued_job(&id)
+            .ok_or(LinknotesError::NoteNotFound)?;
+        tags.retain(|tag| tag.manual);
+        update_metadata(&workspace.location, |metadata| {
+            let created_at = metadata
+                .get(&job.file_name)
+                .filter(|note| note.id == id)
+                .and_then(|note| note.created_at);
+            metadata.insert(
+                job.file_name,
+                NoteMetadata {
+                    id,
+                    tags,
+                    created_at,
+                },
+            );
+        })?;
+        return Ok(());
+    };
     let note: ContextualDocument = document_table
         .select(location.document_id.clone()) // Select the document based on the document ID.
         .await
-        .unwrap();
+        .map_err(LinknotesError::storage)?;
+    if let Some(key) = metadata_key(&location.location) {
+        update_metadata(&workspace.location, |metadata| {
+            metadata.insert(
+                key,
+                NoteMetadata {
+                    id: id.clone(),
+                    tags: tags.iter().filter(|tag| tag.manual).cloned().collect(),
+                    created_at: Some(note.created_at),
+                },
+            );
+        })?;
+    }
     let automatic_tags = note.tags.into_iter().filter(|tag| !tag.manual); // Filter out the automatic tags.
     tags.extend(automatic_tags);
     tags.sort(); // Sort the tags.
     tags.dedup(); // Deduplicate the tags.
     let id = location.document_id;
-    db.query(format!(
-        "UPDATE {}:{} SET tags = {}",
-        table_name,
-        id,
-        serde_json::to_string(&tags).unwrap()
-    ))
-    .await
-    .unwrap();
+    db.query("UPDATE type::thing($table, $id) SET tags = $tags") // Bind the values instead of formatting them into the query so they can't change the query.
+        .bind(("table", table_name.to_string()))
+        .bind(("id", id))
+        .bind(("tags", tags))
+        .await?
+        .check()?;
 
     Ok(())
 }
 
-/// Retrieves the location of a document from the database based on the provided title.
+/// Retrieves the tags of a note from the database based on the provided id.
 ///
 /// This function performs an asynchronous database query to select the document location
-/// from the `DOCUMENT_NAME_TABLE` using the given `title`. If the document is found, its
-/// location is returned. If the document does not exist, a `DocumentDoesNotExistError` is returned.
+/// from the `DOCUMENT_NAME_TABLE` using the given `id`. If the document is found, its
+/// tags are returned. If the document does not exist, `LinknotesError::NoteNotFound` is returned.
 ///
 /// # Errors
 ///
-/// Returns a `DocumentDoesNotExistError` if the document with the specified title does not exist in the database.
+/// Returns `LinknotesError::NoteNotFound` if the document with the specified id does not exist in the database.
 #[tauri::command]
 pub async fn get_tags(
     // Define the `get_tags` function as a Tauri command.
-    title: String,
+    id: NoteId,
     workspace_id: WorkspaceId,
-) -> Result<Vec<Tag>, DocumentDoesNotExistError> {
-    tracing::info!("get_tags called with title {:?}", title);
-    let workspace = get_workspace_ref(workspace_id);
-    let document_table = workspace.document_table().await.unwrap();
-    let db = document_table.table().db();
+) -> Result<Vec<Tag>, LinknotesError> {
+    tracing::info!("get_tags called with id {}", id);
+    let workspace = get_workspace_ref(workspace_id)?;
+    Ok(current_note(&id, &workspace).await?.tags)
+}
 
-    let location: ContextualDocumentLocation = db
-        .select((DOCUMENT_NAME_TABLE, &title))
-        .await
-        .unwrap()
-        .ok_or(DocumentDoesNotExistError)?;
-    let note: ContextualDocument = document_table.select(location.document_id).await.unwrap();
-    Ok(note.tags)
+/// Get the latest version of a note. A note that is waiting to be indexed is returned with the text it was last saved
+/// with, so this never waits for the indexing queue.
+///
+/// # Errors
+///
+/// Returns `LinknotesError::NoteNotFound` if the note is neither queued nor stored in the workspace.
+async fn current_note(
+    id: &NoteId,
+    workspace: &Workspace,
+) -> Result<ContextualDocument, LinknotesError> {
+    let queued = workspace.indexing().queued_job(id);
+    let document_table = workspace.document_table().await?;
+    let indexed = match select_location(document_table.table().db(), id).await? {
+        Some(location) => Some(
+            document_table
+                .select(location.document_id)
+                .await
+                .map_err(LinknotesError::storage)?,
+        ),
+        None => None,
+    };
+    match (queued, indexed) {
+        (Some(job), indexed) => Ok(queued_document(
+            &workspace.location,
+            id.clone(),
+            job,
+            indexed,
+        )),
+        (None, Some(note)) => Ok(note),
+        (None, None) => Err(LinknotesError::NoteNotFound),
+    }
 }
 
-/// Save a note with a title, and contents in a workspace. While saving the note, we generate embeddings for the document using BERT.
-/// The embeddings are used to add one tag automatically based on the classifier. They will also be used for search later.
+/// Save a note with a title, and contents in a workspace. The file is written right away and the note is added to the
+/// background indexing queue, which generates embeddings for the document using BERT. The embeddings are used to add
+/// one tag automatically based on the classifier. They will also be used for search later. Commands that read notes
+/// return the queued text until the note is indexed, and search waits for the queue, so they always see the saved text.
 ///
 /// # Arguments
 ///
-/// * `title` - The title of the note.
+/// * `id` - The id of the note to update. If this is `None`, a new note is created. Notes can share a title, so saving
+///   a note without an id never changes another note with the same title.
+/// * `title` - The title of the note. If the note already exists with a different title, it is renamed first.
 /// * `text` - The text of the note.
 /// * `workspace_id` - The ID of the workspace to save the note in.
 ///
@@ -225,129 +755,535 @@ pub async fn get_tags(
 ///
 /// # Returns
 ///
-/// A `Result` containing `()` (the empty type) if the note is saved successfully, or an error message as a `String` if the note cannot be saved.
+/// A `Result` containing the `NoteId` of the note if the note is saved successfully, or a `LinknotesError` if the note cannot be saved.
 ///
 /// # Errors
 ///
 /// This function will return an error if:
 /// - The workspace does not exist
+/// - The id is `Some` and the note does not exist
+/// - The title changed and the note cannot be renamed
 /// - the query execution fails
 /// - if there is an issue serializing or deserializing the database query and result
-/// - if there is an issue downloading the bert model
-/// - if there is an issue training the classifier
+/// - the file cannot be written
+///
+/// Errors while embedding the note are reported in the `indexing-progress` event instead.
 #[tauri::command]
 pub async fn save_note(
+    id: Option<NoteId>,
     title: String,
     text: String,
     workspace_id: WorkspaceId,
-) -> Result<(), String> {
+) -> Result<NoteId, LinknotesError> {
     tracing::info!("Add_note called");
     tracing::info!("Workspace added with id: {:?}", workspace_id);
+    let queue = {
+        let workspace = get_workspace_ref(workspace_id)?;
+        workspace.check_writable()?;
+        workspace.indexing()
+    };
+
+    // If the note was renamed, move it to the new title before saving the new text
+    if let Some(id) = &id {
+        // A note that is waiting to be indexed may not be in the database yet
+        let current_title = match queue.title_of(id) {
+            Some(title) => title,
+            None => {
+                let workspace = get_workspace_ref(workspace_id)?;
+                let document_table = workspace.document_table().await?;
+                select_location(document_table.table().db(), id)
+                    .await?
+                    .ok_or(LinknotesError::NoteNotFound)?
+                    .title
+            }
+        };
+        if current_title != title {
+            rename_note(id.clone(), title.clone(), workspace_id).await?;
+        }
+    }
+
+    // A note without an id is always a new note, even if another note has the same title
+    let note_id = id.unwrap_or_else(NoteId::new);
+    // Write the file now so the text is safe on disk, and embed the note in the background
+    let file_name = write_note(&note_id, &title, &text, workspace_id).await?;
+    enqueue_note(
+        workspace_id,
+        note_id.clone(),
+        IndexJob {
+            title,
+            file_name,
+            text,
+        },
+    )?;
+    Ok(note_id)
+}
 
-    let workspace = get_workspace_ref(workspace_id);
-    let document_path = workspace.document_path(&title).unwrap();
+/// Add a note file that isn't in the index yet to the indexing queue, like a file that was created outside of the app.
+/// Unlike [`save_note`], the file is kept where it is. The note keeps the id the metadata sidecar has for the file if
+/// no other note uses that id. Otherwise it gets a new id.
+///
+/// # Errors
+///
+/// Returns `LinknotesError::InvalidInput` if the path has no file name, or `LinknotesError::WorkspaceReadOnly` if the
+/// workspace can't be changed.
+pub(crate) async fn import_note(
+    path: &Path,
+    title: String,
+    text: String,
+    workspace_id: WorkspaceId,
+) -> Result<NoteId, LinknotesError> {
+    let file_name = metadata_key(path)
+        .ok_or_else(|| LinknotesError::InvalidInput(format!("{path:?} is not a note file")))?;
+    let note_id = {
+        let workspace = get_workspace_ref(workspace_id)?;
+        workspace.check_writable()?;
+        let stored = read_metadata(&workspace.location)
+            .remove(&file_name)
+            .map(|metadata| metadata.id);
+        let document_table = workspace.document_table().await?;
+        match stored {
+            Some(id)
+                if workspace.indexing().queued_job(&id).is_none()
+                    && select_location(document_table.table().db(), &id)
+                        .await?
+                        .is_none() =>
+            {
+                id
+            }
+            _ => NoteId::new(),
+        }
+    };
+    enqueue_note(
+        workspace_id,
+        note_id.clone(),
+        IndexJob {
+            title,
+            file_name,
+            text,
+        },
+    )?;
+    Ok(note_id)
+}
+
+/// Choose the file a note is written to. A note keeps its file while its title stays the same. Otherwise it is stored
+/// in the file for the title, unless another note already uses that file. Then the id of the note is added to the file
+/// name, so any number of notes can share a title. File names are compared ignoring case because case insensitive
+/// filesystems would store both notes in the same file.
+///
+/// # Errors
+///
+/// Returns `LinknotesError::InvalidTitle` if the title can't be used in a file name.
+async fn choose_file_name(
+    note_id: &NoteId,
+    title: &str,
+    workspace: &Workspace,
+) -> Result<String, LinknotesError> {
+    let file_name = title_to_file_name(title)?;
+    let unique_file_name = title_to_unique_file_name(title, note_id)?;
+    let queue = workspace.indexing();
+    let document_table = workspace.document_table().await?;
+    let db = document_table.table().db();
+
+    let current = match queue.queued_job(note_id) {
+        Some(job) => Some(job.file_name),
+        None => select_location(db, note_id)
+            .await?
+            .and_then(|location| metadata_key(&location.location)),
+    };
+    if let Some(current) =
+        current.filter(|current| *current == file_name || *current == unique_file_name)
+    {
+        return Ok(current);
+    }
+
+    // Notes that are waiting to be indexed may not have a location record yet
+    let lowercase = file_name.to_lowercase();
+    let queued = queue
+        .queued_jobs()
+        .into_iter()
+        .any(|(id, job)| id != *note_id && job.file_name.to_lowercase() == lowercase);
+    if queued
+        || colliding_note(db, note_id, Path::new(&file_name))
+            .await?
+            .is_some()
+    {
+        Ok(unique_file_name)
+    } else {
+        Ok(file_name)
+    }
+}
+
+/// Write the text of a note to its file. Returns the name of the file in the notes directory, which is chosen with
+/// [`choose_file_name`].
+///
+/// # Errors
+///
+/// Returns `LinknotesError::InvalidTitle` if the title can't be used in a file name, or `LinknotesError::Io` if the
+/// file can't be written.
+async fn write_note(
+    note_id: &NoteId,
+    title: &str,
+    text: &str,
+    workspace_id: WorkspaceId,
+) -> Result<String, LinknotesError> {
+    let workspace = get_workspace_ref(workspace_id)?;
+    let file_name = choose_file_name(note_id, title, &workspace).await?;
 
     // Write the note content to the file
-    fs::write(&document_path, &text).unwrap();
+    fs::write(workspace.notes_dir()?.join(&file_name), text)?;
+    Ok(file_name)
+}
+
+/// Embed and store a note whose file is already written. Sentences that didn't change since the note was last
+/// indexed keep the embeddings stored in the document table.
+pub(crate) async fn index_written_note(
+    note_id: NoteId,
+    job: IndexJob,
+    workspace_id: WorkspaceId,
+) -> Result<NoteId, LinknotesError> {
+    let IndexJob {
+        title,
+        file_name,
+        text,
+    } = job;
+    let note = {
+        let workspace = get_workspace_ref(workspace_id)?;
+        let embedder = workspace.embedder().await?;
+        let document_table = workspace.document_table().await?;
+        let previous = select_location(document_table.table().db(), &note_id).await?;
+        embed_changed_sentences(&embedder, document_table, previous.as_ref(), title, text).await?
+    };
+    store_note(note_id, note, file_name, None, workspace_id).await
+}
+
+/// The text of a note split into sentences along with the embedding of each sentence.
+///
+/// # Fields
+///
+/// * `document` - The title and text of the note.
+/// * `sentences` - The byte range of each sentence in the body of the document.
+/// * `hashes` - The hash of the text of each sentence.
+/// * `embeddings` - The embedding of each sentence.
+pub(crate) struct EmbeddedNote {
+    pub(crate) document: Document,
+    pub(crate) sentences: Vec<Range<usize>>,
+    pub(crate) hashes: Vec<String>,
+    pub(crate) embeddings: Vec<Embedding<BertSpace>>,
+}
 
-    let document_table = workspace.document_table().await.unwrap();
+/// Split notes into sentences and embed the sentences of every note in a single batch. Sentences that are already in
+/// the embedding cache don't run the model.
+///
+/// # Arguments
+///
+/// * `embedder` - The embedder of the workspace the notes are stored in.
+/// * `notes` - The title and text of each note.
+///
+/// # Returns
+///
+/// The embedded notes in the same order as `notes`, or `LinknotesError::EmbeddingModelUnavailable` if the model fails
+/// or doesn't return an embedding for every sentence.
+pub(crate) async fn embed_notes(
+    embedder: &NotesEmbedder,
+    notes: Vec<(String, String)>,
+) -> Result<Vec<EmbeddedNote>, LinknotesError> {
+    tracing::info!("Chunking {} documents", notes.len());
+    let notes: Vec<_> = notes
+        .into_iter()
+        .map(|(title, text)| split_note(title, text))
+        .collect();
+
+    let all_sentences: Vec<&str> = notes
+        .iter()
+        .flat_map(|(document, sentences, _)| {
+            sentences
+                .iter()
+                .map(|sentence| &document.body()[sentence.clone()])
+        })
+        .collect();
+    tracing::info!("Embedding {} sentences", all_sentences.len());
+    let mut embeddings = if all_sentences.is_empty() {
+        Vec::new()
+    } else {
+        embed_batch_cached(embedder, &all_sentences)
+            .await
+            .map_err(LinknotesError::embedding_model)?
+    }
+    .into_iter();
+
+    notes
+        .into_iter()
+        .map(|(document, sentences, hashes)| {
+            let embeddings: Vec<_> = embeddings.by_ref().take(sentences.len()).collect();
+            // A short batch would shift the embeddings of every later note onto the wrong sentences
+            if embeddings.len() != sentences.len() {
+                return Err(LinknotesError::embedding_model(
+                    "the model didn't return an embedding for every sentence",
+                ));
+            }
+            Ok(EmbeddedNote {
+                document,
+                sentences,
+                hashes,
+                embeddings,
+            })
+        })
+        .collect()
+}
+
+/// Split a note into sentences and hash the text of every sentence
+fn split_note(title: String, text: String) -> (Document, Vec<Range<usize>>, Vec<String>) {
+    let document = Document::from_parts(title, text);
+    let sentences = chunk_text(document.body());
+    let hashes = sentences
+        .iter()
+        .map(|sentence| text_hash(&document.body()[sentence.clone()]))
+        .collect();
+    (document, sentences, hashes)
+}
+
+/// Embed a new version of a note. Sentences with the same hash as a segment of the `previous` location of the note
+/// keep the embedding the document table stores for that segment, so only new and edited sentences run the model. If
+/// there is no previous location or its embeddings can't be read, every sentence is embedded.
+///
+/// # Errors
+///
+/// Returns `LinknotesError::EmbeddingModelUnavailable` if the model fails or doesn't return an embedding for every
+/// changed sentence.
+pub(crate) async fn embed_changed_sentences(
+    embedder: &NotesEmbedder,
+    document_table: &ContextualDocumentTable,
+    previous: Option<&ContextualDocumentLocation>,
+    title: String,
+    text: String,
+) -> Result<EmbeddedNote, LinknotesError> {
+    let (document, sentences, hashes) = split_note(title, text);
+
+    let mut unchanged: HashMap<String, Embedding<BertSpace>> = HashMap::new();
+    if let Some(previous) = previous {
+        match stored_chunk_embeddings(document_table, &previous.document_id).await {
+            Ok(mut stored) => {
+                for segment in &previous.segments {
+                    let embedding = stored.remove(&segment.source_char_range);
+                    if let (Some(hash), Some(embedding)) = (&segment.hash, embedding) {
+                        unchanged.insert(hash.clone(), embedding);
+                    }
+                }
+            }
+            Err(err) => tracing::warn!(
+                "Failed to read the stored embeddings of {:?}: {}",
+                previous.title,
+                err
+            ),
+        }
+    }
+
+    let changed: Vec<&str> = sentences
+        .iter()
+        .zip(&hashes)
+        .filter(|(_, hash)| !unchanged.contains_key(*hash))
+        .map(|(sentence, _)| &document.body()[sentence.clone()])
+        .collect();
+    tracing::info!(
+        "Embedding {} of {} sentences",
+        changed.len(),
+        sentences.len()
+    );
+    let mut new_embeddings = if changed.is_empty() {
+        Vec::new()
+    } else {
+        embed_batch_cached(embedder, &changed)
+            .await
+            .map_err(LinknotesError::embedding_model)?
+    }
+    .into_iter();
+    let embeddings = hashes
+        .iter()
+        .map(|hash| {
+            unchanged
+                .get(hash)
+                .cloned()
+                .or_else(|| new_embeddings.next())
+        })
+        .collect::<Option<Vec<_>>>()
+        .ok_or_else(|| {
+            LinknotesError::embedding_model(
+                "the model didn't return an embedding for every sentence",
+            )
+        })?;
+
+    Ok(EmbeddedNote {
+        document,
+        sentences,
+        hashes,
+        embeddings,
+    })
+}
+
+/// Store an embedded note in the database under `note_id`. The file of the note must already be written to `file_name`
+/// in the notes directory. If the note already has a location record, the document in the embedding table is replaced
+/// and the manual tags are kept unless `manual_tags` is `Some`.
+///
+/// # Returns
+///
+/// A `Result` containing the `NoteId` of the note if the note is stored successfully, or a `LinknotesError` if the note cannot be stored.
+pub(crate) async fn store_note(
+    note_id: NoteId,
+    note: EmbeddedNote,
+    file_name: String,
+    manual_tags: Option<Vec<Tag>>,
+    workspace_id: WorkspaceId,
+) -> Result<NoteId, LinknotesError> {
+    let workspace = get_workspace_ref(workspace_id)?;
+    let EmbeddedNote {
+        document,
+        sentences,
+        hashes,
+        embeddings,
+    } = note;
+    if embeddings.len() != sentences.len() {
+        return Err(LinknotesError::embedding_model(
+            "the model didn't return an embedding for every sentence",
+        ));
+    }
+    let title = document.title().to_string();
+    let text = document.body().to_string();
+    let notes_dir = workspace.notes_dir()?;
+    let document_path = notes_dir.join(&file_name);
+    let document_table = workspace.document_table().await?;
     let db = document_table.table().db();
-    let document = Document::from_parts(title.clone(), text);
-    tracing::info!("Chunking document");
-    let body = document.body();
-    let sentences = chunk_text(body);
-    let bert = bert().await.unwrap();
-    let embeddings = bert
-        .embed_batch(sentences.iter().map(|sentence| &body[sentence.clone()]))
-        .await
-        .unwrap();
 
+    // The embeddings are only stored in the document table. The hash is enough to find the embedding of an unchanged
+    // sentence there the next time the note is saved
+    let segments: Vec<Segment> = sentences
+        .iter()
+        .zip(hashes)
+        .map(|(byte_range, hash)| Segment {
+            source_char_range: byte_range.clone(),
+            hash: Some(hash),
+        })
+        .collect();
     let chunks = sentences
-        .clone()
         .into_iter()
-        .zip(embeddings.into_iter())
+        .zip(embeddings)
         .map(|(byte_range, embedding)| Chunk {
             byte_range,
             embeddings: vec![embedding],
         });
-    let segments = sentences
-        .iter()
-        .map(|byte_range| Segment {
-            source_char_range: byte_range.clone(),
-        })
-        .collect();
     tracing::info!("Looking for existing document");
     // First check if the document already exists
-    let current_location: Option<ContextualDocumentLocation> = db
-        .select((DOCUMENT_NAME_TABLE, title.as_str()))
-        .await
-        .unwrap();
+    let current_location = select_location(db, &note_id).await?;
     let mut tags = Vec::new();
+    let mut created_at = None;
     if let Some(current_location) = &current_location {
+        // Notes saved before titles were escaped may be stored in a different file. Remove the old file now that the
+        // note is written to the new file. A file name that only differs by case is the new file on case insensitive
+        // filesystems, so it is kept
+        let old_path = current_location.path(&notes_dir);
+        if lowercase_file_name(&old_path) != lowercase_file_name(&document_path)
+            && old_path.exists()
+        {
+            fs::remove_file(&old_path)?;
+        }
         let previous_document = document_table
             .select(current_location.document_id.clone())
             .await
-            .unwrap();
+            .map_err(LinknotesError::storage)?;
         // If this is the same as the note already in the db, just return
         if current_location.segments == segments && previous_document.document == document {
-            return Ok(());
+            return Ok(note_id);
         }
         tags = previous_document.tags.clone();
+        created_at = Some(previous_document.created_at).filter(|created_at| *created_at > 0);
         // Delete the old document if it exists
         document_table
             .delete(current_location.document_id.clone())
             .await
-            .unwrap();
+            .map_err(LinknotesError::storage)?;
     }
 
+    // Tags set before the note was indexed the first time and the creation time of a note that is indexed again after
+    // a rebuild are only in the metadata sidecar
+    let sidecar = match current_location {
+        Some(_) => None,
+        None => metadata_key(&document_path)
+            .and_then(|key| read_metadata(&workspace.location).remove(&key))
+            .filter(|metadata| metadata.id == note_id),
+    };
+    let created_at = created_at
+        .or_else(|| sidecar.as_ref().and_then(|metadata| metadata.created_at))
+        .unwrap_or_else(|| file_created_at(&document_path));
+    match manual_tags {
+        Some(manual_tags) => tags = manual_tags,
+        None if current_location.is_none() => {
+            tags = sidecar.map(|metadata| metadata.tags).unwrap_or_default();
+        }
+        None => {}
+    }
     tags.retain(|tag| tag.manual);
+    // Keep the id and manual tags next to the notes so the index can be rebuilt from the notes directory
+    if let Some(key) = metadata_key(&document_path) {
+        update_metadata(&workspace.location, |metadata| {
+            metadata.insert(
+                key,
+                NoteMetadata {
+                    id: note_id.clone(),
+                    tags: tags.clone(),
+                    created_at: Some(created_at),
+                },
+            );
+        })?;
+    }
     // Classify the document and add an automatic tag
-    let mut contextual = ContextualDocument { document, tags };
+    let mut contextual = ContextualDocument {
+        note_id: note_id.clone(),
+        document,
+        tags,
+        created_at,
+    };
     let tag = workspace // Classify the document using the workspace classifier.
         .classify(&contextual) // Classify the document using the workspace classifier.
         .await // Await the result.
-        .map_err(|err| err.to_string())?; // Map any errors to a string.
+        .map_err(LinknotesError::classifier)?; // Map any errors to a classifier error.
     contextual.tags.push(tag); // Push the tag to the document tags.
 
     tracing::info!("Inserting document with id: {:?}", contextual);
     let document_id = document_table // Insert the document into the document table.
         .insert_with_chunks(contextual, chunks) // Insert the document with the associated chunks.
         .await
-        .map_err(|err| err.to_string())?;
+        .map_err(LinknotesError::storage)?;
     tracing::info!("Document inserted successfully");
 
-    let location = ContextualDocumentLocation {
-        document_id,
-        location: document_path,
-        segments,
-    };
+    let location =
+        ContextualDocumentLocation::new(title, document_id, PathBuf::from(file_name), segments);
 
     // If it doesn't, create it
     if current_location.is_none() {
         // If the document does not exist, create it.
         let _: Option<ContextualDocumentLocation> = db // Create a new document location entry in the database.
-            .create((DOCUMENT_NAME_TABLE, title.as_str())) // Create a new document location entry in the database.
+            .create((DOCUMENT_NAME_TABLE, note_id.as_str())) // Create a new document location entry in the database.
             .content(location)
-            .await // Await the result.
-            .map_err(|err| err.to_string())?; // Map any errors to a string.
+            .await?; // Await the result.
     }
     // Otherwise, update it
     else {
         let _: Option<ContextualDocumentLocation> = db // Update the document location entry in the database.
-            .update((DOCUMENT_NAME_TABLE, title.as_str())) // Update the document location entry in the database.
+            .update((DOCUMENT_NAME_TABLE, note_id.as_str())) // Update the document location entry in the database.
             .content(location)
-            .await
-            .map_err(|err| err.to_string())?; // Map any errors to a string.
+            .await?;
     }
 
-    Ok(())
+    // Replace the links going out of this note with the links in the new text
+    update_links(db, &note_id, &text).await?;
+
+    Ok(note_id)
 }
 
-/// Remove a note from a specific path from a workspace.
+/// Remove a note from a workspace.
 ///
 /// # Arguments
 ///
-/// * `title` - The title of the note to remove.
+/// * `id` - The id of the note to remove.
 /// * `workspace_id` - The ID of the workspace to remove the note from.
 ///
 /// # Preconditions
@@ -357,39 +1293,296 @@ pub async fn save_note(
 ///
 /// # Returns
 ///
-/// Nothing.
+/// A `Result` containing `()` if the note is removed, or a `LinknotesError` if the database or file can't be updated.
 #[tauri::command]
-pub async fn remove_note(title: String, workspace_id: WorkspaceId) {
-    tracing::info!("Removing note with title: {:?}", title); // Log the removal of a note with the specified title.
-    let workspace = get_workspace_ref(workspace_id); // Get a reference to the workspace using the `workspace_id`.
-    let document_table = workspace.document_table().await.unwrap(); // Get the document table for the workspace.
+pub async fn remove_note(id: NoteId, workspace_id: WorkspaceId) -> Result<(), LinknotesError> {
+    tracing::info!("Removing note with id: {}", id); // Log the removal of a note with the specified id.
+    let queue = {
+        let workspace = get_workspace_ref(workspace_id)?; // Get a reference to the workspace using the `workspace_id`.
+        workspace.check_writable()?; // Read-only workspaces can't be changed.
+        workspace.indexing()
+    };
+    // Drop the queued version of the note and let the note finish if it is being indexed, so it isn't stored again
+    // after it is removed. Other notes in the queue are not waited for
+    let queued = queue.queued_job(&id);
+    queue.remove(&id);
+    queue.wait_for_note(&id).await;
+    let workspace = get_workspace_ref(workspace_id)?;
+    let document_table = workspace.document_table().await?; // Get the document table for the workspace.
     let db = document_table.table().db(); // Get the database connection for the document table.
 
-    // Get the document path
-    let document_path = workspace.document_path(&title).unwrap(); // Get the document path based on the title.
-
     // First check if the document already exists
     let current_location: Option<ContextualDocumentLocation> =
-        db.delete((DOCUMENT_NAME_TABLE, &title)).await.unwrap();
+        db.delete((DOCUMENT_NAME_TABLE, id.as_str())).await?;
     if let Some(current_location) = &current_location {
         // Delete the old document if it exists
         document_table
             .delete(current_location.document_id.clone())
             .await
-            .unwrap();
+            .map_err(LinknotesError::storage)?;
+
+        if let Some(key) = metadata_key(&current_location.location) {
+            update_metadata(&workspace.location, |metadata| {
+                metadata.remove(&key);
+            })?;
+        }
+
+        // Remove the .txt file
+        let path = current_location.path(&workspace.notes_dir()?);
+        if path.exists() {
+            fs::remove_file(&path)?;
+        }
+    } else if let Some(job) = queued {
+        // The note was never indexed, so only the file and the metadata need to be removed
+        let path = workspace.notes_dir()?.join(&job.file_name);
+        if let Some(key) = metadata_key(&path) {
+            update_metadata(&workspace.location, |metadata| {
+                metadata.remove(&key);
+            })?;
+        }
+        if path.exists() {
+            fs::remove_file(&path)?;
+        }
     }
 
-    // Remove the .txt file
-    if document_path.exists() {
-        fs::remove_file(document_path).unwrap();
+    // Remove the links going out of the note. Links pointing to the note are kept as dangling links
+    delete_outgoing_links(db, &id).await
+}
+
+/// Rename a note in a workspace. The file is moved to the new title and the location record, the document in the
+/// embedding table and the link graph are updated in a single transaction. The existing chunks, embeddings and tags
+/// are kept as they are. `[[links]]` in other notes that point to the note are rewritten to use the new title, and
+/// those notes are returned so the frontend can tell the user which notes changed. Links are only rewritten if no other
+/// note keeps the old title, because they still point to that note otherwise. Notes can share a title, so if another
+/// note already has the new title, the file of the note gets its id added to the name.
+///
+/// # Arguments
+///
+/// * `id` - The id of the note to rename.
+/// * `new_title` - The title the note should have after the rename.
+/// * `workspace_id` - The ID of the workspace the note is in.
+///
+/// # Preconditions
+///
+/// - The workspace must exist.
+/// - The note must exist in the workspace.
+///
+/// # Returns
+///
+/// A `Result` containing the ids of the notes whose text was changed to link to the new title if the note is renamed
+/// successfully, or a `LinknotesError` if the note cannot be renamed. The renamed note is included if it links to
+/// itself.
+///
+/// # Errors
+///
+/// This function will return an error if:
+/// - The note does not exist in the workspace
+/// - The new title can't be used in a file name
+/// - The file cannot be moved or the database cannot be updated. The file is moved back if the database update fails
+#[tauri::command]
+pub async fn rename_note(
+    id: NoteId,
+    new_title: String,
+    workspace_id: WorkspaceId,
+) -> Result<Vec<NoteId>, LinknotesError> {
+    tracing::info!("Renaming note {} to {:?}", id, new_title);
+    rename_note_file(id, new_title, None, workspace_id).await
+}
+
+/// Rename a note whose file was already moved to `file_name` in the notes directory outside of the app. Everything
+/// [`rename_note`] updates is updated except the file. Returns the notes whose links were rewritten.
+pub(crate) async fn rename_moved_note(
+    id: NoteId,
+    new_title: String,
+    file_name: String,
+    workspace_id: WorkspaceId,
+) -> Result<Vec<NoteId>, LinknotesError> {
+    tracing::info!("Note {} was moved to {:?}", id, file_name);
+    rename_note_file(id, new_title, Some(file_name), workspace_id).await
+}
+
+/// Rename a note like [`rename_note`]. If `moved_to` is set, the file of the note was already moved to that file name
+/// and is left where it is.
+async fn rename_note_file(
+    id: NoteId,
+    new_title: String,
+    moved_to: Option<String>,
+    workspace_id: WorkspaceId,
+) -> Result<Vec<NoteId>, LinknotesError> {
+    // The note needs a location record to be renamed, so wait until it is indexed. Other notes in the queue are not
+    // waited for
+    let queue = get_workspace_ref(workspace_id)?.indexing();
+    queue.wait_for_note(&id).await;
+
+    let notes_to_relink = {
+        let workspace = get_workspace_ref(workspace_id)?;
+        workspace.check_writable()?;
+        let document_table = workspace.document_table().await?;
+        let db = document_table.table().db();
+
+        let location = select_location(db, &id)
+            .await?
+            .ok_or(LinknotesError::NoteNotFound)?;
+        let old_title = location.title;
+        let moved = moved_to.is_some();
+        if old_title == new_title && !moved {
+            return Ok(Vec::new());
+        }
+        let notes_dir = workspace.notes_dir()?;
+        let old_path = location.path(&notes_dir);
+        let new_file_name = match moved_to {
+            Some(file_name) => file_name,
+            None => {
+                let file_name = choose_file_name(&id, &new_title, &workspace).await?;
+                // A file that only differs by case from the old file is the old file on case insensitive filesystems.
+                // Any other file that already exists is kept
+                let path = notes_dir.join(&file_name);
+                if path.exists() && lowercase_file_name(&path) != lowercase_file_name(&old_path) {
+                    title_to_unique_file_name(&new_title, &id)?
+                } else {
+                    file_name
+                }
+            }
+        };
+        let new_path = notes_dir.join(&new_file_name);
+
+        // Links to the old title keep pointing to another note with the same title. A note that is waiting to be
+        // indexed may not be in the database yet
+        let old_title_kept = queue
+            .queued_jobs()
+            .iter()
+            .any(|(queued_id, job)| *queued_id != id && job.title == old_title)
+            || find_note_ids(db, &old_title)
+                .await?
+                .iter()
+                .any(|other| *other != id);
+
+        // Move the file first. A rename within the notes directory is atomic
+        if !moved {
+            fs::rename(&old_path, &new_path)?;
+        }
+
+        let mut transaction = db
+            .query("BEGIN TRANSACTION")
+            .query("UPDATE type::thing($documents, $document_id) SET document.title = $new_title")
+            .query("UPDATE type::thing($paths, $id) SET title = $new_title, location = $location, file_key = $file_key");
+        if !old_title_kept {
+            transaction = transaction.query(
+                "UPDATE type::table($links) SET target = $new_title WHERE target = $old_title",
+            );
+        }
+        let updated = transaction
+            .query("COMMIT TRANSACTION")
+            .bind(("documents", document_table.table().table().to_string()))
+            .bind(("document_id", location.document_id))
+            .bind(("paths", DOCUMENT_NAME_TABLE))
+            .bind(("id", id.clone()))
+            .bind(("links", DOCUMENT_LINK_TABLE))
+            .bind(("old_title", old_title.clone()))
+            .bind(("new_title", new_title.clone()))
+            .bind(("file_key", new_file_name.to_lowercase()))
+            .bind(("location", PathBuf::from(new_file_name)))
+            .await
+            .and_then(|response| response.check());
+        if let Err(err) = updated {
+            // Put the file back so the note stays consistent with the database
+            if !moved {
+                _ = fs::rename(&new_path, &old_path);
+            }
+            return Err(err.into());
+        }
+        if let (Some(old_key), Some(new_key)) = (metadata_key(&old_path), metadata_key(&new_path)) {
+            update_metadata(&workspace.location, |metadata| {
+                if let Some(note) = metadata.remove(&old_key) {
+                    metadata.insert(new_key, note);
+                }
+            })?;
+        }
+        if old_title_kept {
+            return Ok(Vec::new());
+        }
+
+        // Find the notes with [[links]] to the old title. The link graph already points at the new title, but the
+        // text of those notes still needs to be updated
+        let mut sources = db
+            .query("SELECT VALUE source FROM type::table($links) WHERE target = $new_title")
+            .bind(("links", DOCUMENT_LINK_TABLE))
+            .bind(("new_title", new_title.clone()))
+            .await?;
+        let mut sources: Vec<NoteId> = sources.take(0)?;
+        sources.sort();
+        sources.dedup();
+        // The links of notes that are waiting to be indexed are not in the graph yet, so their queued text is checked
+        let queued = queue.queued_jobs();
+        sources.retain(|source| !queued.iter().any(|(queued_id, _)| queued_id == source));
+        let mut linking_notes = Vec::new();
+        for source in sources {
+            let location = select_location(db, &source).await?;
+            let Some(location) = location else {
+                continue;
+            };
+            let note = document_table
+                .select(location.document_id)
+                .await
+                .map_err(LinknotesError::storage)?;
+            linking_notes.push((source, location.title, note.document.body().to_string()));
+        }
+        linking_notes.extend(queued.into_iter().filter_map(|(source, job)| {
+            parse_links(&source, &job.text)
+                .iter()
+                .any(|link| link.target == old_title)
+                .then_some((source, job.title, job.text))
+        }));
+
+        let mut notes_to_relink = Vec::new();
+        for (source, title, body) in linking_notes {
+            let body = body.as_str();
+            let mut relinked = String::new();
+            let mut last_char = 0;
+            let mut chars = body.chars();
+            for link in parse_links(&source, body) {
+                if link.target != old_title {
+                    continue;
+                }
+                relinked.extend(chars.by_ref().take(link.character_range.start - last_char));
+                _ = chars.by_ref().take(link.character_range.len()).count();
+                last_char = link.character_range.end;
+                match &link.heading {
+                    Some(heading) => relinked.push_str(&format!("[[{}#{}]]", new_title, heading)),
+                    None => relinked.push_str(&format!("[[{}]]", new_title)),
+                }
+            }
+            relinked.extend(chars);
+            if relinked != body {
+                // The renamed note may link to itself, so use the title after the rename
+                let title = if source == id {
+                    new_title.clone()
+                } else {
+                    title
+                };
+                notes_to_relink.push((source, title, relinked));
+            }
+        }
+        notes_to_relink
+    };
+
+    // Save the rewritten notes after the workspace reference is released
+    let mut relinked = Vec::new();
+    for (source, title, text) in notes_to_relink {
+        tracing::info!("Rewriting the links to {:?} in note {}", new_title, source);
+        // save_note can call rename_note, so the future needs to be boxed
+        Box::pin(save_note(Some(source.clone()), title, text, workspace_id)).await?;
+        relinked.push(source);
     }
+
+    Ok(relinked)
 }
 
-/// Read a note from a specific path from a workspace.
+/// Read a note from a workspace.
 ///
 /// # Arguments
 ///
-/// * `title` - The title of the note to read.
+/// * `id` - The id of the note to read.
 /// * `workspace_id` - The ID of the workspace to read the note from.
 ///
 /// # Preconditions
@@ -399,7 +1592,7 @@ pub async fn remove_note(title: String, workspace_id: WorkspaceId) {
 ///
 /// # Returns
 ///
-/// A `Result` containing a `ContextualDocument` if the note is read successfully, or an error message as a `DocumentDoesNotExistError` if the note cannot be read.
+/// A `Result` containing a `ContextualDocument` if the note is read successfully, or a `LinknotesError` if the note cannot be read.
 ///
 /// # Errors
 ///
@@ -408,20 +1601,12 @@ pub async fn remove_note(title: String, workspace_id: WorkspaceId) {
 #[tauri::command]
 pub async fn read_note(
     // Define the `read_note` function as a Tauri command.
-    title: String,             // Define the `title` parameter as a `String`.
+    id: NoteId,                // Define the `id` parameter as a `NoteId`.
     workspace_id: WorkspaceId, // Define the `workspace_id` parameter as a `WorkspaceId`.
-) -> Result<ContextualDocument, DocumentDoesNotExistError> {
+) -> Result<ContextualDocument, LinknotesError> {
     // Define the return type of the function.
-    let workspace = get_workspace_ref(workspace_id);
-    let document_table = workspace.document_table().await.unwrap();
-    let db = document_table.table().db(); // Get the database connection for the document table.
-    let location: ContextualDocumentLocation = db // Retrieve the document location based on the title.
-        .select((DOCUMENT_NAME_TABLE, &title)) // Select the document location based on the title.
-        .await
-        .unwrap() // Unwrap the result.
-        .ok_or(DocumentDoesNotExistError)?;
-    let note: ContextualDocument = document_table.select(location.document_id).await.unwrap();
-    Ok(note)
+    let workspace = get_workspace_ref(workspace_id)?;
+    current_note(&id, &workspace).await // Notes waiting to be indexed are read from the queue instead of waiting for it.
 }
 
 // Tests for the note module.
@@ -429,17 +1614,17 @@ pub async fn read_note(
 async fn test_set_tags() {
     use crate::note::save_note;
     use crate::workspace::{
-        delete_workspace, files_in_workspace, load_workspace, unload_workspace,
+        delete_workspace, files_in_workspace, load_workspace_with, unload_workspace,
     };
     use std::env::temp_dir;
 
     _ = tracing_subscriber::fmt::try_init();
 
     let temp = temp_dir();
-    let workspace = load_workspace(temp);
+    let workspace = load_workspace_with(temp, Some(NotesEmbedder::hashing())).unwrap();
     let title = "test-note".to_string();
     let text = "test note".to_string();
-    save_note(title.clone(), text.clone(), workspace)
+    let id = save_note(None, title.clone(), text.clone(), workspace)
         .await
         .unwrap();
     let tags = vec![
@@ -452,13 +1637,11 @@ async fn test_set_tags() {
             manual: true,
         },
     ];
-    set_tags(title.clone(), tags.clone(), workspace)
-        .await
-        .unwrap();
+    set_tags(id.clone(), tags.clone(), workspace).await.unwrap();
 
     let title2 = "my-other-test-note".to_string();
     let text2 = "testing other note".to_string();
-    save_note(title2.clone(), text2.clone(), workspace)
+    let id2 = save_note(None, title2.clone(), text2.clone(), workspace)
         .await
         .unwrap();
     let tags2 = vec![
@@ -471,30 +1654,42 @@ async fn test_set_tags() {
             manual: true,
         },
     ];
-    set_tags(title2.clone(), tags2.clone(), workspace)
+    set_tags(id2.clone(), tags2.clone(), workspace)
         .await
         .unwrap();
 
-    let notes = files_in_workspace(workspace).await;
+    let notes = files_in_workspace(workspace).await.unwrap();
+    // Every note records when it was created
+    assert!(notes.iter().all(|note| note.created_at > 0));
 
     assert_eq!(
         notes,
         vec![
             ContextualDocument {
+                note_id: id.clone(),
                 document: Document::from_parts(title, text.clone()),
-                tags
+                tags,
+                created_at: notes[0].created_at,
             },
             ContextualDocument {
+                note_id: id2,
                 document: Document::from_parts(title2, text2.clone()),
-                tags: tags2
+                tags: tags2,
+                created_at: notes[1].created_at,
             }
         ]
     );
 
-    let results =
-        crate::search::search("test".to_string(), vec!["tag2".to_string()], 10, workspace)
-            .await
-            .unwrap();
+    let results = crate::search::search(
+        "test".to_string(),
+        vec!["tag2".to_string()].into(),
+        10,
+        None,
+        None,
+        workspace,
+    )
+    .await
+    .unwrap();
     assert_eq!(results.len(), 2);
     assert_eq!(results[0].title, "test-note");
     assert_eq!(results[0].character_range, 0..text.len());
@@ -503,26 +1698,544 @@ async fn test_set_tags() {
 
     let results = crate::search::search(
         "test".to_string(),
-        vec!["tag1".to_string(), "tag2".to_string()],
+        vec!["tag1".to_string(), "tag2".to_string()].into(),
         10,
+        None,
+        None,
         workspace,
     )
     .await
     .unwrap();
     assert_eq!(results.len(), 1);
+    assert_eq!(results[0].id, id);
     assert_eq!(results[0].title, "test-note");
     assert_eq!(results[0].character_range, 0..text.len());
 
     let results = crate::search::search(
         "my note is here".to_string(),
-        vec!["testing".to_string()],
+        vec!["testing".to_string()].into(),
         10,
+        None,
+        None,
         workspace,
     )
     .await
     .unwrap();
     assert!(results.is_empty());
 
-    delete_workspace(workspace);
-    unload_workspace(workspace);
+    delete_workspace(workspace).unwrap();
+    unload_workspace(workspace).unwrap();
+}
+
+#[tokio::test]
+async fn test_rename_note() {
+    use crate::links::get_backlinks;
+    use crate::workspace::{delete_workspace, load_workspace_with, unload_workspace};
+
+    _ = tracing_subscriber::fmt::try_init();
+
+    let temp = std::env::temp_dir();
+    let workspace_path = temp.join("testing-rename-workspace");
+    _ = std::fs::remove_dir_all(&workspace_path);
+    let workspace = load_workspace_with(workspace_path, Some(NotesEmbedder::hashing())).unwrap();
+
+    let text = "Integrals measure the area under a curve.".to_string();
+    let id = save_note(None, "Integrals".to_string(), text.clone(), workspace)
+        .await
+        .unwrap();
+    let tags = vec![Tag {
+        name: "Calculus".to_string(),
+        manual: true,
+    }];
+    set_tags(id.clone(), tags.clone(), workspace).await.unwrap();
+    let calculus_id = save_note(
+        None,
+        "Calculus".to_string(),
+        "Calculus builds on [[Integrals#Area]].".to_string(),
+        workspace,
+    )
+    .await
+    .unwrap();
+
+    let result = rename_note(NoteId::new(), "Other".to_string(), workspace).await;
+    assert!(matches!(result, Err(LinknotesError::NoteNotFound)));
+
+    let relinked = rename_note(id.clone(), "Integration".to_string(), workspace)
+        .await
+        .unwrap();
+    assert_eq!(relinked, vec![calculus_id.clone()]);
+
+    assert!(get_note_id("Integrals".to_string(), workspace)
+        .await
+        .is_err());
+    assert_eq!(
+        get_note_id("Integration".to_string(), workspace)
+            .await
+            .unwrap(),
+        id
+    );
+    let note = read_note(id.clone(), workspace).await.unwrap();
+    assert_eq!(note.note_id, id);
+    assert_eq!(note.document.title(), "Integration");
+    assert_eq!(note.document.body(), text);
+    assert!(note.tags.contains(&tags[0]));
+    {
+        let notes_dir = get_workspace_ref(workspace).unwrap().notes_dir().unwrap();
+        assert!(!notes_dir.join("Integrals.txt").exists());
+        assert!(notes_dir.join("Integration.txt").exists());
+    }
+
+    // Links to the old title are rewritten to the new title
+    let backlinks = get_backlinks(id, workspace).await.unwrap();
+    assert_eq!(backlinks.len(), 1);
+    assert_eq!(backlinks[0].source, calculus_id);
+    let calculus = read_note(calculus_id, workspace).await.unwrap();
+    assert_eq!(
+        calculus.document.body(),
+        "Calculus builds on [[Integration#Area]]."
+    );
+
+    delete_workspace(workspace).unwrap();
+    unload_workspace(workspace).unwrap();
+}
+
+#[tokio::test]
+async fn test_shared_titles() {
+    use crate::indexing::wait_for_indexing;
+    use crate::workspace::{delete_workspace, load_workspace_with, unload_workspace};
+
+    _ = tracing_subscriber::fmt::try_init();
+
+    let temp = std::env::temp_dir();
+    let workspace_path = temp.join("testing-shared-titles-workspace");
+    _ = std::fs::remove_dir_all(&workspace_path);
+    let workspace = load_workspace_with(workspace_path, Some(NotesEmbedder::hashing())).unwrap();
+
+    // Saving without an id always creates a new note, even before the first note is indexed
+    let first = save_note(
+        None,
+        "Integrals".to_string(),
+        "Integrals measure area.".to_string(),
+        workspace,
+    )
+    .await
+    .unwrap();
+    let second = save_note(
+        None,
+        "Integrals".to_string(),
+        "Integrals undo derivatives.".to_string(),
+        workspace,
+    )
+    .await
+    .unwrap();
+    assert_ne!(first, second);
+    let limits = save_note(
+        None,
+        "Limits".to_string(),
+        "Limits approach a value.".to_string(),
+        workspace,
+    )
+    .await
+    .unwrap();
+    wait_for_indexing(workspace).await.unwrap();
+
+    // The second note is stored in a file with its id, so neither note overwrites the other
+    let notes_dir = get_workspace_ref(workspace).unwrap().notes_dir().unwrap();
+    let first_file = notes_dir.join(title_to_file_name("Integrals").unwrap());
+    let second_file = notes_dir.join(title_to_unique_file_name("Integrals", &second).unwrap());
+    assert_eq!(
+        fs::read_to_string(&first_file).unwrap(),
+        "Integrals measure area."
+    );
+    assert_eq!(
+        fs::read_to_string(&second_file).unwrap(),
+        "Integrals undo derivatives."
+    );
+    assert_eq!(
+        read_note(second.clone(), workspace)
+            .await
+            .unwrap()
+            .document
+            .body(),
+        "Integrals undo derivatives."
+    );
+    assert_eq!(
+        get_note_id("Integrals".to_string(), workspace)
+            .await
+            .unwrap(),
+        first
+    );
+
+    // Saving the second note again keeps its file
+    save_note(
+        Some(second.clone()),
+        "Integrals".to_string(),
+        "Integrals undo derivatives and measure area.".to_string(),
+        workspace,
+    )
+    .await
+    .unwrap();
+    wait_for_indexing(workspace).await.unwrap();
+    assert_eq!(
+        fs::read_to_string(&second_file).unwrap(),
+        "Integrals undo derivatives and measure area."
+    );
+    assert_eq!(
+        fs::read_to_string(&first_file).unwrap(),
+        "Integrals measure area."
+    );
+
+    // A note can be renamed to a title that is already used
+    rename_note(limits.clone(), "Integrals".to_string(), workspace)
+        .await
+        .unwrap();
+    assert!(notes_dir
+        .join(title_to_unique_file_name("Integrals", &limits).unwrap())
+        .exists());
+    assert!(!notes_dir
+        .join(title_to_file_name("Limits").unwrap())
+        .exists());
+
+    // Removing one of the notes keeps the others
+    remove_note(first.clone(), workspace).await.unwrap();
+    assert!(!first_file.exists());
+    assert!(second_file.exists());
+    assert_eq!(
+        get_note_id("Integrals".to_string(), workspace)
+            .await
+            .unwrap(),
+        second
+    );
+
+    delete_workspace(workspace).unwrap();
+    unload_workspace(workspace).unwrap();
+}
+
+#[tokio::test]
+async fn test_created_at() {
+    use crate::indexing::wait_for_indexing;
+    use crate::query::TagFilter;
+    use crate::search::{search, SearchMode};
+    use crate::workspace::{delete_workspace, load_workspace_with, unload_workspace};
+
+    _ = tracing_subscriber::fmt::try_init();
+
+    let temp = std::env::temp_dir();
+    let workspace_path = temp.join("testing-created-at-workspace");
+    _ = std::fs::remove_dir_all(&workspace_path);
+    let workspace =
+        load_workspace_with(workspace_path.clone(), Some(NotesEmbedder::hashing())).unwrap();
+    let id = save_note(
+        None,
+        "Integrals".to_string(),
+        "Integrals are the area under a curve.".to_string(),
+        workspace,
+    )
+    .await
+    .unwrap();
+    wait_for_indexing(workspace).await.unwrap();
+
+    // The creation time is stored with the document and in the metadata sidecar
+    let note = read_note(id.clone(), workspace).await.unwrap();
+    assert!(note.created_at > 0);
+    let metadata = read_metadata(&workspace_path);
+    assert_eq!(metadata["Integrals.txt"].created_at, Some(note.created_at));
+
+    // Notes stored before segment hashes and creation times existed are migrated when the workspace is opened
+    let hashed_segments = {
+        let workspace = get_workspace_ref(workspace).unwrap();
+        let document_table = workspace.document_table().await.unwrap();
+        let db = document_table.table().db();
+        db.query("UPDATE type::table($documents) SET created_at = NONE")
+            .bind(("documents", document_table.table().table().to_string()))
+            .await
+            .unwrap()
+            .check()
+            .unwrap();
+        db.query("UPDATE type::table($paths) SET file_key = NONE")
+            .bind(("paths", DOCUMENT_NAME_TABLE))
+            .await
+            .unwrap()
+            .check()
+            .unwrap();
+        let mut segments = select_location(db, &id).await.unwrap().unwrap().segments;
+        let hashed_segments = segments.clone();
+        for segment in &mut segments {
+            segment.hash = None;
+        }
+        db.query("UPDATE type::thing($paths, $note_id) SET segments = $segments")
+            .bind(("paths", DOCUMENT_NAME_TABLE))
+            .bind(("note_id", id.clone()))
+            .bind(("segments", segments))
+            .await
+            .unwrap()
+            .check()
+            .unwrap();
+        hashed_segments
+    };
+    update_metadata(&workspace_path, |metadata| {
+        metadata.get_mut("Integrals.txt").unwrap().created_at = Some(60_000);
+    })
+    .unwrap();
+    crate::manifest::update_manifest(&workspace_path, |manifest| manifest.schema_version = 2)
+        .unwrap();
+    unload_workspace(workspace).unwrap();
+    let workspace =
+        load_workspace_with(workspace_path.clone(), Some(NotesEmbedder::hashing())).unwrap();
+    let note = read_note(id.clone(), workspace).await.unwrap();
+    assert_eq!(note.created_at, 60_000);
+    {
+        let workspace = get_workspace_ref(workspace).unwrap();
+        let document_table = workspace.document_table().await.unwrap();
+        let location = select_location(document_table.table().db(), &id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(location.segments, hashed_segments);
+        assert_eq!(location.file_key, "integrals.txt");
+    }
+    assert_eq!(
+        crate::manifest::read_manifest(&workspace_path)
+            .unwrap()
+            .unwrap()
+            .schema_version,
+        crate::manifest::SCHEMA_VERSION
+    );
+
+    // Search filters on the stored creation time
+    let search_created = |created: &str| {
+        search(
+            format!("created:{created} area"),
+            TagFilter::default(),
+            10,
+            Some(SearchMode::Lexical),
+            None,
+            workspace,
+        )
+    };
+    assert_eq!(search_created("1970-01-01").await.unwrap()[0].id, id);
+    assert!(search_created(">1970-01-01").await.unwrap().is_empty());
+
+    delete_workspace(workspace).unwrap();
+    unload_workspace(workspace).unwrap();
+}
+
+#[tokio::test]
+async fn test_rename_note_relinks_after_multibyte_text() {
+    use crate::workspace::{delete_workspace, load_workspace_with, unload_workspace};
+
+    _ = tracing_subscriber::fmt::try_init();
+
+    let temp = std::env::temp_dir();
+    let workspace_path = temp.join("testing-rename-multibyte-workspace");
+    _ = std::fs::remove_dir_all(&workspace_path);
+    let workspace = load_workspace_with(workspace_path, Some(NotesEmbedder::hashing())).unwrap();
+
+    let id = save_note(
+        None,
+        "Integrals".to_string(),
+        "Integrals measure the area under a curve.".to_string(),
+        workspace,
+    )
+    .await
+    .unwrap();
+    // The links come after characters that take several bytes, so byte and character offsets differ
+    let calculus_id = save_note(
+        None,
+        "Calculus".to_string(),
+        "∫ and ∑ are symbols 📐. Read [[Integrals]] and then [[Integrals#Area]] — é.".to_string(),
+        workspace,
+    )
+    .await
+    .unwrap();
+
+    let relinked = rename_note(id, "Integration".to_string(), workspace)
+        .await
+        .unwrap();
+    assert_eq!(relinked, vec![calculus_id.clone()]);
+    let calculus = read_note(calculus_id, workspace).await.unwrap();
+    assert_eq!(
+        calculus.document.body(),
+        "∫ and ∑ are symbols 📐. Read [[Integration]] and then [[Integration#Area]] — é."
+    );
+
+    delete_workspace(workspace).unwrap();
+    unload_workspace(workspace).unwrap();
+}
+
+#[tokio::test]
+async fn test_reuse_unchanged_embeddings() {
+    use crate::indexing::wait_for_indexing;
+    use crate::workspace::{delete_workspace, load_workspace_with, unload_workspace};
+
+    _ = tracing_subscriber::fmt::try_init();
+
+    // Every sentence gets an embedding and a hash
+    let text =
+        "Limits describe the value a function approaches. Integrals are the area under a curve.";
+    let sentences = chunk_text(text);
+    let embedded = embed_notes(
+        &NotesEmbedder::hashing(),
+        vec![("Calculus".to_string(), text.to_string())],
+    )
+    .await
+    .unwrap();
+    assert_eq!(embedded[0].embeddings.len(), sentences.len());
+    assert_eq!(
+        embedded[0].hashes[0],
+        text_hash(&text[sentences[0].clone()])
+    );
+
+    // Saving a note stores the hash of each sentence, but not its embedding
+    let temp = std::env::temp_dir();
+    let workspace_path = temp.join("testing-incremental-embedding-workspace");
+    _ = std::fs::remove_dir_all(&workspace_path);
+    let workspace = load_workspace_with(workspace_path, Some(NotesEmbedder::hashing())).unwrap();
+    let id = save_note(None, "Calculus".to_string(), text.to_string(), workspace)
+        .await
+        .unwrap();
+    wait_for_indexing(workspace).await.unwrap();
+    #[derive(Deserialize)]
+    struct StoredSegment {
+        hash: Option<String>,
+        embedding: Option<Vec<f32>>,
+    }
+    let segments: Vec<StoredSegment> = {
+        let workspace = get_workspace_ref(workspace).unwrap();
+        let document_table = workspace.document_table().await.unwrap();
+        let mut response = document_table
+            .table()
+            .db()
+            .query("SELECT VALUE segments FROM type::thing($paths, $id)")
+            .bind(("paths", DOCUMENT_NAME_TABLE))
+            .bind(("id", id.to_string()))
+            .await
+            .unwrap();
+        let segments: Option<Vec<StoredSegment>> = response.take(0).unwrap();
+        segments.unwrap()
+    };
+    assert_eq!(segments.len(), sentences.len());
+    assert!(segments.iter().all(|segment| segment.hash.is_some()));
+    assert!(segments.iter().all(|segment| segment.embedding.is_none()));
+
+    // Editing one sentence keeps the hash of the other sentence
+    let edited =
+        "Limits describe the value a function approaches. Integrals sum infinitely small pieces.";
+    let embedded = embed_notes(
+        &NotesEmbedder::hashing(),
+        vec![("Calculus".to_string(), edited.to_string())],
+    )
+    .await
+    .unwrap();
+    let stored = text_segments(text);
+    let edited_segments = text_segments(edited);
+    assert_eq!(stored[0], edited_segments[0]);
+    assert_ne!(stored[1], edited_segments[1]);
+    assert_eq!(embedded[0].embeddings.len(), edited_segments.len());
+
+    // Only the edited sentence runs the model. The other sentence keeps the embedding stored in the document table
+    let (counting, counter) = NotesEmbedder::counting();
+    {
+        let workspace = get_workspace_ref(workspace).unwrap();
+        let document_table = workspace.document_table().await.unwrap();
+        let previous = select_location(document_table.table().db(), &id)
+            .await
+            .unwrap()
+            .unwrap();
+        let reembedded = embed_changed_sentences(
+            &counting,
+            document_table,
+            Some(&previous),
+            "Calculus".to_string(),
+            edited.to_string(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(counter.embedded(), 1);
+        assert_eq!(reembedded.embeddings.len(), edited_segments.len());
+        assert_eq!(
+            reembedded.embeddings[0].to_vec(),
+            embedded[0].embeddings[0].to_vec()
+        );
+    }
+
+    save_note(
+        Some(id.clone()),
+        "Calculus".to_string(),
+        edited.to_string(),
+        workspace,
+    )
+    .await
+    .unwrap();
+    let note = read_note(id, workspace).await.unwrap();
+    assert_eq!(note.document.body(), edited);
+
+    delete_workspace(workspace).unwrap();
+    unload_workspace(workspace).unwrap();
+}
+
+#[tokio::test]
+async fn test_copied_workspace() {
+    use crate::indexing::wait_for_indexing;
+    use crate::workspace::{copy_dir_all, delete_workspace, load_workspace_with, unload_workspace};
+
+    _ = tracing_subscriber::fmt::try_init();
+
+    let temp = std::env::temp_dir();
+    let original_path = temp.join("testing-original-workspace");
+    let copy_path = temp.join("testing-copied-workspace");
+    _ = std::fs::remove_dir_all(&original_path);
+    _ = std::fs::remove_dir_all(&copy_path);
+
+    let workspace =
+        load_workspace_with(original_path.clone(), Some(NotesEmbedder::hashing())).unwrap();
+    let id = save_note(
+        None,
+        "Integrals".to_string(),
+        "Integrals measure area.".to_string(),
+        workspace,
+    )
+    .await
+    .unwrap();
+    wait_for_indexing(workspace).await.unwrap();
+    unload_workspace(workspace).unwrap();
+    copy_dir_all(&original_path, &copy_path).unwrap();
+
+    // Changing the notes in the copy only changes the files in the copy
+    let original_file = original_path
+        .join("notes")
+        .join(title_to_file_name("Integrals").unwrap());
+    let copied_notes = copy_path.join("notes");
+    let copy = load_workspace_with(copy_path.clone(), Some(NotesEmbedder::hashing())).unwrap();
+    save_note(
+        Some(id.clone()),
+        "Integrals".to_string(),
+        "Integrals measure the area under a curve.".to_string(),
+        copy,
+    )
+    .await
+    .unwrap();
+    assert!(original_file.exists());
+    assert_eq!(
+        std::fs::read_to_string(&original_file).unwrap(),
+        "Integrals measure area."
+    );
+
+    rename_note(id.clone(), "Integration".to_string(), copy)
+        .await
+        .unwrap();
+    assert!(original_file.exists());
+    assert!(copied_notes
+        .join(title_to_file_name("Integration").unwrap())
+        .exists());
+
+    remove_note(id, copy).await.unwrap();
+    assert!(original_file.exists());
+    assert!(!copied_notes
+        .join(title_to_file_name("Integration").unwrap())
+        .exists());
+
+    delete_workspace(copy).unwrap();
+    unload_workspace(copy).unwrap();
+    _ = std::fs::remove_dir_all(&original_path);
 }
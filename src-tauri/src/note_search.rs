@@ -0,0 +1,336 @@
+/*!
+# Prologue Comments
+## Name of Code Artifact: Note-level Search Results
+
+## Brief Description: This code groups the sentence results of a search by note. One note with many similar sentences would otherwise fill the whole result list. Each note gets the score of its best sentence and its top matching sentences, and every sentence comes with a snippet of the surrounding sentences and the range to highlight in both UTF-8 and UTF-16 so the frontend can render it directly.
+## Programmer’s Name: agent
+
+## Date Created: 2026-10-17
+## Dates Revised and Description of Revisions:
+## -> 2026-10-17: Added the search_notes command.
+## -> 2026-10-17: Moved loading the note bodies into the search module so diversification can share it.
+
+## Preconditions:
+- The workspace must be loaded.
+
+## Acceptable and Unacceptable Input Values/Types:
+- Each tauri command takes an object with types defined by the type definition in the code. Arguments are represented as
+fields in that object.
+- The query, tags and mode are the same as for `search`.
+
+## Postconditions:
+
+- Every note appears at most once in the results.
+
+## Return Values/Types:
+
+- `search_notes` returns the matching notes ordered from the most to the least relevant, or a `LinknotesError` if the search fails.
+
+## Side Effects:
+-  None
+
+## Invariants:
+
+- The highlight range of a snippet is always inside the text of the snippet.
+
+## Known Faults:
+
+- Notes are found from the best `results * CANDIDATES_PER_NOTE` sentences, so a note whose sentences all rank below that is missing even if fewer than `results` notes are returned.
+
+*/
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::ops::Range;
+
+#[cfg(test)]
+use pretty_assertions::assert_eq;
+
+use crate::classifier::chunk_text;
+use crate::error::LinknotesError;
+use crate::note::NoteId;
+use crate::query::TagFilter;
+use crate::search::{
+    byte_range, note_bodies, search, utf8_range_to_utf16_range, SearchMode, SearchResult,
+};
+use crate::workspace::{get_workspace_ref, WorkspaceId};
+
+/// The number of sentences searched for every note that is returned
+const CANDIDATES_PER_NOTE: usize = 10;
+/// The number of sentences returned for every note if the frontend doesn't pick a number
+const DEFAULT_HITS_PER_NOTE: usize = 3;
+/// The number of sentences of context on each side of a hit if the frontend doesn't pick a number
+const DEFAULT_CONTEXT_SENTENCES: usize = 1;
+
+/// A passage of a note around a search hit.
+///
+/// # Fields
+///
+/// * `text` - The sentences around the hit.
+/// * `highlight_utf8` - The byte range of the hit in `text`.
+/// * `highlight_utf16` - The UTF-16 range of the hit in `text` for javascript strings.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Snippet {
+    pub text: String,
+    pub highlight_utf8: Range<usize>,
+    pub highlight_utf16: Range<usize>,
+}
+
+/// A sentence of a note that matched the search.
+///
+/// # Fields
+///
+/// * `score` - How relevant the sentence is. See [`SearchResult::score`].
+/// * `distance` - The embedding distance between the query and the sentence, or `None` if only the full-text index found it.
+/// * `character_range` - The range of characters of the sentence in the note.
+/// * `snippet` - The sentence with the sentences around it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SentenceHit {
+    pub score: f32,
+    pub distance: Option<f32>,
+    pub character_range: Range<usize>,
+    pub snippet: Snippet,
+}
+
+/// A note that matched the search.
+///
+/// # Fields
+///
+/// * `id` - The id of the note.
+/// * `title` - The title of the note.
+/// * `score` - The score of the best sentence in the note.
+/// * `hits` - The best sentences in the note, from the most to the least relevant.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NoteSearchResult {
+    pub id: NoteId,
+    pub title: String,
+    pub score: f32,
+    pub hits: Vec<SentenceHit>,
+}
+
+/// Cut the sentences around a byte range out of a note. `context_sentences` sentences before and after the sentences
+/// of the range are included.
+fn snippet(body: &str, hit: Range<usize>, context_sentences: usize) -> Snippet {
+    let sentences = chunk_text(body);
+    let sentence_of = |index: usize| sentences.iter().position(|sentence| index < sentence.end);
+    let window = match (
+        sentence_of(hit.start),
+        sentence_of(hit.end.saturating_sub(1)),
+    ) {
+        (Some(first), last) => {
+            let last = last.unwrap_or(sentences.len() - 1);
+            let first = first.saturating_sub(context_sentences);
+            let last = (last + context_sentences).min(sentences.len() - 1);
+            sentences[first].start.min(hit.start)..sentences[last].end.max(hit.end)
+        }
+        // The hit is past the last sentence, or the note has no sentences
+        (None, _) => hit.clone(),
+    };
+    let text = body[window.clone()].to_string();
+    let highlight_utf8 = hit.start - window.start..hit.end - window.start;
+    let highlight_utf16 = utf8_range_to_utf16_range(highlight_utf8.clone(), &text);
+    Snippet {
+        text,
+        highlight_utf8,
+        highlight_utf16,
+    }
+}
+
+/// Group sentence results by note. Results must be ordered from the most to the least relevant, so each note keeps its
+/// best sentences and the notes are ordered by their best sentence.
+fn group_results(
+    sentences: Vec<SearchResult>,
+    bodies: &HashMap<NoteId, String>,
+    results: usize,
+    hits_per_note: usize,
+    context_sentences: usize,
+) -> Vec<NoteSearchResult> {
+    let mut notes: Vec<NoteSearchResult> = Vec::new();
+    for sentence in sentences {
+        let Some(body) = bodies.get(&sentence.id) else {
+            // The note was removed after the search
+            continue;
+        };
+        let index = match notes.iter().position(|note| note.id == sentence.id) {
+            Some(index) => index,
+            None if notes.len() < results => {
+                notes.push(NoteSearchResult {
+                    id: sentence.id.clone(),
+                    title: sentence.title.clone(),
+                    score: sentence.score,
+                    hits: Vec::new(),
+                });
+                notes.len() - 1
+            }
+            None => continue,
+        };
+        let note = &mut notes[index];
+        if note.hits.len() < hits_per_note {
+            let hit = byte_range(body, sentence.character_range.clone());
+            note.hits.push(SentenceHit {
+                score: sentence.score,
+                distance: sentence.distance,
+                character_range: sentence.character_range,
+                snippet: snippet(body, hit, context_sentences),
+            });
+        }
+    }
+    notes
+}
+
+/// Search the notes in a workspace and return one result per note instead of one result per sentence.
+///
+/// # Arguments
+///
+/// * `text` - The search query. See [`search`].
+/// * `tags` - The tags the returned notes must have. See [`search`].
+/// * `results` - The number of notes to return.
+/// * `mode` - `semantic`, `lexical` or `hybrid`. Defaults to `hybrid`.
+/// * `hits_per_note` - The number of sentences to return for every note. Defaults to 3.
+/// * `context_sentences` - The number of sentences around each hit to include in its snippet. Defaults to 1.
+/// * `workspace_id` - The ID of the workspace to search in.
+///
+/// # Errors
+///
+/// This function will return an error if the search fails. See [`search`].
+#[tauri::command]
+pub async fn search_notes(
+    text: String,
+    tags: TagFilter,
+    results: usize,
+    mode: Option<SearchMode>,
+    hits_per_note: Option<usize>,
+    context_sentences: Option<usize>,
+    workspace_id: WorkspaceId,
+) -> Result<Vec<NoteSearchResult>, LinknotesError> {
+    tracing::info!(
+        "search_notes called with text {:?}, tags {:?} and mode {:?}",
+        text,
+        tags,
+        mode
+    );
+    let sentences = search(
+        text,
+        tags,
+        results.saturating_mul(CANDIDATES_PER_NOTE),
+        mode,
+        None,
+        workspace_id,
+    )
+    .await?;
+
+    let bodies = {
+        let workspace = get_workspace_ref(workspace_id)?;
+        let document_table = workspace.document_table().await?;
+        note_bodies(document_table, &sentences).await?
+    };
+
+    Ok(group_results(
+        sentences,
+        &bodies,
+        results,
+        hits_per_note.unwrap_or(DEFAULT_HITS_PER_NOTE),
+        context_sentences.unwrap_or(DEFAULT_CONTEXT_SENTENCES),
+    ))
+}
+
+#[test]
+fn test_group_results() {
+    let note = NoteId::new();
+    let other_note = NoteId::new();
+    let body = "Cats sleep. 🐈 Cats purr loudly. Dogs bark. Birds sing.".to_string();
+    let bodies = HashMap::from([(note.clone(), body.clone()), (other_note.clone(), body)]);
+    let result = |id: &NoteId, character_range: Range<usize>, score: f32| SearchResult {
+        distance: Some(1.0 - score),
+        score,
+        id: id.clone(),
+        title: id.to_string(),
+        character_range,
+    };
+    let sentences = vec![
+        result(&note, 12..32, 0.9),
+        result(&note, 32..43, 0.8),
+        result(&other_note, 0..12, 0.7),
+        result(&note, 0..12, 0.6),
+    ];
+
+    // Each note shows up once with its best sentences
+    let notes = group_results(sentences.clone(), &bodies, 10, 2, 1);
+    assert_eq!(notes.len(), 2);
+    assert_eq!(notes[0].id, note);
+    assert_eq!(notes[0].score, 0.9);
+    assert_eq!(notes[0].hits.len(), 2);
+    assert_eq!(notes[1].id, other_note);
+
+    // The snippet has the sentences around the hit and the highlight is the hit
+    let snippet = &notes[0].hits[0].snippet;
+    assert_eq!(
+        snippet.text.trim(),
+        "Cats sleep. 🐈 Cats purr loudly. Dogs bark."
+    );
+    assert_eq!(
+        &snippet.text[snippet.highlight_utf8.clone()],
+        "🐈 Cats purr loudly. "
+    );
+    assert_eq!(snippet.highlight_utf8, 12..35);
+    assert_eq!(snippet.highlight_utf16, 12..33);
+
+    // Without context the snippet is only the hit
+    let notes = group_results(sentences, &bodies, 1, 1, 0);
+    assert_eq!(notes.len(), 1);
+    assert_eq!(notes[0].hits[0].snippet.text, "🐈 Cats purr loudly. ");
+}
+
+#[tokio::test]
+async fn test_search_notes() {
+    use crate::embedder::NotesEmbedder;
+    use crate::workspace::load_workspace_with;
+    use crate::{delete_workspace, save_note, unload_workspace};
+    _ = tracing_subscriber::fmt::try_init();
+
+    let workspace_path = std::env::temp_dir().join("testing-search-notes-workspace");
+    _ = std::fs::remove_dir_all(&workspace_path);
+    let workspace = load_workspace_with(workspace_path, Some(NotesEmbedder::hashing())).unwrap();
+
+    let cats = save_note(
+        None,
+        "Cats".to_string(),
+        "Cats sleep all day. Cats sleep all night. Cats sleep in the sun. Dogs bark.".to_string(),
+        workspace,
+    )
+    .await
+    .unwrap();
+    let naps = save_note(
+        None,
+        "Naps".to_string(),
+        "People sleep after lunch.".to_string(),
+        workspace,
+    )
+    .await
+    .unwrap();
+
+    let notes = search_notes(
+        "sleep".to_string(),
+        TagFilter::default(),
+        10,
+        Some(SearchMode::Semantic),
+        Some(2),
+        Some(1),
+        workspace,
+    )
+    .await
+    .unwrap();
+    let ids: Vec<NoteId> = notes.iter().map(|note| note.id.clone()).collect();
+    assert!(ids.contains(&cats));
+    assert!(ids.contains(&naps));
+    let cats = notes.iter().find(|note| note.id == cats).unwrap();
+    assert_eq!(cats.hits.len(), 2);
+    for hit in &cats.hits {
+        let highlight = &hit.snippet.text[hit.snippet.highlight_utf8.clone()];
+        assert!(highlight.contains("sleep"));
+        assert!(hit.snippet.text.len() > highlight.len());
+    }
+
+    delete_workspace(workspace).unwrap();
+    unload_workspace(workspace).unwrap();
+}
@@ -0,0 +1,781 @@
+/*!
+# Prologue Comments
+## Name of Code Artifact: Search Query Language
+
+## Brief Description: This code parses the search box into a query. Plain words and quoted phrases describe what the notes are about, and operators filter the notes: `tag:Math` keeps notes with a tag, `title:"integrals"` keeps notes whose title contains the text, `created:>2024-10-01` keeps notes created after a date, `-` excludes notes, `OR` matches either side and parentheses group terms. The parser produces an AST that is compiled into a SurrealDB filter plus the text for the semantic search.
+## Programmer’s Name: agent
+
+## Date Created: 2026-10-17
+## Dates Revised and Description of Revisions:
+## -> 2026-10-17: Added the query parser and the parse_search_query command.
+## -> 2026-10-17: Added tag filters with any-of, all-of and none-of tags and a manual-only flag.
+## -> 2026-10-17: Compared dates with the creation time stored with every note instead of the time in the note id.
+
+## Preconditions:
+- None
+
+## Acceptable and Unacceptable Input Values/Types:
+- Terms next to each other must all match. `OR` and `AND` must be upper case to be operators.
+- Field names are case insensitive. Words with other field names like `C:` are plain words.
+- Dates are written as `YYYY-MM-DD` in UTC and can be prefixed with `>`, `>=`, `<`, `<=` or `=`.
+- Unterminated quotes, empty phrases, operators without a term, unbalanced parentheses and invalid dates are errors.
+- A tag filter is either a list of tags the note must all have, or an object with `all`, `any` and `exclude` lists and a `manual_only` flag.
+
+## Postconditions:
+
+- Every value in the query is bound as a parameter, so the query can't change the SurrealDB statement.
+
+## Return Values/Types:
+
+- `parse_search_query` returns the `QueryExpr` of the query, or a `LinknotesError::InvalidQuery` with the UTF-16 range of the mistake.
+
+## Side Effects:
+-  None
+
+## Invariants:
+
+- Plain words only rank results. They never filter results out unless they are excluded with `-`.
+
+## Known Faults:
+
+- Notes stored before creation times were recorded have the time their file was created, or the time it last changed on filesystems without creation times.
+
+*/
+
+use serde::{Deserialize, Serialize};
+use std::ops::Range;
+
+#[cfg(test)]
+use pretty_assertions::assert_eq;
+
+use crate::error::LinknotesError;
+use crate::search::utf8_range_to_utf16_range;
+
+/// How a date in a `created:` term is compared.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DateComparison {
+    Before,
+    BeforeOrOn,
+    On,
+    OnOrAfter,
+    After,
+}
+
+/// A calendar date in UTC.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QueryDate {
+    pub year: i32,
+    pub month: u32,
+    pub day: u32,
+}
+
+impl QueryDate {
+    /// The number of days from the unix epoch to the date
+    fn days_since_epoch(&self) -> i64 {
+        // Howard Hinnant's days_from_civil algorithm
+        let year = self.year as i64 - if self.month <= 2 { 1 } else { 0 };
+        let era = year.div_euclid(400);
+        let year_of_era = year - era * 400;
+        let month = self.month as i64;
+        let day_of_year =
+            (153 * (month + if month > 2 { -3 } else { 9 }) + 2) / 5 + self.day as i64 - 1;
+        let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+        era * 146097 + day_of_era - 719468
+    }
+
+    /// The start of the date or of the day `days` days later in milliseconds since the unix epoch
+    fn start_millis(&self, days: i64) -> u64 {
+        (self.days_since_epoch() + days).max(0) as u64 * 24 * 60 * 60 * 1000
+    }
+}
+
+/// A parsed search query.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", content = "value")]
+pub enum QueryExpr {
+    /// A plain word. Words rank the results by meaning
+    Word(String),
+    /// A quoted phrase. The note must contain the phrase
+    Phrase(String),
+    /// `tag:name`. The note must have the tag
+    Tag(String),
+    /// `title:text`. The title of the note must contain the text
+    Title(String),
+    /// `created:>date`. The note must have been created in the range
+    Created {
+        comparison: DateComparison,
+        date: QueryDate,
+    },
+    /// `-term`. The note must not match the term
+    Not(Box<QueryExpr>),
+    /// Terms next to each other or joined with `AND`. The note must match every term
+    And(Vec<QueryExpr>),
+    /// Terms joined with `OR`. The note must match one of the terms
+    Or(Vec<QueryExpr>),
+}
+
+/// A mistake in a search query.
+///
+/// # Fields
+///
+/// * `message` - What is wrong with the query.
+/// * `range` - The UTF-16 range of the mistake in the query, so the frontend can underline it.
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[error("{message}")]
+pub struct QueryError {
+    pub message: String,
+    pub range: Range<usize>,
+}
+
+/// A query compiled for SurrealDB.
+///
+/// # Fields
+///
+/// * `text` - The words and phrases of the query for the semantic and lexical search.
+/// * `filter` - A SurrealQL condition the documents must match, or `None` if every document matches.
+/// * `bindings` - The values of the parameters in the filter.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(crate) struct CompiledQuery {
+    pub(crate) text: String,
+    pub(crate) filter: Option<String>,
+    pub(crate) bindings: Vec<(String, String)>,
+}
+
+impl CompiledQuery {
+    /// The filter as an extra condition for a `WHERE` clause
+    pub(crate) fn and_filter(&self) -> String {
+        self.filter
+            .as_ref()
+            .map(|filter| format!(" AND ({filter})"))
+            .unwrap_or_default()
+    }
+}
+
+/// Which tags the notes a search returns must have.
+///
+/// # Fields
+///
+/// * `all` - The note must have every one of these tags.
+/// * `any` - The note must have at least one of these tags. An empty list allows every note.
+/// * `exclude` - The note must not have any of these tags.
+/// * `manual_only` - Only count tags the user added, not tags the classifier guessed. This also applies to `tag:` terms
+///   in the query.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(from = "TagFilterInput")]
+pub struct TagFilter {
+    pub all: Vec<String>,
+    pub any: Vec<String>,
+    pub exclude: Vec<String>,
+    pub manual_only: bool,
+}
+
+impl TagFilter {
+    /// The terms the tags add to a query
+    fn terms(&self) -> Vec<QueryExpr> {
+        let tag = |name: &String| QueryExpr::Tag(name.clone());
+        let mut terms: Vec<QueryExpr> = self.all.iter().map(tag).collect();
+        if !self.any.is_empty() {
+            terms.push(QueryExpr::Or(self.any.iter().map(tag).collect()));
+        }
+        terms.extend(
+            self.exclude
+                .iter()
+                .map(|name| QueryExpr::Not(Box::new(tag(name)))),
+        );
+        terms
+    }
+}
+
+impl From<Vec<String>> for TagFilter {
+    fn from(all: Vec<String>) -> Self {
+        Self {
+            all,
+            ..Default::default()
+        }
+    }
+}
+
+/// A tag filter as the frontend sends it. A plain list of tags is the tags the note must all have
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum TagFilterInput {
+    All(Vec<String>),
+    Filter {
+        #[serde(default)]
+        all: Vec<String>,
+        #[serde(default)]
+        any: Vec<String>,
+        #[serde(default)]
+        exclude: Vec<String>,
+        #[serde(default)]
+        manual_only: bool,
+    },
+}
+
+impl From<TagFilterInput> for TagFilter {
+    fn from(input: TagFilterInput) -> Self {
+        match input {
+            TagFilterInput::All(all) => all.into(),
+            TagFilterInput::Filter {
+                all,
+                any,
+                exclude,
+                manual_only,
+            } => Self {
+                all,
+                any,
+                exclude,
+                manual_only,
+            },
+        }
+    }
+}
+
+/// A token of a search query
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Word(String),
+    Phrase(String),
+    Field(String, String),
+    Minus,
+    Or,
+    And,
+    Open,
+    Close,
+}
+
+/// The fields that are operators. Other words with a colon are plain words
+const FIELDS: &[&str] = &["tag", "title", "created"];
+
+struct Lexer<'a> {
+    input: &'a str,
+    position: usize,
+}
+
+impl<'a> Lexer<'a> {
+    fn error(&self, message: impl Into<String>, range: Range<usize>) -> QueryError {
+        QueryError {
+            message: message.into(),
+            range: utf8_range_to_utf16_range(range, self.input),
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.input[self.position..].chars().next()
+    }
+
+    /// Read a quoted phrase starting at the opening quote
+    fn phrase(&mut self) -> Result<String, QueryError> {
+        let start = self.position;
+        self.position += 1;
+        match self.input[self.position..].find('"') {
+            Some(end) => {
+                let phrase = self.input[self.position..self.position + end].to_string();
+                self.position += end + 1;
+                if phrase.trim().is_empty() {
+                    return Err(self.error("the phrase is empty", start..self.position));
+                }
+                Ok(phrase)
+            }
+            None => Err(self.error("the quote is never closed", start..self.input.len())),
+        }
+    }
+
+    /// Read a word up to the next whitespace, quote or parenthesis
+    fn word(&mut self) -> &'a str {
+        let rest = &self.input[self.position..];
+        let end = rest
+            .find(|c: char| c.is_whitespace() || matches!(c, '"' | '(' | ')'))
+            .unwrap_or(rest.len());
+        self.position += end;
+        &rest[..end]
+    }
+
+    fn tokens(mut self) -> Result<Vec<(Token, Range<usize>)>, QueryError> {
+        let mut tokens = Vec::new();
+        while let Some(c) = self.peek() {
+            let start = self.position;
+            let token = match c {
+                c if c.is_whitespace() => {
+                    self.position += c.len_utf8();
+                    continue;
+                }
+                '(' => {
+                    self.position += 1;
+                    Token::Open
+                }
+                ')' => {
+                    self.position += 1;
+                    Token::Close
+                }
+                '"' => Token::Phrase(self.phrase()?),
+                // A dash is only an operator when it is directly in front of a term
+                '-' if self.input[start + 1..]
+                    .chars()
+                    .next()
+                    .is_some_and(|next| !next.is_whitespace() && next != ')') =>
+                {
+                    self.position += 1;
+                    Token::Minus
+                }
+                _ => {
+                    let word = self.word();
+                    let field = word.split_once(':').and_then(|(name, value)| {
+                        let name = name.to_lowercase();
+                        FIELDS.contains(&name.as_str()).then_some((name, value))
+                    });
+                    match field {
+                        Some((name, value)) => {
+                            let value = if value.is_empty() && self.peek() == Some('"') {
+                                self.phrase()?
+                            } else {
+                                value.to_string()
+                            };
+                            if value.is_empty() {
+                                return Err(self.error(
+                                    format!("{name}: needs a value"),
+                                    start..self.position,
+                                ));
+                            }
+                            Token::Field(name, value)
+                        }
+                        None if word == "OR" => Token::Or,
+                        None if word == "AND" => Token::And,
+                        None => Token::Word(word.to_string()),
+                    }
+                }
+            };
+            tokens.push((token, start..self.position));
+        }
+        Ok(tokens)
+    }
+}
+
+struct Parser<'a> {
+    input: &'a str,
+    tokens: Vec<(Token, Range<usize>)>,
+    position: usize,
+}
+
+impl Parser<'_> {
+    fn error(&self, message: impl Into<String>, range: Range<usize>) -> QueryError {
+        QueryError {
+            message: message.into(),
+            range: utf8_range_to_utf16_range(range, self.input),
+        }
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position).map(|(token, _)| token)
+    }
+
+    /// The range of the current token, or an empty range at the end of the query
+    fn range(&self) -> Range<usize> {
+        self.tokens
+            .get(self.position)
+            .map(|(_, range)| range.clone())
+            .unwrap_or(self.input.len()..self.input.len())
+    }
+
+    /// or := and ("OR" and)*
+    fn or(&mut self) -> Result<QueryExpr, QueryError> {
+        let mut terms = vec![self.and()?];
+        while self.peek() == Some(&Token::Or) {
+            self.position += 1;
+            terms.push(self.and()?);
+        }
+        Ok(if terms.len() == 1 {
+            terms.remove(0)
+        } else {
+            QueryExpr::Or(terms)
+        })
+    }
+
+    /// and := unary (("AND")? unary)*
+    fn and(&mut self) -> Result<QueryExpr, QueryError> {
+        let mut terms = vec![self.unary()?];
+        loop {
+            match self.peek() {
+                Some(Token::And) => {
+                    self.position += 1;
+                    terms.push(self.unary()?);
+                }
+                None | Some(Token::Or) | Some(Token::Close) => break,
+                Some(_) => terms.push(self.unary()?),
+            }
+        }
+        Ok(if terms.len() == 1 {
+            terms.remove(0)
+        } else {
+            QueryExpr::And(terms)
+        })
+    }
+
+    /// unary := "-" unary | "(" or ")" | term
+    fn unary(&mut self) -> Result<QueryExpr, QueryError> {
+        let range = self.range();
+        let Some((token, _)) = self.tokens.get(self.position).cloned() else {
+            return Err(self.error("expected a search term", range));
+        };
+        self.position += 1;
+        match token {
+            Token::Minus => Ok(QueryExpr::Not(Box::new(self.unary()?))),
+            Token::Open => {
+                let inner = self.or()?;
+                if self.peek() != Some(&Token::Close) {
+                    return Err(self.error("the parenthesis is never closed", range));
+                }
+                self.position += 1;
+                Ok(inner)
+            }
+            Token::Close => Err(self.error("there is no parenthesis to close", range)),
+            Token::Or | Token::And => Err(self.error("expected a search term", range)),
+            Token::Word(word) => Ok(QueryExpr::Word(word)),
+            Token::Phrase(phrase) => Ok(QueryExpr::Phrase(phrase)),
+            Token::Field(name, value) => self.field(&name, value, range),
+        }
+    }
+
+    fn field(
+        &self,
+        name: &str,
+        value: String,
+        range: Range<usize>,
+    ) -> Result<QueryExpr, QueryError> {
+        match name {
+            "tag" => Ok(QueryExpr::Tag(value)),
+            "title" => Ok(QueryExpr::Title(value)),
+            _ => {
+                let (comparison, date) = [
+                    (">=", DateComparison::OnOrAfter),
+                    ("<=", DateComparison::BeforeOrOn),
+                    (">", DateComparison::After),
+                    ("<", DateComparison::Before),
+                    ("=", DateComparison::On),
+                ]
+                .into_iter()
+                .find_map(|(prefix, comparison)| {
+                    value.strip_prefix(prefix).map(|date| (comparison, date))
+                })
+                .unwrap_or((DateComparison::On, value.as_str()));
+                let date = parse_date(date).ok_or_else(|| {
+                    self.error(format!("{date:?} is not a date like 2024-10-01"), range)
+                })?;
+                Ok(QueryExpr::Created { comparison, date })
+            }
+        }
+    }
+}
+
+/// Parse a `YYYY-MM-DD` date
+fn parse_date(date: &str) -> Option<QueryDate> {
+    let mut parts = date.split('-');
+    let year: i32 = parts.next()?.parse().ok()?;
+    let month: u32 = parts.next()?.parse().ok()?;
+    let day: u32 = parts.next()?.parse().ok()?;
+    if parts.next().is_some() || !(1..=12).contains(&month) {
+        return None;
+    }
+    let leap = year % 4 == 0 && (year % 100 != 0 || year % 400 == 0);
+    let days_in_month = match month {
+        2 if leap => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    };
+    (1..=days_in_month)
+        .contains(&day)
+        .then_some(QueryDate { year, month, day })
+}
+
+/// Parse a search query. Returns `None` if the query is empty.
+///
+/// # Errors
+///
+/// Returns a `QueryError` with the range of the first mistake in the query.
+pub fn parse_query(input: &str) -> Result<Option<QueryExpr>, QueryError> {
+    let tokens = Lexer { input, position: 0 }.tokens()?;
+    if tokens.is_empty() {
+        return Ok(None);
+    }
+    let mut parser = Parser {
+        input,
+        tokens,
+        position: 0,
+    };
+    let expr = parser.or()?;
+    if parser.position < parser.tokens.len() {
+        // The only token `or` stops at without consuming it is a closing parenthesis
+        return Err(parser.error("there is no parenthesis to close", parser.range()));
+    }
+    Ok(Some(expr))
+}
+
+/// Collects the text and parameters while a query is compiled
+struct Compiler {
+    text: Vec<String>,
+    bindings: Vec<(String, String)>,
+    /// The tag names `tag:` terms are compared with
+    tag_names: &'static str,
+}
+
+impl Compiler {
+    /// Bind a value to a new parameter and return the name of the parameter in the query
+    fn bind(&mut self, value: String) -> String {
+        let name = format!("q{}", self.bindings.len());
+        self.bindings.push((name.clone(), value));
+        format!("${name}")
+    }
+
+    /// The condition for a term. `None` means every document matches. Words only rank results, so they match every
+    /// document unless they are excluded
+    fn condition(&mut self, expr: &QueryExpr, negated: bool) -> Option<String> {
+        match expr {
+            QueryExpr::Word(word) if negated => {
+                let word = self.bind(word.to_lowercase());
+                Some(format!(
+                    "string::contains(string::lowercase(document.body), {word})"
+                ))
+            }
+            QueryExpr::Word(word) => {
+                self.text.push(word.clone());
+                None
+            }
+            QueryExpr::Phrase(phrase) => {
+                if !negated {
+                    self.text.push(phrase.clone());
+                }
+                let phrase = self.bind(phrase.to_lowercase());
+                Some(format!(
+                    "string::contains(string::lowercase(document.body), {phrase})"
+                ))
+            }
+            QueryExpr::Tag(tag) => {
+                let tag = self.bind(tag.clone());
+                Some(format!("{tag} INSIDE {}", self.tag_names))
+            }
+            QueryExpr::Title(title) => {
+                let title = self.bind(title.to_lowercase());
+                Some(format!(
+                    "string::contains(string::lowercase(document.title), {title})"
+                ))
+            }
+            QueryExpr::Created { comparison, date } => {
+                // The time is a number computed from the parsed date, so it can't change the query
+                let bound = |days: i64, operator: &str| {
+                    format!("created_at {operator} {}", date.start_millis(days))
+                };
+                Some(match comparison {
+                    DateComparison::Before => bound(0, "<"),
+                    DateComparison::BeforeOrOn => bound(1, "<"),
+                    DateComparison::OnOrAfter => bound(0, ">="),
+                    DateComparison::After => bound(1, ">="),
+                    DateComparison::On => {
+                        let start = bound(0, ">=");
+                        let end = bound(1, "<");
+                        format!("({start} AND {end})")
+                    }
+                })
+            }
+            QueryExpr::Not(inner) => self
+                .condition(inner, !negated)
+                .map(|condition| format!("!({condition})")),
+            QueryExpr::And(terms) => {
+                let conditions: Vec<String> = terms
+                    .iter()
+                    .filter_map(|term| self.condition(term, negated))
+                    .collect();
+                (!conditions.is_empty()).then(|| format!("({})", conditions.join(" AND ")))
+            }
+            QueryExpr::Or(terms) => {
+                let conditions: Vec<Option<String>> = terms
+                    .iter()
+                    .map(|term| self.condition(term, negated))
+                    .collect();
+                // A term that matches every document makes the whole OR match every document
+                let conditions: Option<Vec<String>> = conditions.into_iter().collect();
+                conditions.map(|conditions| format!("({})", conditions.join(" OR ")))
+            }
+        }
+    }
+}
+
+/// Compile a parsed query and a tag filter into a SurrealDB filter and the text for the semantic search
+pub(crate) fn compile_query(expr: Option<&QueryExpr>, tags: &TagFilter) -> CompiledQuery {
+    let mut compiler = Compiler {
+        text: Vec::new(),
+        bindings: Vec::new(),
+        tag_names: if tags.manual_only {
+            "tags[WHERE manual = true].name"
+        } else {
+            "tags.name"
+        },
+    };
+    let mut terms: Vec<QueryExpr> = expr.cloned().into_iter().collect();
+    terms.extend(tags.terms());
+    let expr = match terms.len() {
+        0 => None,
+        1 => terms.pop(),
+        _ => Some(QueryExpr::And(terms)),
+    };
+    let filter = expr.and_then(|expr| compiler.condition(&expr, false));
+    CompiledQuery {
+        text: compiler.text.join(" "),
+        filter,
+        bindings: compiler.bindings,
+    }
+}
+
+/// Parse and compile a search query with a tag filter.
+///
+/// # Errors
+///
+/// Returns `LinknotesError::InvalidQuery` if the query can't be parsed.
+pub(crate) fn prepare_query(
+    input: &str,
+    tags: &TagFilter,
+) -> Result<CompiledQuery, LinknotesError> {
+    let expr = parse_query(input)?;
+    Ok(compile_query(expr.as_ref(), tags))
+}
+
+/// Parse a search query without running it, so the frontend can underline mistakes while the query is typed.
+///
+/// # Errors
+///
+/// Returns `LinknotesError::InvalidQuery` with the UTF-16 range of the first mistake in the query.
+#[tauri::command]
+pub fn parse_search_query(query: String) -> Result<Option<QueryExpr>, LinknotesError> {
+    tracing::info!("parse_search_query called with query {:?}", query);
+    parse_query(&query).map_err(LinknotesError::from)
+}
+
+#[test]
+fn test_parse_query() {
+    let word = |word: &str| QueryExpr::Word(word.to_string());
+    assert_eq!(parse_query("  "), Ok(None));
+    assert_eq!(
+        parse_query("my note is here"),
+        Ok(Some(QueryExpr::And(vec![
+            word("my"),
+            word("note"),
+            word("is"),
+            word("here")
+        ])))
+    );
+    assert_eq!(
+        parse_query(
+            r#"tag:Math -tag:Draft title:"integrals" "exact phrase" created:>2024-10-01 OR limits"#
+        ),
+        Ok(Some(QueryExpr::Or(vec![
+            QueryExpr::And(vec![
+                QueryExpr::Tag("Math".to_string()),
+                QueryExpr::Not(Box::new(QueryExpr::Tag("Draft".to_string()))),
+                QueryExpr::Title("integrals".to_string()),
+                QueryExpr::Phrase("exact phrase".to_string()),
+                QueryExpr::Created {
+                    comparison: DateComparison::After,
+                    date: QueryDate {
+                        year: 2024,
+                        month: 10,
+                        day: 1
+                    }
+                },
+            ]),
+            word("limits"),
+        ])))
+    );
+    // Words that look like other fields and dashes between words are plain words
+    assert_eq!(
+        parse_query("C:drive - (a OR b)"),
+        Ok(Some(QueryExpr::And(vec![
+            word("C:drive"),
+            word("-"),
+            QueryExpr::Or(vec![word("a"), word("b")]),
+        ])))
+    );
+
+    // Errors point at the mistake in UTF-16 units
+    let error = |query: &str| parse_query(query).unwrap_err();
+    assert_eq!(error(r#"📝 "open"#).range, 3..8);
+    assert_eq!(error("(integrals").range, 0..1);
+    assert_eq!(error("integrals)").range, 9..10);
+    assert_eq!(error("a OR").range, 4..4);
+    assert_eq!(error("tag:").range, 0..4);
+    assert_eq!(error("created:2024-02-30").range, 0..18);
+    assert_eq!(error(r#"x """#).range, 2..4);
+}
+
+#[test]
+fn test_compile_query() {
+    let query = prepare_query(
+        r#"integrals -tag:Draft "area under" -limits"#,
+        &TagFilter::default(),
+    )
+    .unwrap();
+    assert_eq!(query.text, "integrals area under");
+    assert_eq!(
+        query.filter.as_deref(),
+        Some("(!($q0 INSIDE tags.name) AND string::contains(string::lowercase(document.body), $q1) AND !(string::contains(string::lowercase(document.body), $q2)))")
+    );
+    assert_eq!(
+        query.bindings,
+        vec![
+            ("q0".to_string(), "Draft".to_string()),
+            ("q1".to_string(), "area under".to_string()),
+            ("q2".to_string(), "limits".to_string()),
+        ]
+    );
+
+    // Words only rank results, so an OR with a word matches every note
+    let query = prepare_query("tag:Math OR integrals", &TagFilter::default()).unwrap();
+    assert_eq!(query.filter, None);
+
+    // Dates compare the creation time of the note
+    let query = prepare_query("created:2024-10-01", &TagFilter::default()).unwrap();
+    assert_eq!(
+        query.filter.as_deref(),
+        Some("(created_at >= 1727740800000 AND created_at < 1727827200000)")
+    );
+    assert!(query.bindings.is_empty());
+
+    assert!(matches!(
+        prepare_query("tag:Math OR", &TagFilter::default()),
+        Err(LinknotesError::InvalidQuery(_))
+    ));
+}
+
+#[test]
+fn test_tag_filter() {
+    // A list of tags is the tags the note must all have
+    let tags: TagFilter = serde_json::from_value(serde_json::json!(["Math", "Calculus"])).unwrap();
+    assert_eq!(
+        tags,
+        vec!["Math".to_string(), "Calculus".to_string()].into()
+    );
+    let query = prepare_query("", &tags).unwrap();
+    assert_eq!(
+        query.filter.as_deref(),
+        Some("($q0 INSIDE tags.name AND $q1 INSIDE tags.name)")
+    );
+
+    let tags: TagFilter = serde_json::from_value(serde_json::json!({
+        "any": ["Math", "Physics"],
+        "exclude": ["Draft"],
+        "manual_only": true
+    }))
+    .unwrap();
+    let query = prepare_query("tag:Calculus", &tags).unwrap();
+    assert_eq!(
+        query.filter.as_deref(),
+        Some("($q0 INSIDE tags[WHERE manual = true].name AND ($q1 INSIDE tags[WHERE manual = true].name OR $q2 INSIDE tags[WHERE manual = true].name) AND !($q3 INSIDE tags[WHERE manual = true].name))")
+    );
+    assert_eq!(
+        query
+            .bindings
+            .iter()
+            .map(|(_, value)| value.as_str())
+            .collect::<Vec<_>>(),
+        vec!["Calculus", "Math", "Physics", "Draft"]
+    );
+}
@@ -0,0 +1,423 @@
+/*!
+# Prologue Comments
+## Name of Code Artifact: Rebuild the Index
+
+## Brief Description: This code rebuilds the database of a workspace from the notes directory. It clears the location records, documents, embeddings and links, then reads every note file and embeds the notes in batches. Note ids and manual tags are restored from the `notes-metadata.json` sidecar if it exists. Progress is reported with a `reindex-progress` event after every batch.
+## Programmer’s Name: agent
+
+## Date Created: 2026-10-17
+## Dates Revised and Description of Revisions:
+## -> 2026-10-17: Added the reindex_workspace command.
+## -> 2026-10-17: Dropped the tables wholesale and rebuilt databases that can't be opened from scratch.
+## -> 2026-10-17: Paused the indexing queue during the rebuild and embedded the notes of a failed batch one at a time.
+
+## Preconditions:
+- The workspace must be loaded and writable.
+
+## Acceptable and Unacceptable Input Values/Types:
+- Each tauri command takes an object with types defined by the type definition in the code. Arguments are represented as
+fields in that object.
+- Files that are not valid UTF-8 or can't be embedded are skipped.
+
+## Postconditions:
+
+- The database contains exactly the notes in the notes directory.
+
+## Return Values/Types:
+
+- `reindex_workspace` returns a `ReindexReport` with the notes that were indexed and the files that were skipped, or a `LinknotesError` on failure.
+
+## Side Effects:
+-  Every table in the workspace database is cleared and rebuilt. If the database can't be opened, it is deleted first.
+-  A `reindex-progress` event is emitted after every batch of notes.
+
+## Invariants:
+
+- The note files are never changed.
+
+## Known Faults:
+
+- Notes saved while the index is being rebuilt are only indexed after the rebuild finishes.
+- Embeddings of documents that are missing from the documents table or fail to be deleted stay in the embedding index.
+
+*/
+
+use serde::Serialize;
+use std::path::PathBuf;
+use surrealdb::sql::Id;
+use tauri::{AppHandle, Emitter};
+
+#[cfg(test)]
+use pretty_assertions::assert_eq;
+
+use crate::embedder::NotesEmbedder;
+use crate::error::LinknotesError;
+use crate::indexing::{pause_indexing, wait_for_indexing};
+use crate::lexical::define_full_text_index;
+use crate::links::DOCUMENT_LINK_TABLE;
+use crate::metadata::{metadata_key, read_metadata, update_metadata};
+use crate::note::{
+    define_location_index, embed_notes, store_note, EmbeddedNote, NoteId, DOCUMENT_NAME_TABLE,
+};
+use crate::search::MetaId;
+use crate::workspace::{file_name_to_title, get_workspace_ref, reset_broken_database, WorkspaceId};
+
+/// The event emitted after every batch of notes is indexed
+pub const REINDEX_PROGRESS_EVENT: &str = "reindex-progress";
+/// How many notes are embedded at once
+const REINDEX_BATCH_SIZE: usize = 16;
+
+/// The payload of the `reindex-progress` event.
+///
+/// # Fields
+///
+/// * `workspace_id` - The workspace that is being rebuilt.
+/// * `indexed` - The number of note files that were processed so far.
+/// * `total` - The number of note files in the notes directory.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ReindexProgress {
+    pub workspace_id: WorkspaceId,
+    pub indexed: usize,
+    pub total: usize,
+}
+
+/// The result of rebuilding a workspace.
+///
+/// # Fields
+///
+/// * `indexed` - The ids of the notes that were indexed.
+/// * `skipped` - Note files that couldn't be read or indexed.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct ReindexReport {
+    pub indexed: Vec<NoteId>,
+    pub skipped: Vec<PathBuf>,
+}
+
+/// Remove every document, embedding, location record and link from the workspace database. A database that can't be
+/// opened is deleted and created again from scratch.
+async fn clear_index(workspace_id: WorkspaceId) -> Result<(), LinknotesError> {
+    let opened = {
+        let workspace = get_workspace_ref(workspace_id)?;
+        workspace.retrain_classifier();
+        workspace.document_table().await.map(|_| ())
+    };
+    if let Err(err) = opened {
+        tracing::error!(
+            "Failed to open the database, rebuilding it from scratch: {}",
+            err
+        );
+        reset_broken_database(workspace_id)?;
+    }
+
+    let workspace = get_workspace_ref(workspace_id)?;
+    let document_table = workspace.document_table().await?;
+    let db = document_table.table().db();
+    let documents_table = document_table.table().table().to_string();
+
+    // Delete the documents through the document table so their embeddings are removed too. The tables are dropped
+    // below either way, so a document that can't be deleted only leaves an embedding behind
+    let mut documents = db
+        .query("SELECT meta::id(id) as id FROM type::table($table)")
+        .bind(("table", documents_table.clone()))
+        .await?;
+    let documents: Vec<MetaId> = documents.take(0)?;
+    for document in documents {
+        if let Err(err) = document_table.delete(Id::String(document.id.clone())).await {
+            tracing::error!("Failed to delete document {}: {}", document.id, err);
+        }
+    }
+
+    db.query("BEGIN TRANSACTION")
+        .query(format!("REMOVE TABLE IF EXISTS {documents_table}"))
+        .query(format!("REMOVE TABLE IF EXISTS {DOCUMENT_NAME_TABLE}"))
+        .query(format!("REMOVE TABLE IF EXISTS {DOCUMENT_LINK_TABLE}"))
+        .query("COMMIT TRANSACTION")
+        .await?
+        .check()?;
+    // The full text index is removed with the documents table and the file name index with the location table
+    define_full_text_index(document_table).await?;
+    define_location_index(db).await?;
+    Ok(())
+}
+
+/// Embed a batch of notes. If the batch fails, every note is embedded on its own so one note the model can't embed
+/// doesn't stop the rest. Notes that still fail are `None`.
+async fn embed_batch(
+    embedder: &NotesEmbedder,
+    texts: Vec<(String, String)>,
+) -> Vec<Option<EmbeddedNote>> {
+    match embed_notes(embedder, texts.clone()).await {
+        Ok(embedded) => embedded.into_iter().map(Some).collect(),
+        Err(err) => {
+            tracing::error!(
+                "Failed to embed a batch of notes, embedding them one at a time: {}",
+                err
+            );
+            let mut embedded = Vec::new();
+            for (title, text) in texts {
+                match embed_notes(embedder, vec![(title.clone(), text)]).await {
+                    Ok(mut note) => embedded.push(note.pop()),
+                    Err(err) => {
+                        tracing::error!("Failed to embed {:?}: {}", title, err);
+                        embedded.push(None);
+                    }
+                }
+            }
+            embedded
+        }
+    }
+}
+
+/// Rebuild the database of a workspace from the note files in the notes directory. `on_progress` is called after
+/// every batch of notes is indexed. The indexing queue is paused until the rebuild finishes, so notes saved in the
+/// meantime are stored after the rebuild instead of between clearing the database and rebuilding it.
+///
+/// # Errors
+///
+/// This function will return an error if:
+/// - The workspace is not loaded or is read-only
+/// - The notes directory can't be read
+/// - The database can't be cleared
+/// - The embedding model can't be loaded
+pub(crate) async fn reindex(
+    workspace_id: WorkspaceId,
+    on_progress: impl Fn(ReindexProgress),
+) -> Result<ReindexReport, LinknotesError> {
+    wait_for_indexing(workspace_id).await?;
+    let _pause = pause_indexing(workspace_id).await?;
+    let (root, notes_dir, embedder) = {
+        let workspace = get_workspace_ref(workspace_id)?;
+        workspace.check_writable()?;
+        (
+            workspace.location.clone(),
+            workspace.notes_dir()?,
+            workspace.embedder().await?,
+        )
+    };
+    let metadata = read_metadata(&root);
+
+    let mut files = Vec::new();
+    for entry in std::fs::read_dir(&notes_dir)? {
+        let path = entry?.path();
+        let title = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .and_then(file_name_to_title);
+        if let Some(title) = title {
+            files.push((path, title));
+        }
+    }
+    files.sort();
+    let total = files.len();
+    tracing::info!("Rebuilding the index from {} notes", total);
+
+    clear_index(workspace_id).await?;
+
+    let mut report = ReindexReport::default();
+    let mut processed = 0;
+    for batch in files.chunks(REINDEX_BATCH_SIZE) {
+        let mut notes = Vec::new();
+        let mut texts = Vec::new();
+        for (path, title) in batch {
+            match std::fs::read_to_string(path) {
+                Ok(text) => {
+                    let existing = metadata_key(path).and_then(|key| metadata.get(&key));
+                    let id = existing
+                        .map(|note| note.id.clone())
+                        .unwrap_or_else(NoteId::new);
+                    let tags = existing.map(|note| note.tags.clone()).unwrap_or_default();
+                    notes.push((path, id, tags));
+                    texts.push((title.clone(), text));
+                }
+                Err(err) => {
+                    tracing::error!("Failed to read {:?}: {}", path, err);
+                    report.skipped.push(path.clone());
+                }
+            }
+        }
+
+        let embedded = embed_batch(&embedder, texts).await;
+        for ((path, id, tags), note) in notes.into_iter().zip(embedded) {
+            let (Some(note), Some(file_name)) = (note, metadata_key(path)) else {
+                report.skipped.push(path.clone());
+                continue;
+            };
+            match store_note(id, note, file_name, Some(tags), workspace_id).await {
+                Ok(id) => report.indexed.push(id),
+                Err(err) => {
+                    tracing::error!("Failed to index {:?}: {}", path, err);
+                    report.skipped.push(path.clone());
+                }
+            }
+        }
+
+        processed += batch.len();
+        on_progress(ReindexProgress {
+            workspace_id,
+            indexed: processed,
+            total,
+        });
+    }
+
+    // Forget the metadata of notes whose file no longer exists
+    let file_names: Vec<String> = files
+        .iter()
+        .filter_map(|(path, _)| metadata_key(path))
+        .collect();
+    update_metadata(&root, |metadata| {
+        metadata.retain(|key, _| file_names.contains(key));
+    })?;
+
+    Ok(report)
+}
+
+/// Rebuild the database of a workspace from the notes directory. Use this if the database was deleted or corrupted.
+/// The workspace emits a `reindex-progress` event with a [`ReindexProgress`] payload after every batch of notes.
+///
+/// # Errors
+///
+/// This function will return an error if:
+/// - The workspace is not loaded or is read-only
+/// - The notes directory can't be read
+/// - The database can't be cleared
+/// - The embedding model can't be loaded
+#[tauri::command]
+pub async fn reindex_workspace(
+    app: AppHandle,
+    workspace_id: WorkspaceId,
+) -> Result<ReindexReport, LinknotesError> {
+    tracing::info!("reindex_workspace called with id: {:?}", workspace_id);
+    reindex(workspace_id, |progress| {
+        if let Err(err) = app.emit(REINDEX_PROGRESS_EVENT, progress) {
+            tracing::error!("Failed to emit {}: {}", REINDEX_PROGRESS_EVENT, err);
+        }
+    })
+    .await
+}
+
+#[tokio::test]
+async fn test_reindex_workspace() {
+    use crate::embedder::NotesEmbedder;
+    use crate::links::get_backlinks;
+    use crate::note::{get_tags, read_note, save_note, set_tags, Tag};
+    use crate::workspace::{delete_workspace, load_workspace_with, unload_workspace};
+
+    _ = tracing_subscriber::fmt::try_init();
+
+    let temp = std::env::temp_dir();
+    let workspace_path = temp.join("testing-reindex-workspace");
+    _ = std::fs::remove_dir_all(&workspace_path);
+    let workspace = load_workspace_with(workspace_path, Some(NotesEmbedder::hashing())).unwrap();
+
+    let integrals = save_note(
+        None,
+        "Integrals".to_string(),
+        "Integrals are the area under a curve. See [[Limits]].".to_string(),
+        workspace,
+    )
+    .await
+    .unwrap();
+    let limits = save_note(
+        None,
+        "Limits".to_string(),
+        "Limits describe the value a function approaches.".to_string(),
+        workspace,
+    )
+    .await
+    .unwrap();
+    let calculus = Tag {
+        name: "calculus".to_string(),
+        manual: true,
+    };
+    set_tags(integrals.clone(), vec![calculus.clone()], workspace)
+        .await
+        .unwrap();
+    let notes_dir = get_workspace_ref(workspace).unwrap().notes_dir().unwrap();
+    std::fs::write(notes_dir.join("Derivatives.txt"), "Written in vim.").unwrap();
+
+    let progress = std::sync::Mutex::new(Vec::new());
+    let report = reindex(workspace, |event| progress.lock().unwrap().push(event))
+        .await
+        .unwrap();
+    assert_eq!(report.indexed.len(), 3);
+    assert!(report.skipped.is_empty());
+    assert_eq!(
+        progress.into_inner().unwrap(),
+        vec![ReindexProgress {
+            workspace_id: workspace,
+            indexed: 3,
+            total: 3,
+        }]
+    );
+
+    // Ids and manual tags are kept
+    assert!(report.indexed.contains(&integrals));
+    assert!(report.indexed.contains(&limits));
+    let tags = get_tags(integrals.clone(), workspace).await.unwrap();
+    assert!(tags.contains(&calculus));
+    let note = read_note(limits.clone(), workspace).await.unwrap();
+    assert_eq!(
+        note.document.body(),
+        "Limits describe the value a function approaches."
+    );
+    let backlinks = get_backlinks(limits, workspace).await.unwrap();
+    assert_eq!(backlinks.len(), 1);
+
+    // Files that were added outside of the app are indexed too
+    let derivatives = report
+        .indexed
+        .iter()
+        .find(|id| **id != integrals && **id != limits)
+        .unwrap();
+    let note = read_note(derivatives.clone(), workspace).await.unwrap();
+    assert_eq!(note.document.title(), "Derivatives");
+
+    delete_workspace(workspace).unwrap();
+    unload_workspace(workspace).unwrap();
+}
+
+#[tokio::test]
+async fn test_reindex_broken_database() {
+    use crate::embedder::NotesEmbedder;
+    use crate::note::{get_note_id, save_note};
+    use crate::workspace::{delete_workspace, load_workspace_with, unload_workspace};
+
+    _ = tracing_subscriber::fmt::try_init();
+
+    let temp = std::env::temp_dir();
+    let workspace_path = temp.join("testing-reindex-broken-workspace");
+    _ = std::fs::remove_dir_all(&workspace_path);
+    let workspace =
+        load_workspace_with(workspace_path.clone(), Some(NotesEmbedder::hashing())).unwrap();
+    let id = save_note(
+        None,
+        "Integrals".to_string(),
+        "Integrals are the area under a curve.".to_string(),
+        workspace,
+    )
+    .await
+    .unwrap();
+    wait_for_indexing(workspace).await.unwrap();
+    unload_workspace(workspace).unwrap();
+
+    // Corrupt the database so it can't be opened
+    std::fs::write(workspace_path.join("notes.db").join("CURRENT"), "garbage").unwrap();
+    let workspace = load_workspace_with(workspace_path, Some(NotesEmbedder::hashing())).unwrap();
+    assert!(get_workspace_ref(workspace)
+        .unwrap()
+        .document_table()
+        .await
+        .is_err());
+
+    let report = reindex(workspace, |_| {}).await.unwrap();
+    assert_eq!(report.indexed, vec![id.clone()]);
+    assert_eq!(
+        get_note_id("Integrals".to_string(), workspace)
+            .await
+            .unwrap(),
+        id
+    );
+
+    delete_workspace(workspace).unwrap();
+    unload_workspace(workspace).unwrap();
+}
@@ -0,0 +1,693 @@
+/*!
+# Prologue Comments
+## Name of Code Artifact: Workspace Consistency Check and Repair
+
+## Brief Description: This code finds the places where the notes directory, the location records in the `document_paths` table and the documents in the embedding table disagree with each other. It exposes a command that reports the problems and a command that fixes them by re-importing the notes from disk.
+## Programmer’s Name: agent
+
+## Date Created: 2026-10-17
+## Dates Revised and Description of Revisions:
+## -> 2026-10-17: Added the check_workspace and repair_workspace commands.
+## -> 2026-10-17: Resolved note files in the notes directory by name so moved and copied workspaces aren't reported as broken.
+## -> 2026-10-17: Reported files that can't be read instead of failing the check, matched file names exactly before ignoring case, and kept repairing after a problem can't be fixed.
+
+## Preconditions:
+- The workspace must be loaded. It must be writable to be repaired.
+
+## Acceptable and Unacceptable Input Values/Types:
+- Each tauri command takes an object with types defined by the type definition in the code. Arguments are represented as
+fields in that object.
+
+## Postconditions:
+
+- After a successful repair, every note file has a location record and a document, every location record points at an
+existing file and document, and every document belongs to a location record.
+
+## Return Values/Types:
+
+- Both commands return a `WorkspaceReport` with the problems that were found, or a `LinknotesError` if the workspace can't be read. The report of `repair_workspace` also lists the problems that couldn't be fixed.
+
+## Side Effects:
+-  `repair_workspace` modifies the workspace database and may re-embed notes.
+
+## Invariants:
+
+- The files in the notes directory are the source of truth. Repairs never change or remove a note file.
+
+## Known Faults:
+
+- Files that can't be read or are not valid UTF-8 are reported as unreadable and left alone by the repair.
+
+*/
+
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use surrealdb::sql::Id;
+
+#[cfg(test)]
+use pretty_assertions::assert_eq;
+
+use crate::error::LinknotesError;
+use crate::indexing::{wait_for_indexing, IndexJob};
+use crate::links::delete_outgoing_links;
+use crate::metadata::metadata_key;
+use crate::note::{
+    import_note, index_written_note, select_locations, text_segments, ContextualDocumentLocation,
+    NoteId, DOCUMENT_NAME_TABLE,
+};
+use crate::search::MetaId;
+use crate::workspace::{file_name_to_title, get_workspace_ref, WorkspaceId};
+
+/// The problems found in a workspace by [`check_workspace`].
+///
+/// # Fields
+///
+/// * `orphan_files` - Note files in the notes directory that no location record points to.
+/// * `orphan_locations` - Location records that point to a file or document that doesn't exist.
+/// * `orphan_documents` - The ids of documents in the embedding table that no location record points to.
+/// * `segment_mismatches` - Notes whose file was changed without updating the document and segments in the database.
+/// * `unreadable_files` - Note files that can't be read or are not valid UTF-8.
+/// * `repair_failures` - The problems [`repair_workspace`] couldn't fix. This is always empty for [`check_workspace`].
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct WorkspaceReport {
+    pub orphan_files: Vec<PathBuf>,
+    pub orphan_locations: Vec<OrphanLocation>,
+    pub orphan_documents: Vec<String>,
+    pub segment_mismatches: Vec<NoteId>,
+    pub unreadable_files: Vec<UnreadableFile>,
+    pub repair_failures: Vec<RepairFailure>,
+}
+
+impl WorkspaceReport {
+    /// Check if the workspace is consistent
+    pub fn is_empty(&self) -> bool {
+        self.orphan_files.is_empty()
+            && self.orphan_locations.is_empty()
+            && self.orphan_documents.is_empty()
+            && self.segment_mismatches.is_empty()
+            && self.unreadable_files.is_empty()
+            && self.repair_failures.is_empty()
+    }
+}
+
+/// A note file that can't be read.
+///
+/// # Fields
+///
+/// * `path` - The file in the notes directory.
+/// * `error` - The error reading the file.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct UnreadableFile {
+    pub path: PathBuf,
+    pub error: LinknotesError,
+}
+
+/// The part of a workspace a repair changes.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum RepairTarget {
+    /// A note with a location record
+    Note { id: NoteId },
+    /// A note file without a location record
+    File { path: PathBuf },
+    /// A document without a location record
+    Document { id: String },
+}
+
+/// A problem [`repair_workspace`] couldn't fix.
+///
+/// # Fields
+///
+/// * `target` - The part of the workspace that couldn't be repaired.
+/// * `error` - The error the repair failed with.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RepairFailure {
+    pub target: RepairTarget,
+    pub error: LinknotesError,
+}
+
+/// A location record that points to something that doesn't exist.
+///
+/// # Fields
+///
+/// * `id` - The id of the note.
+/// * `title` - The title of the note.
+/// * `missing_file` - The file of the note doesn't exist.
+/// * `missing_document` - The document of the note doesn't exist in the embedding table.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct OrphanLocation {
+    pub id: NoteId,
+    pub title: String,
+    pub missing_file: bool,
+    pub missing_document: bool,
+}
+
+/// Everything the repair needs to know about a location record
+///
+/// # Fields
+///
+/// * `location` - The file of the note in the notes directory of the workspace.
+struct LocationDetails {
+    title: String,
+    document_id: Id,
+    location: PathBuf,
+}
+
+/// The report along with the details of the records in it
+struct Check {
+    report: WorkspaceReport,
+    locations: HashMap<NoteId, LocationDetails>,
+}
+
+/// Compare the notes directory, the location records and the embedding table of a workspace
+async fn check(workspace_id: WorkspaceId) -> Result<Check, LinknotesError> {
+    wait_for_indexing(workspace_id).await?;
+    let workspace = get_workspace_ref(workspace_id)?;
+    let notes_dir = workspace.notes_dir()?;
+    let document_table = workspace.document_table().await?;
+    let db = document_table.table().db();
+
+    let locations = select_locations(db).await?;
+    let mut documents = db
+        .query("SELECT meta::id(id) as id FROM type::table($table)")
+        .bind(("table", document_table.table().table().to_string()))
+        .await?;
+    let documents: Vec<MetaId> = documents.take(0)?;
+    let document_ids: HashSet<Id> = documents.into_iter().map(|id| Id::String(id.id)).collect();
+    let bodies: HashMap<NoteId, String> = workspace
+        .files()
+        .await?
+        .into_iter()
+        .map(|note| (note.note_id, note.document.body().to_string()))
+        .collect();
+
+    // The note files by their file name. Location records are matched to files by name, so the notes are still found
+    // after the workspace is moved or copied. If no file has the exact name, the file whose name only differs in case
+    // is used so notes are still found after the case of a file name changes
+    let mut note_files: HashMap<String, PathBuf> = HashMap::new();
+    let mut lowercase_note_files: HashMap<String, Vec<PathBuf>> = HashMap::new();
+    for entry in std::fs::read_dir(&notes_dir)? {
+        let path = entry?.path();
+        let Some(file_name) = path.file_name().and_then(|name| name.to_str()) else {
+            continue;
+        };
+        if file_name_to_title(file_name).is_some() && path.is_file() {
+            lowercase_note_files
+                .entry(file_name.to_lowercase())
+                .or_default()
+                .push(path.clone());
+            note_files.insert(file_name.to_string(), path);
+        }
+    }
+    let find_note_file = |file_name: &str| {
+        note_files.get(file_name).cloned().or_else(|| {
+            // Several files that only differ in case can't be told apart
+            match lowercase_note_files
+                .get(&file_name.to_lowercase())
+                .map(Vec::as_slice)
+            {
+                Some([path]) => Some(path.clone()),
+                _ => None,
+            }
+        })
+    };
+
+    let mut report = WorkspaceReport::default();
+    let mut details = HashMap::new();
+    let mut located_files = HashSet::new();
+    for (id, location) in locations {
+        let path = location.path(&notes_dir);
+        let file = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .and_then(find_note_file);
+        let missing_document = !document_ids.contains(&location.document_id);
+        match &file {
+            Some(file) if !missing_document => {
+                // The file is the source of truth. If it changed, the database needs to be updated
+                match std::fs::read_to_string(file) {
+                    Ok(text) => {
+                        if bodies.get(&id) != Some(&text)
+                            || text_segments(&text) != location.segments
+                        {
+                            report.segment_mismatches.push(id.clone());
+                        }
+                    }
+                    Err(err) => report.unreadable_files.push(UnreadableFile {
+                        path: file.clone(),
+                        error: err.into(),
+                    }),
+                }
+            }
+            _ => report.orphan_locations.push(OrphanLocation {
+                id: id.clone(),
+                title: location.title.clone(),
+                missing_file: file.is_none(),
+                missing_document,
+            }),
+        }
+        located_files.extend(file.clone());
+        details.insert(
+            id,
+            LocationDetails {
+                title: location.title,
+                document_id: location.document_id,
+                location: file.unwrap_or(path),
+            },
+        );
+    }
+
+    // Find the note files without a location record. Files that can't be read can't be imported, so they are reported
+    // as unreadable instead
+    for path in note_files.into_values() {
+        if located_files.contains(&path) {
+            continue;
+        }
+        match std::fs::read_to_string(&path) {
+            Ok(_) => report.orphan_files.push(path),
+            Err(err) => report.unreadable_files.push(UnreadableFile {
+                path,
+                error: err.into(),
+            }),
+        }
+    }
+
+    // Find the documents without a location record
+    let referenced: HashSet<&Id> = details
+        .values()
+        .map(|details| &details.document_id)
+        .collect();
+    for id in &document_ids {
+        if !referenced.contains(id) {
+            report.orphan_documents.push(id.to_raw());
+        }
+    }
+
+    report.orphan_files.sort();
+    report.orphan_locations.sort_by(|a, b| a.id.cmp(&b.id));
+    report.orphan_documents.sort();
+    report.segment_mismatches.sort();
+    report.unreadable_files.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(Check {
+        report,
+        locations: details,
+    })
+}
+
+/// Check a workspace for notes that are out of sync between the notes directory, the location records and the
+/// embedding table. This doesn't change the workspace.
+///
+/// # Arguments
+///
+/// * `workspace_id` - The ID of the workspace to check.
+///
+/// # Returns
+///
+/// A `Result` containing a `WorkspaceReport` with the problems that were found, or a `LinknotesError` if the workspace
+/// can't be read.
+#[tauri::command]
+pub async fn check_workspace(workspace_id: WorkspaceId) -> Result<WorkspaceReport, LinknotesError> {
+    tracing::info!("check_workspace called with id: {:?}", workspace_id);
+    Ok(check(workspace_id).await?.report)
+}
+
+/// Read the file a location record points to into an indexing job. The note is indexed from the file where it is
+fn note_file_job(title: String, path: &Path) -> Result<IndexJob, LinknotesError> {
+    let file_name = metadata_key(path)
+        .ok_or_else(|| LinknotesError::InvalidInput(format!("{path:?} is not a note file")))?;
+    Ok(IndexJob {
+        title,
+        file_name,
+        text: std::fs::read_to_string(path)?,
+    })
+}
+
+/// Fix the problems [`check_workspace`] finds, using the notes directory as the source of truth:
+/// - Orphan files are imported as new notes.
+/// - Location records without a file are removed along with their document and links.
+/// - Location records without a document are re-imported from the file with the same note id.
+/// - Documents without a location record are removed.
+/// - Notes with mismatched segments are re-indexed from the file.
+/// - Unreadable files are left alone.
+///
+/// A problem that can't be fixed doesn't stop the repair. It is added to the `repair_failures` of the report and the
+/// repair continues with the next problem.
+///
+/// # Arguments
+///
+/// * `workspace_id` - The ID of the workspace to repair.
+///
+/// # Returns
+///
+/// A `Result` containing the `WorkspaceReport` of the problems that were found and the ones that couldn't be fixed, or
+/// a `LinknotesError` if the workspace can't be checked.
+#[tauri::command]
+pub async fn repair_workspace(
+    workspace_id: WorkspaceId,
+) -> Result<WorkspaceReport, LinknotesError> {
+    tracing::info!("repair_workspace called with id: {:?}", workspace_id);
+    get_workspace_ref(workspace_id)?.check_writable()?;
+    let Check {
+        mut report,
+        mut locations,
+    } = check(workspace_id).await?;
+    let mut failures = Vec::new();
+
+    // Remove or re-import the broken location records
+    for orphan in &report.orphan_locations {
+        let Some(details) = locations.remove(&orphan.id) else {
+            continue;
+        };
+        let repair = async {
+            {
+                let workspace = get_workspace_ref(workspace_id)?;
+                let document_table = workspace.document_table().await?;
+                let db = document_table.table().db();
+                let _: Option<ContextualDocumentLocation> =
+                    db.delete((DOCUMENT_NAME_TABLE, orphan.id.as_str())).await?;
+                if !orphan.missing_document {
+                    document_table
+                        .delete(details.document_id)
+                        .await
+                        .map_err(LinknotesError::storage)?;
+                }
+                if orphan.missing_file {
+                    delete_outgoing_links(db, &orphan.id).await?;
+                }
+            }
+            if !orphan.missing_file {
+                tracing::info!(
+                    "Re-importing note {} from {:?}",
+                    orphan.id,
+                    details.location
+                );
+                let job = note_file_job(details.title, &details.location)?;
+                index_written_note(orphan.id.clone(), job, workspace_id).await?;
+            }
+            Ok::<_, LinknotesError>(())
+        };
+        if let Err(error) = repair.await {
+            failures.push(RepairFailure {
+                target: RepairTarget::Note {
+                    id: orphan.id.clone(),
+                },
+                error,
+            });
+        }
+    }
+
+    // Re-index the notes that changed on disk
+    for id in &report.segment_mismatches {
+        let Some(details) = locations.get(id) else {
+            continue;
+        };
+        tracing::info!("Re-indexing note {} from {:?}", id, details.location);
+        let repair = async {
+            let job = note_file_job(details.title.clone(), &details.location)?;
+            index_written_note(id.clone(), job, workspace_id).await?;
+            Ok::<_, LinknotesError>(())
+        };
+        if let Err(error) = repair.await {
+            failures.push(RepairFailure {
+                target: RepairTarget::Note { id: id.clone() },
+                error,
+            });
+        }
+    }
+
+    // Import the files that aren't in the database
+    for path in &report.orphan_files {
+        let Some(title) = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .and_then(file_name_to_title)
+        else {
+            continue;
+        };
+        tracing::info!("Importing note {:?} from {:?}", title, path);
+        let repair = async {
+            let text = std::fs::read_to_string(path)?;
+            import_note(path, title, text, workspace_id).await?;
+            Ok::<_, LinknotesError>(())
+        };
+        if let Err(error) = repair.await {
+            failures.push(RepairFailure {
+                target: RepairTarget::File { path: path.clone() },
+                error,
+            });
+        }
+    }
+
+    // Remove the documents that no note points to
+    for id in &report.orphan_documents {
+        let repair = async {
+            let workspace = get_workspace_ref(workspace_id)?;
+            let document_table = workspace.document_table().await?;
+            document_table
+                .delete(Id::String(id.clone()))
+                .await
+                .map_err(LinknotesError::storage)?;
+            Ok::<_, LinknotesError>(())
+        };
+        if let Err(error) = repair.await {
+            failures.push(RepairFailure {
+                target: RepairTarget::Document { id: id.clone() },
+                error,
+            });
+        }
+    }
+
+    report.repair_failures = failures;
+    Ok(report)
+}
+
+#[tokio::test]
+async fn test_repair_workspace() {
+    use crate::embedder::NotesEmbedder;
+    use crate::note::{read_note, save_note, ContextualDocument};
+    use crate::workspace::{
+        delete_workspace, files_in_workspace, load_workspace_with, unload_workspace,
+    };
+
+    _ = tracing_subscriber::fmt::try_init();
+
+    let temp = std::env::temp_dir();
+    let workspace_path = temp.join("testing-repair-workspace");
+    _ = std::fs::remove_dir_all(&workspace_path);
+    let workspace = load_workspace_with(workspace_path, Some(NotesEmbedder::hashing())).unwrap();
+
+    let mut ids = Vec::new();
+    for title in ["Edited", "Deleted", "Unembedded", "Unlocated"] {
+        let id = save_note(
+            None,
+            title.to_string(),
+            format!("The {} note.", title),
+            workspace,
+        )
+        .await
+        .unwrap();
+        ids.push(id);
+    }
+    let [edited, deleted, unembedded, unlocated] = ids.try_into().unwrap();
+    assert!(check_workspace(workspace).await.unwrap().is_empty());
+
+    let notes_dir = get_workspace_ref(workspace).unwrap().notes_dir().unwrap();
+    // Edit a file without saving it
+    std::fs::write(
+        notes_dir.join("Edited.txt"),
+        "The edited note. It has a new sentence.",
+    )
+    .unwrap();
+    // Delete a file without removing the note
+    std::fs::remove_file(notes_dir.join("Deleted.txt")).unwrap();
+    // Write a file without saving it
+    std::fs::write(notes_dir.join("Orphan.txt"), "The orphan note.").unwrap();
+    {
+        let workspace = get_workspace_ref(workspace).unwrap();
+        let document_table = workspace.document_table().await.unwrap();
+        let db = document_table.table().db();
+        // Remove a document without removing the location
+        db.query("DELETE type::table($table) WHERE note_id = $id")
+            .bind(("table", document_table.table().table().to_string()))
+            .bind(("id", unembedded.clone()))
+            .await
+            .unwrap()
+            .check()
+            .unwrap();
+        // Remove a location without removing the document
+        let _: Option<ContextualDocumentLocation> = db
+            .delete((DOCUMENT_NAME_TABLE, unlocated.as_str()))
+            .await
+            .unwrap();
+    }
+
+    let report = check_workspace(workspace).await.unwrap();
+    assert_eq!(
+        report.orphan_files,
+        vec![
+            notes_dir.join("Orphan.txt"),
+            notes_dir.join("Unlocated.txt")
+        ]
+    );
+    let mut expected_locations = vec![
+        OrphanLocation {
+            id: deleted.clone(),
+            title: "Deleted".to_string(),
+            missing_file: true,
+            missing_document: false,
+        },
+        OrphanLocation {
+            id: unembedded.clone(),
+            title: "Unembedded".to_string(),
+            missing_file: false,
+            missing_document: true,
+        },
+    ];
+    expected_locations.sort_by(|a, b| a.id.cmp(&b.id));
+    assert_eq!(report.orphan_locations, expected_locations);
+    assert_eq!(report.orphan_documents.len(), 1);
+    assert_eq!(report.segment_mismatches, vec![edited.clone()]);
+
+    assert_eq!(repair_workspace(workspace).await.unwrap(), report);
+    assert!(check_workspace(workspace).await.unwrap().is_empty());
+
+    let note = read_note(edited, workspace).await.unwrap();
+    assert_eq!(
+        note.document.body(),
+        "The edited note. It has a new sentence."
+    );
+    assert_eq!(
+        read_note(deleted, workspace).await.err(),
+        Some(LinknotesError::NoteNotFound)
+    );
+    // The note keeps its id when it is re-imported
+    read_note(unembedded, workspace).await.unwrap();
+    let mut titles: Vec<String> = files_in_workspace(workspace)
+        .await
+        .unwrap()
+        .iter()
+        .map(|note: &ContextualDocument| note.document.title().to_string())
+        .collect();
+    titles.sort();
+    assert_eq!(titles, vec!["Edited", "Orphan", "Unembedded", "Unlocated"]);
+
+    delete_workspace(workspace).unwrap();
+    unload_workspace(workspace).unwrap();
+}
+
+#[tokio::test]
+async fn test_check_copied_workspace() {
+    use crate::embedder::NotesEmbedder;
+    use crate::note::{read_note, save_note, set_tags, Tag};
+    use crate::workspace::{copy_dir_all, delete_workspace, load_workspace_with, unload_workspace};
+
+    _ = tracing_subscriber::fmt::try_init();
+
+    let temp = std::env::temp_dir();
+    let original_path = temp.join("testing-repair-original-workspace");
+    let copy_path = temp.join("testing-repair-copied-workspace");
+    _ = std::fs::remove_dir_all(&original_path);
+    _ = std::fs::remove_dir_all(&copy_path);
+
+    let workspace =
+        load_workspace_with(original_path.clone(), Some(NotesEmbedder::hashing())).unwrap();
+    let id = save_note(
+        None,
+        "Integrals".to_string(),
+        "Integrals measure the area under a curve.".to_string(),
+        workspace,
+    )
+    .await
+    .unwrap();
+    let tags = vec![Tag {
+        name: "Calculus".to_string(),
+        manual: true,
+    }];
+    set_tags(id.clone(), tags.clone(), workspace).await.unwrap();
+    wait_for_indexing(workspace).await.unwrap();
+    unload_workspace(workspace).unwrap();
+    copy_dir_all(&original_path, &copy_path).unwrap();
+    _ = std::fs::remove_dir_all(&original_path);
+
+    // The copy finds its notes even though the original directory is gone
+    let copy = load_workspace_with(copy_path, Some(NotesEmbedder::hashing())).unwrap();
+    assert_eq!(
+        check_workspace(copy).await.unwrap(),
+        WorkspaceReport::default()
+    );
+    assert!(repair_workspace(copy).await.unwrap().is_empty());
+    let note = read_note(id, copy).await.unwrap();
+    assert_eq!(note.document.title(), "Integrals");
+    assert!(note.tags.contains(&tags[0]));
+
+    delete_workspace(copy).unwrap();
+    unload_workspace(copy).unwrap();
+}
+
+#[tokio::test]
+async fn test_check_unreadable_and_renamed_files() {
+    use crate::embedder::NotesEmbedder;
+    use crate::note::save_note;
+    use crate::workspace::{delete_workspace, load_workspace_with, unload_workspace};
+
+    _ = tracing_subscriber::fmt::try_init();
+
+    let temp = std::env::temp_dir();
+    let workspace_path = temp.join("testing-repair-unreadable-workspace");
+    _ = std::fs::remove_dir_all(&workspace_path);
+    let workspace = load_workspace_with(workspace_path, Some(NotesEmbedder::hashing())).unwrap();
+    for title in ["Integrals", "Derivatives"] {
+        save_note(
+            None,
+            title.to_string(),
+            format!("{title} are part of calculus."),
+            workspace,
+        )
+        .await
+        .unwrap();
+    }
+    wait_for_indexing(workspace).await.unwrap();
+    let notes_dir = get_workspace_ref(workspace).unwrap().notes_dir().unwrap();
+
+    // A file whose name only differs in case is still the file of the note
+    std::fs::rename(
+        notes_dir.join("Derivatives.txt"),
+        notes_dir.join("DERIVATIVES.txt"),
+    )
+    .unwrap();
+    // Another file with a name that only differs in case doesn't replace the file with the exact name. Filesystems
+    // that ignore case can't have both files
+    let case_sensitive = !notes_dir.join("integrals.txt").exists();
+    let mut orphan_files = Vec::new();
+    if case_sensitive {
+        std::fs::write(notes_dir.join("integrals.txt"), "Integrals are sums.").unwrap();
+        orphan_files.push(notes_dir.join("integrals.txt"));
+    }
+    // Files that are not valid UTF-8 are reported instead of failing the check
+    std::fs::write(notes_dir.join("Binary.txt"), [0xff, 0xfe, 0x00]).unwrap();
+
+    let report = check_workspace(workspace).await.unwrap();
+    assert_eq!(report.orphan_files, orphan_files);
+    assert!(report.orphan_locations.is_empty());
+    assert!(report.segment_mismatches.is_empty());
+    assert_eq!(report.unreadable_files.len(), 1);
+    assert_eq!(
+        report.unreadable_files[0].path,
+        notes_dir.join("Binary.txt")
+    );
+
+    // The repair imports the readable file and leaves the unreadable one alone
+    let repaired = repair_workspace(workspace).await.unwrap();
+    assert!(repaired.repair_failures.is_empty());
+    let report = check_workspace(workspace).await.unwrap();
+    assert!(report.orphan_files.is_empty());
+    assert_eq!(report.unreadable_files.len(), 1);
+    assert_eq!(
+        std::fs::read(notes_dir.join("Binary.txt")).unwrap(),
+        [0xff, 0xfe, 0x00]
+    );
+
+    delete_workspace(workspace).unwrap();
+    unload_workspace(workspace).unwrap();
+}
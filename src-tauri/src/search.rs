@@ -13,6 +13,12 @@
 ## -> 2024-10-18: Added support for multiple workspaces.
 ## -> 2024-11-14: Added support for context searching
 ## -> 2024-12-08: Finalized documentation and testing.
+## -> 2026-10-17: Added lexical and hybrid search modes.
+## -> 2026-10-17: Added the search query language.
+## -> 2026-10-17: Added tag filters with any-of, all-of and none-of tags and a manual-only flag.
+## -> 2026-10-17: Added MMR diversification to search and context_search.
+## -> 2026-10-17: Stopped lexical search from loading the embedding model.
+## -> 2026-10-17: Diversified results with the sentence embeddings stored when the notes were indexed.
 
 
 ## Preconditions:
@@ -23,6 +29,7 @@
 ## Acceptable and Unacceptable Input Values/Types:
 - Each tauri command takes an object with types defined by the type definition in the code. Arguments are represented as
 fields in that object.
+- Search text is parsed with the query language in `query.rs`. Queries that can't be parsed return `LinknotesError::InvalidQuery`.
 
 ## Postconditions:
 
@@ -31,7 +38,7 @@ fields in that object.
 ## Return Values/Types:
 
 - Functions return Result types with success yielding appropriate outputs (e.g., search results, note metadata).
-- Errors are encapsulated in the anyhow::Result type for robust error handling.
+- Errors are encapsulated in the `LinknotesError` type so the frontend can react to each kind of failure.
 -  Error and Exception Condition Values/Types: anyhow::Error:
     -> For general errors.
     -> Initialization errors for BERT or issues with workspace paths are raised.
@@ -56,122 +63,299 @@ fields in that object.
 
 use kalosm::{language::*, IntoEmbeddingIndexedTableSearchFilter}; // Import the necessary modules from the kalosm crate.
 use serde::{Deserialize, Serialize}; // Import the necessary modules from the serde crate.
+use std::collections::HashMap; // Import the HashMap type from the standard library.
 use std::ops::Range; // Import the Range module from the standard library.
 use surrealdb::sql::Id; // Import the Id module from the surrealdb crate.
 
-// Import the bert embedding model, methods to chunk text and use the workspace
-use crate::bert;
+// Import the embedding model, methods to chunk text and use the workspace
 use crate::classifier::chunk_text;
+use crate::diversify::{
+    candidates, check_lambda, diversify_results, mmr, sentence_embeddings, NoteSentence,
+};
+use crate::embedder::NotesEmbedder;
+use crate::embedding_migration::check_embedding_model;
+use crate::error::LinknotesError;
+use crate::indexing::wait_for_indexing;
+use crate::lexical::{best_sentence, lexical_hits, query_terms};
+use crate::note::NoteId;
+use crate::query::{prepare_query, CompiledQuery, TagFilter};
 use crate::workspace::{get_workspace_ref, WorkspaceId};
+use crate::ContextualDocumentTable;
 
 /// An id of a surrealdb object. This type is only used for deserialization of a database query
 #[derive(Serialize, Deserialize)]
-struct MetaId {
-    id: String, // The id of the document
+pub(crate) struct MetaId {
+    pub(crate) id: String, // The id of the document
 }
 
 // Represents the result of a search operation.
 //
 // # Fields
 //
-// * `distance` - A floating-point value representing the distance or relevance of the search result.
+// * `distance` - The embedding distance between the query and the sentence, or `None` if only the full-text index found the result.
+// * `score` - How relevant the result is. Higher is better. This is `1 - distance` in semantic mode, the BM25 score in lexical mode, and the reciprocal rank fusion score in hybrid mode.
+// * `id` - The id of the note the search result is in.
 // * `title` - A string containing the title of the search result.
 // * `character_range` - A range of character indices indicating the position of the search result within the source text.
-#[derive(Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct SearchResult {
-    pub distance: f32,
+    pub distance: Option<f32>,
+    pub score: f32,
+    pub id: NoteId,
     pub title: String,
     pub character_range: Range<usize>,
 }
 
-/// Queries the database for documents that contain all specified tags.
+/// How `search` finds notes.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SearchMode {
+    /// The sentences with the closest embeddings to the query
+    Semantic,
+    /// The notes that contain every word of the query, ranked with BM25
+    Lexical,
+    /// Both semantic and lexical results, combined with reciprocal rank fusion
+    #[default]
+    Hybrid,
+}
+
+/// The constant of reciprocal rank fusion. Larger values make the top ranks of each list matter less
+const RRF_K: f32 = 60.0;
+
+/// Convert a byte range in some text to a range of characters
+fn char_range(text: &str, byte_range: Range<usize>) -> Range<usize> {
+    let char_start = text[0..byte_range.start].chars().count();
+    let char_len = text[byte_range].chars().count();
+    char_start..char_start + char_len
+}
+
+/// Convert a range of characters in some text to a byte range
+pub(crate) fn byte_range(text: &str, character_range: Range<usize>) -> Range<usize> {
+    let byte_index = |character: usize| {
+        text.char_indices()
+            .nth(character)
+            .map(|(index, _)| index)
+            .unwrap_or(text.len())
+    };
+    byte_index(character_range.start)..byte_index(character_range.end)
+}
+
+/// Get the bodies of the notes some search results are in, keyed by the id of the note
+pub(crate) async fn note_bodies(
+    document_table: &ContextualDocumentTable,
+    results: &[SearchResult],
+) -> Result<HashMap<NoteId, String>, LinknotesError> {
+    /// The body of a note. This type is only used for deserialization of a database query
+    #[derive(Deserialize)]
+    struct NoteBody {
+        note_id: NoteId,
+        body: String,
+    }
+
+    let mut ids: Vec<NoteId> = results.iter().map(|result| result.id.clone()).collect();
+    ids.sort();
+    ids.dedup();
+    let mut bodies = document_table
+        .table()
+        .db()
+        .query("SELECT note_id, document.body AS body FROM type::table($table) WHERE note_id INSIDE $ids")
+        .bind(("table", document_table.table().table().to_string()))
+        .bind(("ids", ids))
+        .await?;
+    let bodies: Vec<NoteBody> = bodies.take(0)?;
+    Ok(bodies
+        .into_iter()
+        .map(|note| (note.note_id, note.body))
+        .collect())
+}
+
+/// Find the sentences with the closest embeddings to the query text in documents that match the filter of the query
+async fn semantic_results(
+    document_table: &ContextualDocumentTable,
+    embedder: &NotesEmbedder,
+    query: &CompiledQuery,
+    results: usize,
+) -> Result<Vec<SearchResult>, LinknotesError> {
+    let embedding = embedder
+        .embed_for(EmbeddingInput::new(
+            query.text.clone(),
+            EmbeddingVariant::Query,
+        ))
+        .await
+        .map_err(LinknotesError::embedding_model)?;
+
+    // The filter only contains parameters, so it is safe to add to the statement
+    let mut request = document_table
+        .table()
+        .db()
+        .query(format!(
+            "SELECT meta::id(id) as id FROM type::table($table) WHERE true{}",
+            query.and_filter()
+        ))
+        .bind(("table", document_table.table().table().to_string()));
+    for binding in &query.bindings {
+        request = request.bind(binding.clone());
+    }
+    let mut matching_documents = request.await?;
+
+    let matching_documents: Vec<MetaId> = matching_documents.take(0)?;
+    let nearest = document_table
+        .search(embedding)
+        .with_results(results)
+        .with_filter(matching_documents.into_iter().map(|id| Id::String(id.id)))
+        .await
+        .map_err(LinknotesError::storage)?;
+
+    Ok(nearest
+        .into_iter()
+        .map(|result| SearchResult {
+            distance: Some(result.distance),
+            score: 1.0 - result.distance,
+            id: result.record.note_id.clone(),
+            title: result.record.document.title().to_string(),
+            character_range: char_range(result.record.document.body(), result.byte_range),
+        })
+        .collect())
+}
+
+/// Find the notes that contain every word of the query text and match the filter of the query. Each result points at
+/// the sentence with the most words of the query
+async fn lexical_results(
+    document_table: &ContextualDocumentTable,
+    query: &CompiledQuery,
+    results: usize,
+) -> Result<Vec<SearchResult>, LinknotesError> {
+    let terms = query_terms(&query.text);
+    let hits = lexical_hits(document_table, query, results).await?;
+    Ok(hits
+        .into_iter()
+        .map(|hit| {
+            let body = hit.document.body();
+            let sentence = best_sentence(body, &terms).unwrap_or(0..body.len());
+            SearchResult {
+                distance: None,
+                score: hit.score,
+                id: hit.note_id,
+                title: hit.document.title().to_string(),
+                character_range: char_range(body, sentence),
+            }
+        })
+        .collect())
+}
+
+/// Combine ranked lists of results with reciprocal rank fusion. Each result scores `1 / (RRF_K + rank)` in every list
+/// it is in. Results in the same note with overlapping ranges are the same result.
+fn fuse_results(lists: Vec<Vec<SearchResult>>, results: usize) -> Vec<SearchResult> {
+    let mut fused: Vec<SearchResult> = Vec::new();
+    for list in lists {
+        for (rank, result) in list.into_iter().enumerate() {
+            let score = 1.0 / (RRF_K + rank as f32 + 1.0);
+            let existing = fused.iter_mut().find(|existing| {
+                existing.id == result.id
+                    && existing.character_range.start < result.character_range.end
+                    && result.character_range.start < existing.character_range.end
+            });
+            match existing {
+                Some(existing) => {
+                    existing.score += score;
+                    existing.distance = existing.distance.or(result.distance);
+                }
+                None => fused.push(SearchResult { score, ..result }),
+            }
+        }
+    }
+    fused.sort_by(|a, b| b.score.total_cmp(&a.score));
+    fused.truncate(results);
+    fused
+}
+
+/// Search the notes in a workspace that match the tag filter.
 ///
-/// This function constructs and executes a SQL query to retrieve the IDs of documents
-/// from the `document_table` that contain all the tags specified in the `tags` vector.
-/// The query uses the `CONTAINSALL` function to ensure that all tags are present in the
-/// document's tags.
+/// Semantic search finds the sentences with the closest embeddings to the text. Lexical search finds the notes that
+/// contain every word of the text with a BM25 full-text index, which finds exact identifiers, acronyms and course codes
+/// that embeddings miss. Hybrid search runs both and combines them with reciprocal rank fusion.
+///
+/// The text is parsed with the query language, so `tag:Math -tag:Draft title:"integrals" "exact phrase"
+/// created:>2024-10-01` only searches matching notes. The plain words and phrases are what the notes are searched for.
+///
+/// If `mmr_lambda` is set, more candidates are retrieved and re-ranked with maximal marginal relevance so near-duplicate
+/// sentences don't crowd out distinct results.
 ///
 /// # Arguments
 ///
 /// * `text` - A string containing the search query.
-/// * `tags` - A vector of tags that the returned documents must contain.
+/// * `tags` - The tags the returned documents must have. Either a list of tags the documents must all have, or an object
+///   with `all`, `any` and `exclude` lists and a `manual_only` flag to ignore tags the classifier added.
 /// * `results` - The number of results to return.
+/// * `mode` - `semantic`, `lexical` or `hybrid`. Defaults to `hybrid`.
+/// * `mmr_lambda` - How much to prefer relevance over diversity, between 0 and 1. `None` doesn't diversify the results.
 /// * `workspace_id` - The ID of the workspace to search in.
 ///
 /// # Returns
 ///
-/// A `Result` containing a vector of document IDs if the query is successful, or an error
-/// message as a `String` if the query fails.
+/// A `Result` containing the search results ordered from the most to the least relevant if the query is successful,
+/// or a `LinknotesError` if the query fails.
 ///
 /// # Errors
 ///
 /// This function will return an error if:
+/// - The query can't be parsed
+/// - The MMR lambda is not between 0 and 1
 /// - The workspace does not exist
-/// - The tags do not exist in the database
+/// - The workspace was indexed with another embedding model and hasn't been migrated. Lexical search still works
 /// - the query execution fails
 /// - if there is an issue serializing or deserializing the database query and result
 #[tauri::command]
 pub async fn search(
     text: String,
-    tags: Vec<String>,
+    tags: TagFilter,
     results: usize,
+    mode: Option<SearchMode>,
+    mmr_lambda: Option<f32>,
     workspace_id: WorkspaceId,
-) -> Result<Vec<SearchResult>, String> {
-    tracing::info!("Search called with text {:?} and tags {:?}", text, tags);
-    let workspace = get_workspace_ref(workspace_id);
-    let document_table = workspace
-        .document_table()
-        .await
-        .map_err(|e| e.to_string())?;
-    let bert = bert().await.map_err(|e| e.to_string())?;
-    let embedding = bert
-        .embed_for(EmbeddingInput::new(text, EmbeddingVariant::Query))
-        .await
-        .map_err(|e| e.to_string())?;
+) -> Result<Vec<SearchResult>, LinknotesError> {
+    let mode = mode.unwrap_or_default();
+    tracing::info!(
+        "Search called with text {:?}, tags {:?}, mode {:?} and MMR lambda {:?}",
+        text,
+        tags,
+        mode,
+        mmr_lambda
+    );
+    let query = prepare_query(&text, &tags)?;
+    let mmr_lambda = check_lambda(mmr_lambda)?;
+    let candidates = candidates(results, mmr_lambda);
+    wait_for_indexing(workspace_id).await?;
+    let workspace = get_workspace_ref(workspace_id)?;
+    let document_table = workspace.document_table().await?;
 
-    let mut documents_with_all_tags = document_table
-        .table()
-        .db()
-        .query(format!(
-            "SELECT meta::id(id) as id FROM {} WHERE tags.name CONTAINSALL {}",
-            document_table.table().table(),
-            serde_json::to_string(&tags).unwrap()
-        ))
-        .await
-        .map_err(|e| e.to_string())?;
+    let semantic = if mode == SearchMode::Lexical {
+        Vec::new()
+    } else {
+        // Lexical search doesn't need the embedding model, so it is only loaded here
+        let embedder = workspace.embedder().await?;
+        // Embeddings from different models can't be compared
+        check_embedding_model(&workspace.location, &embedder)?;
+        semantic_results(document_table, &embedder, &query, candidates).await?
+    };
+    let lexical = if mode == SearchMode::Semantic {
+        Vec::new()
+    } else {
+        lexical_results(document_table, &query, candidates).await?
+    };
 
-    let documents_with_all_tags: Vec<MetaId> =
-        documents_with_all_tags.take(0).map_err(|e| e.to_string())?;
-    let nearest = document_table
-        .search(embedding)
-        .with_results(results)
-        .with_filter(
-            documents_with_all_tags
-                .into_iter()
-                .map(|id| Id::String(id.id)),
-        )
-        .await
-        .map_err(|e| e.to_string())?;
-
-    Ok(nearest
-        .into_iter()
-        .map(|result| {
-            let title = result.record.document.title().to_string();
-            let char_start = result.record.document.body()[0..result.byte_range.start]
-                .chars()
-                .count();
-            let char_len = result.record.document.body()[result.byte_range.clone()]
-                .chars()
-                .count();
-            let character_range = char_start..char_start + char_len;
-            let distance = result.distance;
-            SearchResult {
-                distance,
-                title,
-                character_range,
-            }
-        })
-        .collect())
+    let ranked = match mode {
+        SearchMode::Semantic => semantic,
+        SearchMode::Lexical => lexical,
+        SearchMode::Hybrid => fuse_results(vec![semantic, lexical], candidates),
+    };
+    match mmr_lambda {
+        // Diversifying uses the stored sentence embeddings, so lexical results usually don't load the model
+        Some(lambda) => {
+            diversify_results(document_table, &workspace, ranked, lambda, results).await
+        }
+        None => Ok(ranked),
+    }
 }
 
 /// Represents the result of a context search operation. It contains the utf16 index of the most relevant section of the search result within [`ContextResult::text`]
@@ -179,6 +363,8 @@ pub async fn search(
 pub struct ContextResult {
     // The distance from the search result to the cursor
     pub distance: f32,
+    // The id of the note the search result is in
+    pub id: NoteId,
     // The title of the document
     pub title: String,
     // The text of the search result. This includes the context sentences around the search result
@@ -217,7 +403,7 @@ fn get_sentence_range(
 /// # Returns
 ///
 /// A range of utf16 byte indexes.
-fn utf8_range_to_utf16_range(utf8_range: Range<usize>, text: &str) -> Range<usize> {
+pub(crate) fn utf8_range_to_utf16_range(utf8_range: Range<usize>, text: &str) -> Range<usize> {
     let utf16_start = text[..utf8_range.start] // Get the text before the start of the range
         .chars() // Convert the text to a character iterator.
         .map(|c| c.len_utf16()) // Map each character to its utf16 length.
@@ -236,29 +422,32 @@ fn utf8_range_to_utf16_range(utf8_range: Range<usize>, text: &str) -> Range<usiz
 ///
 /// # Arguments
 ///
-/// * `document_title` - The title of the document the cursor is in or None if the cursor is not in a document.
+/// * `document_id` - The id of the note the cursor is in or None if the cursor is not in a saved note.
 /// * `document_text` - The entire text of the document the cursor is ine.
 /// * `cursor_utf16_index` - The character index of the cursor within that document in utf16 bytes.
 /// * `results` - The number of results to return.
 /// * `context_sentences` - The number of sentences of context to return around the search result.
+/// * `mmr_lambda` - How much to prefer relevance over diversity, between 0 and 1. `None` returns the nearest sentences.
 /// * `workspace_id` - The ID of the workspace to search in.
 ///
 /// # Returns
 ///
-/// A `Result` containing a vector of search results if the query is successful, or an error
-/// message as a `String` if the query fails.
+/// A `Result` containing a vector of search results if the query is successful, or a
+/// `LinknotesError` if the query fails.
 ///
 /// # Errors
 ///
 /// This function will return an error if:
-/// - The document title is both Some and not saved in the database
+/// - The document id is both Some and not saved in the database
+/// - The MMR lambda is not between 0 and 1
 /// - The workspace does not exist
+/// - The workspace was indexed with another embedding model and hasn't been migrated
 /// - the query execution fails
 /// - if there is an issue serializing or deserializing the database query and result
 #[tauri::command]
 pub async fn context_search(
-    // The title of the document the cursor is in
-    document_title: Option<String>,
+    // The id of the note the cursor is in
+    document_id: Option<NoteId>,
     // The entire text of the document we are generating context for
     document_text: String,
     // The character index of the cursor in utf16 bytes
@@ -267,15 +456,19 @@ pub async fn context_search(
     results: usize,
     // The number of sentences of context to return around the search result
     context_sentences: usize,
+    // How much to prefer relevance over diversity, or None to return the nearest sentences
+    mmr_lambda: Option<f32>,
     // The workspace to search in
     workspace_id: WorkspaceId,
-) -> Result<Vec<ContextResult>, String> {
-    tracing::info!("Search called with title {:?}, text {:?}, character index {:?}, results {:?}, and context_sentences {:?}", document_title, document_text, cursor_utf16_index, results, context_sentences);
-    let workspace = get_workspace_ref(workspace_id);
-    let document_table = workspace
-        .document_table()
-        .await
-        .map_err(|err| format!("{}", err))?;
+) -> Result<Vec<ContextResult>, LinknotesError> {
+    tracing::info!("Search called with id {:?}, text {:?}, character index {:?}, results {:?}, context_sentences {:?} and MMR lambda {:?}", document_id, document_text, cursor_utf16_index, results, context_sentences, mmr_lambda);
+    let mmr_lambda = check_lambda(mmr_lambda)?;
+    wait_for_indexing(workspace_id).await?;
+    let workspace = get_workspace_ref(workspace_id)?;
+    let document_table = workspace.document_table().await?;
+    let embedder = workspace.embedder().await?;
+    // Embeddings from different models can't be compared
+    check_embedding_model(&workspace.location, &embedder)?;
     // First split up the text into sentences
     let sentences = chunk_text(&document_text);
     tracing::info!(
@@ -293,8 +486,11 @@ pub async fn context_search(
             cursor_byte_index = Some(byte_index);
         }
     }
-    let cursor_byte_index = cursor_byte_index
-        .ok_or_else(|| "Cannot search around a sentence that is not in the document".to_string())?;
+    let cursor_byte_index = cursor_byte_index.ok_or_else(|| {
+        LinknotesError::InvalidInput(
+            "Cannot search around a sentence that is not in the document".to_string(),
+        )
+    })?;
     tracing::info!("Cursor byte index: {:?}", cursor_byte_index);
     let cursor_sentence_index = sentences
         .iter()
@@ -311,31 +507,28 @@ pub async fn context_search(
     tracing::info!("Searching with context {:?}", context);
 
     // Embed the context
-    let bert = bert().await.map_err(|err| format!("{}", err))?;
-    let embedding = bert
+    let embedding = embedder
         .embed(context)
         .await
-        .map_err(|err| format!("{}", err))?;
+        .map_err(LinknotesError::embedding_model)?;
 
     // And search for the nearest results
-    let mut search = document_table.search(embedding).with_results(results);
+    let mut search = document_table
+        .search(embedding)
+        .with_results(candidates(results, mmr_lambda));
 
     // Filter out the current document from the search results if it has been saved
-    if let Some(document_title) = document_title {
-        // Get the ids of all documents with a different title
+    if let Some(document_id) = document_id {
+        // Get the ids of all documents in other notes
         let mut all_other_document_ids = document_table
             .table()
             .db()
-            .query(format!(
-                "SELECT meta::id(id) as id FROM {} WHERE document.title != \"{}\"",
-                document_table.table().table(),
-                document_title
-            ))
-            .await
-            .map_err(|e| e.to_string())?;
+            .query("SELECT meta::id(id) as id FROM type::table($table) WHERE note_id != $note_id")
+            .bind(("table", document_table.table().table().to_string()))
+            .bind(("note_id", document_id))
+            .await?;
 
-        let all_other_document_ids: Vec<MetaId> =
-            all_other_document_ids.take(0).map_err(|e| e.to_string())?;
+        let all_other_document_ids: Vec<MetaId> = all_other_document_ids.take(0)?;
 
         // Only include those documents in the search results
         search = search.with_filter(
@@ -344,17 +537,37 @@ pub async fn context_search(
                 .map(|id| Id::String(id.id))
                 .into_embedding_indexed_table_search_filter(&document_table.table())
                 .await
-                .map_err(|e| e.to_string())?,
+                .map_err(LinknotesError::storage)?,
         );
     }
 
-    let nearest = search.await.map_err(|err| format!("{}", err))?;
+    let mut nearest = search.await.map_err(LinknotesError::storage)?;
+
+    // Re-rank the nearest sentences so near-duplicates don't crowd out distinct results
+    if let Some(lambda) = mmr_lambda {
+        let sentences: Vec<NoteSentence> = nearest
+            .iter()
+            .map(|result| NoteSentence {
+                note_id: &result.record.note_id,
+                byte_range: result.byte_range.clone(),
+                text: &result.record.document.body()[result.byte_range.clone()],
+            })
+            .collect();
+        let embeddings = sentence_embeddings(document_table, &workspace, &sentences).await?;
+        let relevance: Vec<f32> = nearest.iter().map(|result| 1.0 - result.distance).collect();
+        let mut candidates: Vec<_> = nearest.into_iter().map(Some).collect();
+        nearest = mmr(&relevance, &embeddings, lambda, results)
+            .into_iter()
+            .filter_map(|index| candidates[index].take())
+            .collect();
+    }
 
     tracing::info!("Nearest results: {:?}", nearest);
 
     Ok(nearest
         .into_iter()
         .map(|result| {
+            let id = result.record.note_id.clone();
             let title = result.record.document.title().to_string();
             let body = result.record.document.body();
 
@@ -388,6 +601,7 @@ pub async fn context_search(
             );
             ContextResult {
                 distance,
+                id,
                 title,
                 relevant_range,
                 text,
@@ -398,35 +612,46 @@ pub async fn context_search(
 
 #[tokio::test]
 async fn test_note_context() {
-    use crate::{delete_workspace, load_workspace, remove_note, save_note, unload_workspace};
+    use crate::workspace::load_workspace_with;
+    use crate::{delete_workspace, remove_note, save_note, unload_workspace};
     _ = tracing_subscriber::fmt::try_init();
 
     let temp = std::env::temp_dir();
     let workspace_path = temp.join("testing-notes-workspace");
     _ = std::fs::remove_dir_all(&workspace_path);
-    let workspace = load_workspace(workspace_path);
+    let workspace = load_workspace_with(workspace_path, Some(NotesEmbedder::hashing())).unwrap();
 
-    save_note(
+    let id = save_note(
+        None,
         "mynote".to_string(),
         "my note is here".to_string(),
         workspace,
     )
     .await
     .unwrap();
-    remove_note("mynote".to_string(), workspace).await;
+    remove_note(id, workspace).await.unwrap();
 
-    save_note(
+    let id = save_note(
+        None,
         "search-note".to_string(),
         "The math is mathing QED. The math is mathing QED. This is my note. The cat is here. Yes it is.".to_string(),
         workspace,
     )
     .await.unwrap();
-    let results =
-        crate::search::context_search(None, "The cat is here".to_string(), 0, 1, 3, workspace)
-            .await
-            .unwrap();
+    let results = crate::search::context_search(
+        None,
+        "The cat is here".to_string(),
+        0,
+        1,
+        3,
+        None,
+        workspace,
+    )
+    .await
+    .unwrap();
 
     assert_eq!(results.len(), 1);
+    assert_eq!(results[0].id, id);
     assert_eq!(results[0].title, "search-note");
     assert_eq!(
         results[0].text,
@@ -434,6 +659,502 @@ async fn test_note_context() {
     );
     assert_eq!(results[0].relevant_range, 42..59);
 
-    delete_workspace(workspace);
-    unload_workspace(workspace);
+    delete_workspace(workspace).unwrap();
+    unload_workspace(workspace).unwrap();
+}
+
+#[tokio::test]
+async fn test_special_characters_in_queries() {
+    use crate::note::{get_note_id, read_note, set_tags, Tag};
+    use crate::workspace::load_workspace_with;
+    use crate::{delete_workspace, files_in_workspace, save_note, unload_workspace};
+    _ = tracing_subscriber::fmt::try_init();
+
+    let temp = std::env::temp_dir();
+    let workspace_path = temp.join("testing-special-characters-workspace");
+    _ = std::fs::remove_dir_all(&workspace_path);
+    let workspace = load_workspace_with(workspace_path, Some(NotesEmbedder::hashing())).unwrap();
+
+    let titles = [
+        "He said \"hello\"",
+        "C:\\notes\\back\\slashes\\",
+        "\" OR 1 = 1; DELETE document_paths; --",
+        "SELECT * FROM documents WHERE true",
+        "it's a 'quoted' note",
+    ];
+    let mut ids = Vec::new();
+    for title in titles {
+        let id = save_note(
+            None,
+            title.to_string(),
+            format!("The note {} talks about integrals.", title),
+            workspace,
+        )
+        .await
+        .unwrap();
+        let tags = vec![Tag {
+            name: format!("tag \"{}\"", title),
+            manual: true,
+        }];
+        set_tags(id.clone(), tags.clone(), workspace).await.unwrap();
+        assert_eq!(get_note_id(title.to_string(), workspace).await.unwrap(), id);
+        let note = read_note(id.clone(), workspace).await.unwrap();
+        assert_eq!(note.document.title(), title);
+        assert!(note.tags.contains(&tags[0]));
+        ids.push(id);
+    }
+
+    // None of the titles changed the database
+    let notes = files_in_workspace(workspace).await.unwrap();
+    assert_eq!(notes.len(), titles.len());
+
+    for (title, id) in titles.iter().zip(&ids) {
+        // Tags with quotes only match the note they were added to
+        let results = search(
+            "integrals".to_string(),
+            vec![format!("tag \"{}\"", title)].into(),
+            10,
+            None,
+            None,
+            workspace,
+        )
+        .await
+        .unwrap();
+        assert!(!results.is_empty());
+        assert!(results.iter().all(|result| &result.id == id));
+
+        // The current note is filtered out of context search
+        let results = context_search(
+            Some(id.clone()),
+            "The note talks about integrals.".to_string(),
+            0,
+            10,
+            1,
+            None,
+            workspace,
+        )
+        .await
+        .unwrap();
+        assert!(!results.is_empty());
+        assert!(results.iter().all(|result| &result.id != id));
+    }
+
+    delete_workspace(workspace).unwrap();
+    unload_workspace(workspace).unwrap();
+}
+
+#[test]
+fn test_fuse_results() {
+    let note = NoteId::new();
+    let other_note = NoteId::new();
+    let result = |id: &NoteId, character_range: Range<usize>, distance: Option<f32>| SearchResult {
+        distance,
+        score: 0.0,
+        id: id.clone(),
+        title: String::new(),
+        character_range,
+    };
+    let semantic = vec![
+        result(&other_note, 0..10, Some(0.1)),
+        result(&note, 20..40, Some(0.2)),
+    ];
+    let lexical = vec![result(&note, 25..30, None)];
+
+    // The sentence both searches found is ranked first and keeps the distance
+    let fused = fuse_results(vec![semantic, lexical], 10);
+    assert_eq!(fused.len(), 2);
+    assert_eq!(fused[0].id, note);
+    assert_eq!(fused[0].character_range, 20..40);
+    assert_eq!(fused[0].distance, Some(0.2));
+    assert_eq!(fused[0].score, 1.0 / 62.0 + 1.0 / 61.0);
+    assert_eq!(fused[1].id, other_note);
+    assert_eq!(fuse_results(Vec::new(), 10), Vec::new());
+}
+
+#[tokio::test]
+async fn test_search_modes() {
+    use crate::workspace::load_workspace_with;
+    use crate::{delete_workspace, save_note, unload_workspace};
+    _ = tracing_subscriber::fmt::try_init();
+
+    let workspace_path = std::env::temp_dir().join("testing-search-modes-workspace");
+    _ = std::fs::remove_dir_all(&workspace_path);
+    let workspace = load_workspace_with(workspace_path, Some(NotesEmbedder::hashing())).unwrap();
+
+    let course = save_note(
+        None,
+        "Parallel programming".to_string(),
+        "Computers can do many things at once. CS 4332 covers SIMD instructions.".to_string(),
+        workspace,
+    )
+    .await
+    .unwrap();
+    save_note(
+        None,
+        "Computer science".to_string(),
+        "Computer science courses cover many things.".to_string(),
+        workspace,
+    )
+    .await
+    .unwrap();
+
+    // Lexical search only finds notes with every word and points at the sentence with the words
+    let results = search(
+        "CS 4332".to_string(),
+        TagFilter::default(),
+        10,
+        Some(SearchMode::Lexical),
+        None,
+        workspace,
+    )
+    .await
+    .unwrap();
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0].id, course);
+    assert_eq!(results[0].distance, None);
+    assert_eq!(results[0].character_range, 38..71);
+
+    // Titles are searched too
+    let results = search(
+        "parallel".to_string(),
+        TagFilter::default(),
+        10,
+        Some(SearchMode::Lexical),
+        None,
+        workspace,
+    )
+    .await
+    .unwrap();
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0].id, course);
+
+    // Semantic search returns a distance for every result
+    let results = search(
+        "SIMD".to_string(),
+        TagFilter::default(),
+        10,
+        Some(SearchMode::Semantic),
+        None,
+        workspace,
+    )
+    .await
+    .unwrap();
+    assert!(!results.is_empty());
+    assert!(results.iter().all(|result| result.distance.is_some()));
+
+    // Hybrid search ranks the literal match first
+    let results = search(
+        "SIMD".to_string(),
+        TagFilter::default(),
+        10,
+        None,
+        None,
+        workspace,
+    )
+    .await
+    .unwrap();
+    assert_eq!(results[0].id, course);
+
+    delete_workspace(workspace).unwrap();
+    unload_workspace(workspace).unwrap();
+}
+
+#[tokio::test]
+async fn test_lexical_search_without_model() {
+    use crate::indexing::wait_for_indexing;
+    use crate::manifest::update_manifest;
+    use crate::model::ModelSource;
+    use crate::workspace::load_workspace_with;
+    use crate::{delete_workspace, save_note, unload_workspace};
+
+    let workspace_path = std::env::temp_dir().join("testing-lexical-search-without-model");
+    _ = std::fs::remove_dir_all(&workspace_path);
+    let workspace =
+        load_workspace_with(workspace_path.clone(), Some(NotesEmbedder::hashing())).unwrap();
+    let course = save_note(
+        None,
+        "Parallel programming".to_string(),
+        "CS 4332 covers SIMD instructions.".to_string(),
+        workspace,
+    )
+    .await
+    .unwrap();
+    wait_for_indexing(workspace).await.unwrap();
+    unload_workspace(workspace).unwrap();
+
+    // Load the workspace again with a model that can't be loaded
+    update_manifest(&workspace_path, |manifest| {
+        manifest.model_source = Some(ModelSource::Local(workspace_path.join("missing-model")))
+    })
+    .unwrap();
+    let workspace = load_workspace_with(workspace_path, None).unwrap();
+
+    // Lexical search never loads the model
+    let results = search(
+        "CS 4332".to_string(),
+        TagFilter::default(),
+        10,
+        Some(SearchMode::Lexical),
+        None,
+        workspace,
+    )
+    .await
+    .unwrap();
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0].id, course);
+    // Diversifying lexical results uses the stored sentence embeddings instead of the model
+    let results = search(
+        "CS 4332".to_string(),
+        TagFilter::default(),
+        10,
+        Some(SearchMode::Lexical),
+        Some(0.5),
+        workspace,
+    )
+    .await
+    .unwrap();
+    assert_eq!(results.len(), 1);
+
+    // Semantic search needs the model
+    assert!(matches!(
+        search(
+            "SIMD".to_string(),
+            TagFilter::default(),
+            10,
+            Some(SearchMode::Semantic),
+            None,
+            workspace,
+        )
+        .await,
+        Err(LinknotesError::EmbeddingModelUnavailable(_))
+    ));
+
+    delete_workspace(workspace).unwrap();
+    unload_workspace(workspace).unwrap();
+}
+
+#[tokio::test]
+async fn test_search_query_language() {
+    use crate::note::{set_tags, Tag};
+    use crate::workspace::load_workspace_with;
+    use crate::{delete_workspace, save_note, unload_workspace};
+    _ = tracing_subscriber::fmt::try_init();
+
+    let workspace_path = std::env::temp_dir().join("testing-search-query-language-workspace");
+    _ = std::fs::remove_dir_all(&workspace_path);
+    let workspace = load_workspace_with(workspace_path, Some(NotesEmbedder::hashing())).unwrap();
+
+    let tag = |name: &str| Tag {
+        name: name.to_string(),
+        manual: true,
+    };
+    let final_note = save_note(
+        None,
+        "Definite integrals".to_string(),
+        "Integrals are the area under a curve.".to_string(),
+        workspace,
+    )
+    .await
+    .unwrap();
+    set_tags(final_note.clone(), vec![tag("Math")], workspace)
+        .await
+        .unwrap();
+    let draft = save_note(
+        None,
+        "Integrals draft".to_string(),
+        "Integrals are the area under a curve, maybe.".to_string(),
+        workspace,
+    )
+    .await
+    .unwrap();
+    set_tags(draft.clone(), vec![tag("Math"), tag("Draft")], workspace)
+        .await
+        .unwrap();
+
+    let ids = |results: Vec<SearchResult>| {
+        let mut ids: Vec<NoteId> = results.into_iter().map(|result| result.id).collect();
+        ids.dedup();
+        ids
+    };
+    for mode in [
+        SearchMode::Semantic,
+        SearchMode::Lexical,
+        SearchMode::Hybrid,
+    ] {
+        let results = search(
+            r#"tag:Math -tag:Draft title:"integrals" area"#.to_string(),
+            TagFilter::default(),
+            10,
+            Some(mode),
+            None,
+            workspace,
+        )
+        .await
+        .unwrap();
+        assert_eq!(ids(results), vec![final_note.clone()]);
+
+        let results = search(
+            r#""maybe" area OR title:nothing"#.to_string(),
+            TagFilter::default(),
+            10,
+            Some(mode),
+            None,
+            workspace,
+        )
+        .await
+        .unwrap();
+        assert_eq!(ids(results), vec![draft.clone()]);
+    }
+
+    // Notes created before a date are filtered out
+    let results = search(
+        "created:<2000-01-01 area".to_string(),
+        TagFilter::default(),
+        10,
+        None,
+        None,
+        workspace,
+    )
+    .await
+    .unwrap();
+    assert!(results.is_empty());
+
+    assert!(matches!(
+        search(
+            "(area".to_string(),
+            TagFilter::default(),
+            10,
+            None,
+            None,
+            workspace
+        )
+        .await,
+        Err(LinknotesError::InvalidQuery(_))
+    ));
+
+    // Tag filters can match any of the tags, exclude tags and ignore tags the classifier guessed
+    let guessed = Tag {
+        name: "Guessed".to_string(),
+        manual: false,
+    };
+    set_tags(
+        draft.clone(),
+        vec![tag("Math"), tag("Draft"), guessed],
+        workspace,
+    )
+    .await
+    .unwrap();
+    let any = TagFilter {
+        any: vec!["Guessed".to_string(), "Unused".to_string()],
+        ..Default::default()
+    };
+    let exclude = TagFilter {
+        all: vec!["Math".to_string()],
+        exclude: vec!["Draft".to_string()],
+        ..Default::default()
+    };
+    let manual_only = TagFilter {
+        manual_only: true,
+        ..any.clone()
+    };
+    for (tags, expected) in [
+        (any, vec![draft.clone()]),
+        (exclude, vec![final_note.clone()]),
+        (manual_only, Vec::new()),
+    ] {
+        let results = search("area".to_string(), tags, 10, None, None, workspace)
+            .await
+            .unwrap();
+        assert_eq!(ids(results), expected);
+    }
+
+    delete_workspace(workspace).unwrap();
+    unload_workspace(workspace).unwrap();
+}
+
+#[tokio::test]
+async fn test_diversified_search() {
+    use crate::workspace::load_workspace_with;
+    use crate::{delete_workspace, save_note, unload_workspace};
+    _ = tracing_subscriber::fmt::try_init();
+
+    let workspace_path = std::env::temp_dir().join("testing-diversified-search-workspace");
+    _ = std::fs::remove_dir_all(&workspace_path);
+    let (embedder, counting) = NotesEmbedder::counting();
+    let workspace = load_workspace_with(workspace_path, Some(embedder)).unwrap();
+
+    let body = "The math is mathing QED. The math is mathing QED. Cats purr.";
+    let id = save_note(None, "math".to_string(), body.to_string(), workspace)
+        .await
+        .unwrap();
+    let sentence = |range: Range<usize>| body[byte_range(body, range)].to_string();
+
+    // Without diversification the repeated sentence fills the results
+    let results = search(
+        "The math is mathing QED".to_string(),
+        TagFilter::default(),
+        2,
+        Some(SearchMode::Semantic),
+        None,
+        workspace,
+    )
+    .await
+    .unwrap();
+    assert_eq!(results.len(), 2);
+    assert!(results
+        .iter()
+        .all(|result| sentence(result.character_range.clone()).contains("math")));
+
+    // With diversification the second result is the distinct sentence. Only the query is embedded, because the
+    // sentences have stored embeddings
+    let embedded = counting.embedded();
+    let results = search(
+        "The math is mathing QED".to_string(),
+        TagFilter::default(),
+        2,
+        Some(SearchMode::Semantic),
+        Some(0.3),
+        workspace,
+    )
+    .await
+    .unwrap();
+    assert_eq!(results.len(), 2);
+    assert!(sentence(results[0].character_range.clone()).contains("math"));
+    assert!(sentence(results[1].character_range.clone()).contains("Cats"));
+    assert_eq!(counting.embedded(), embedded + 1);
+
+    let results = context_search(
+        None,
+        "The math is mathing QED.".to_string(),
+        0,
+        2,
+        3,
+        Some(0.3),
+        workspace,
+    )
+    .await
+    .unwrap();
+    assert_eq!(results.len(), 2);
+    assert!(results.iter().all(|result| result.id == id));
+    let relevant: Vec<&str> = results
+        .iter()
+        .map(|result| &result.text[result.relevant_range.clone()])
+        .collect();
+    assert!(relevant[1].contains("Cats"));
+
+    assert!(matches!(
+        search(
+            "math".to_string(),
+            TagFilter::default(),
+            2,
+            None,
+            Some(2.0),
+            workspace
+        )
+        .await,
+        Err(LinknotesError::InvalidInput(_))
+    ));
+
+    delete_workspace(workspace).unwrap();
+    unload_workspace(workspace).unwrap();
 }
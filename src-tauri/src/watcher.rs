@@ -0,0 +1,453 @@
+/*!
+# Prologue Comments
+## Name of Code Artifact: Notes Directory Watcher
+
+## Brief Description: This code watches the notes directory of a workspace for files that are created, modified, deleted or renamed outside of the app (for example by a text editor or a sync tool). Changed files are indexed through the same pipeline as `save_note`, deleted files are removed from the workspace and a `notes-changed` event is sent to the frontend so it can refresh.
+## Programmer’s Name: agent
+
+## Date Created: 2026-10-17
+## Dates Revised and Description of Revisions:
+## -> 2026-10-17: Added the opt-in notes directory watcher.
+## -> 2026-10-17: Renamed notes whose file was moved outside of the app instead of replacing them with a new note.
+
+## Preconditions:
+- The workspace must be loaded and writable before it can be watched.
+
+## Acceptable and Unacceptable Input Values/Types:
+- Each tauri command takes an object with types defined by the type definition in the code. Arguments are represented as
+fields in that object.
+- Files that are not valid UTF-8 or don't have a note file name are ignored.
+
+## Postconditions:
+
+- After the debounce timeout, the index contains the text of every note file that changed on disk.
+
+## Return Values/Types:
+
+- Commands return a `LinknotesError` if the workspace is not loaded, is read-only or can't be watched.
+
+## Side Effects:
+-  The workspace database is modified when files change.
+-  A `notes-changed` event is emitted after every batch of changes.
+
+## Invariants:
+
+- Batches of changes are indexed one at a time, so a note is never saved twice at once by the watcher.
+
+## Known Faults:
+
+- A file renamed outside of the app is only recognized as a rename if the old file and the new file are in the same batch of changes and no other new file in the batch has the same text. Otherwise it is indexed as a new note and the old note, its id and its manual tags are removed.
+
+*/
+
+use notify_debouncer_mini::notify::{RecommendedWatcher, RecursiveMode};
+use notify_debouncer_mini::{new_debouncer, DebounceEventResult, Debouncer};
+use serde::Serialize;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter};
+
+use crate::error::LinknotesError;
+use crate::indexing::wait_for_indexing;
+use crate::metadata::metadata_key;
+use crate::note::{
+    find_note_by_file, import_note, read_note, remove_note, rename_moved_note, save_note, NoteId,
+};
+use crate::workspace::{file_name_to_title, get_workspace_ref, WorkspaceId};
+
+/// The event emitted after the watcher indexes a batch of changes
+pub const NOTES_CHANGED_EVENT: &str = "notes-changed";
+/// How long the notes directory must be quiet before a batch of changes is indexed. Editors often write a file
+/// several times when saving it
+const DEBOUNCE_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// The payload of the `notes-changed` event.
+///
+/// # Fields
+///
+/// * `workspace_id` - The workspace the notes are in.
+/// * `changed` - The notes that were created or modified.
+/// * `removed` - The notes whose files were deleted.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct NotesChanged {
+    pub workspace_id: WorkspaceId,
+    pub changed: Vec<NoteId>,
+    pub removed: Vec<NoteId>,
+}
+
+impl NotesChanged {
+    fn is_empty(&self) -> bool {
+        self.changed.is_empty() && self.removed.is_empty()
+    }
+}
+
+/// A running watcher on the notes directory of a workspace. The watcher stops when this is dropped.
+pub(crate) struct NotesWatcher {
+    _debouncer: Debouncer<RecommendedWatcher>,
+}
+
+impl NotesWatcher {
+    /// Start watching a notes directory. `on_change` is called after every batch of changes that updated the index.
+    pub(crate) fn new(
+        workspace_id: WorkspaceId,
+        notes_dir: &Path,
+        on_change: impl Fn(NotesChanged) + Send + Sync + 'static,
+    ) -> Result<Self, LinknotesError> {
+        let on_change = Arc::new(on_change);
+        // Only index one batch at a time
+        let indexing = Arc::new(tokio::sync::Mutex::new(()));
+        let mut debouncer = new_debouncer(DEBOUNCE_TIMEOUT, move |events: DebounceEventResult| {
+            let events = match events {
+                Ok(events) => events,
+                Err(err) => {
+                    tracing::error!("Failed to watch notes: {}", err);
+                    return;
+                }
+            };
+            let mut paths: Vec<PathBuf> = events.into_iter().map(|event| event.path).collect();
+            paths.sort();
+            paths.dedup();
+            let on_change = on_change.clone();
+            let indexing = indexing.clone();
+            tauri::async_runtime::spawn(async move {
+                let _guard = indexing.lock().await;
+                match reindex_files(workspace_id, paths).await {
+                    Ok(changed) if !changed.is_empty() => on_change(changed),
+                    Ok(_) => {}
+                    Err(err) => tracing::error!("Failed to index changed notes: {}", err),
+                }
+            });
+        })
+        .map_err(|err| LinknotesError::Io(err.to_string()))?;
+        debouncer
+            .watcher()
+            .watch(notes_dir, RecursiveMode::NonRecursive)
+            .map_err(|err| LinknotesError::Io(err.to_string()))?;
+        Ok(Self {
+            _debouncer: debouncer,
+        })
+    }
+}
+
+/// A note whose file was moved to a new file name outside of the app
+struct MovedNote {
+    id: NoteId,
+    from: PathBuf,
+    to: PathBuf,
+    title: String,
+}
+
+/// Find the notes in a batch of changes that were renamed outside of the app. Editors and file managers report a
+/// rename as the old file being deleted and a new file being created. A deleted note and a new file are paired if the
+/// text of the file is the indexed text of the note, and no other deleted note or new file in the batch has that text.
+async fn find_moved_notes(
+    workspace_id: WorkspaceId,
+    paths: &[PathBuf],
+) -> Result<Vec<MovedNote>, LinknotesError> {
+    let mut deleted = Vec::new();
+    let mut created = Vec::new();
+    for path in paths {
+        let Some(title) = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .and_then(file_name_to_title)
+        else {
+            continue;
+        };
+        let existing = {
+            let workspace = get_workspace_ref(workspace_id)?;
+            let document_table = workspace.document_table().await?;
+            find_note_by_file(document_table.table().db(), path).await?
+        };
+        match (path.exists(), existing) {
+            (false, Some((id, _))) => match read_note(id.clone(), workspace_id).await {
+                Ok(note) => deleted.push((id, path, note.document.body().to_string())),
+                Err(err) => tracing::error!("Failed to read deleted note {}: {}", id, err),
+            },
+            (true, None) => match std::fs::read_to_string(path) {
+                Ok(text) => created.push((path, title, text)),
+                Err(err) => tracing::error!("Failed to read {:?}: {}", path, err),
+            },
+            _ => {}
+        }
+    }
+
+    let mut moved = Vec::new();
+    for (id, from, text) in &deleted {
+        let mut created_with_text = created.iter().filter(|(_, _, created)| created == text);
+        let (Some((to, title, _)), None) = (created_with_text.next(), created_with_text.next())
+        else {
+            continue;
+        };
+        let deleted_with_text = deleted
+            .iter()
+            .filter(|(_, _, deleted)| deleted == text)
+            .count();
+        if deleted_with_text == 1 {
+            moved.push(MovedNote {
+                id: id.clone(),
+                from: from.to_path_buf(),
+                to: to.to_path_buf(),
+                title: title.clone(),
+            });
+        }
+    }
+    Ok(moved)
+}
+
+/// Bring the index up to date with a list of files in the notes directory. Notes whose file was renamed are renamed
+/// with their id, tags and links kept. New files are added with [`import_note`], other files that exist are saved with
+/// [`save_note`] if their text changed, and notes whose file no longer exists are removed with [`remove_note`].
+/// Failures for a single file are logged and don't stop the other files from being indexed.
+///
+/// # Returns
+///
+/// The notes that were changed or removed, or `LinknotesError::WorkspaceNotLoaded` if the workspace was unloaded.
+pub(crate) async fn reindex_files(
+    workspace_id: WorkspaceId,
+    paths: Vec<PathBuf>,
+) -> Result<NotesChanged, LinknotesError> {
+    wait_for_indexing(workspace_id).await?;
+    let mut changed = NotesChanged {
+        workspace_id,
+        changed: Vec::new(),
+        removed: Vec::new(),
+    };
+
+    let mut handled = HashSet::new();
+    for moved in find_moved_notes(workspace_id, &paths).await? {
+        tracing::info!("Note file {:?} was moved to {:?}", moved.from, moved.to);
+        let Some(file_name) = metadata_key(&moved.to) else {
+            continue;
+        };
+        match rename_moved_note(moved.id.clone(), moved.title, file_name, workspace_id).await {
+            Ok(relinked) => {
+                // The notes that linked to the old title were rewritten too
+                changed.changed.push(moved.id);
+                changed.changed.extend(relinked);
+                handled.insert(moved.from);
+                handled.insert(moved.to);
+            }
+            // The files are indexed one by one below instead
+            Err(err) => tracing::error!("Failed to rename note {}: {}", moved.id, err),
+        }
+    }
+
+    for path in paths {
+        if handled.contains(&path) {
+            continue;
+        }
+        let Some(title) = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .and_then(file_name_to_title)
+        else {
+            continue;
+        };
+        let existing = {
+            let workspace = get_workspace_ref(workspace_id)?;
+            let document_table = workspace.document_table().await?;
+            find_note_by_file(document_table.table().db(), &path).await?
+        };
+
+        let result = if path.exists() {
+            reindex_file(workspace_id, &path, title, existing)
+                .await
+                .map(|id| changed.changed.extend(id))
+        } else if let Some((id, _)) = existing {
+            tracing::info!("Note file {:?} was deleted", path);
+            remove_note(id.clone(), workspace_id)
+                .await
+                .map(|_| changed.removed.push(id))
+        } else {
+            Ok(())
+        };
+        if let Err(err) = result {
+            tracing::error!("Failed to index {:?}: {}", path, err);
+        }
+    }
+    Ok(changed)
+}
+
+/// Save a note file that exists on disk if the text is different from the text in the index. Returns the id of the
+/// note if it was saved.
+async fn reindex_file(
+    workspace_id: WorkspaceId,
+    path: &Path,
+    title: String,
+    existing: Option<(NoteId, String)>,
+) -> Result<Option<NoteId>, LinknotesError> {
+    let text = std::fs::read_to_string(path)?;
+    let (id, title) = match existing {
+        Some((id, stored_title)) => {
+            // save_note writes the file too, so most events are for text that is already indexed
+            let note = read_note(id.clone(), workspace_id).await?;
+            if note.document.body() == text {
+                return Ok(None);
+            }
+            // Keep the title from the index. Notes saved before titles were escaped have a different file name
+            (Some(id), stored_title)
+        }
+        // A new file keeps its name, so a note that shares its title with another note isn't moved to another file
+        None => {
+            tracing::info!("Note file {:?} was created", path);
+            return import_note(path, title, text, workspace_id).await.map(Some);
+        }
+    };
+    tracing::info!("Note file {:?} changed", path);
+    save_note(id, title, text, workspace_id).await.map(Some)
+}
+
+/// Start watching the notes directory of a workspace for changes made outside of the app. The workspace emits a
+/// `notes-changed` event with a [`NotesChanged`] payload after it indexes a batch of changes. Watching a workspace
+/// that is already watched restarts the watcher.
+///
+/// # Errors
+///
+/// This function will return an error if:
+/// - The workspace is not loaded
+/// - The workspace is read-only
+/// - The notes directory can't be created or watched
+#[tauri::command]
+pub fn watch_workspace(app: AppHandle, workspace_id: WorkspaceId) -> Result<(), LinknotesError> {
+    tracing::info!("watch_workspace called with id: {:?}", workspace_id);
+    let workspace = get_workspace_ref(workspace_id)?;
+    workspace.check_writable()?;
+    let notes_dir = workspace.notes_dir()?;
+    let watcher = NotesWatcher::new(workspace_id, &notes_dir, move |changed| {
+        if let Err(err) = app.emit(NOTES_CHANGED_EVENT, changed) {
+            tracing::error!("Failed to emit {}: {}", NOTES_CHANGED_EVENT, err);
+        }
+    })?;
+    workspace.set_watcher(Some(watcher));
+    Ok(())
+}
+
+/// Stop watching the notes directory of a workspace. This does nothing if the workspace is not watched.
+#[tauri::command]
+pub fn unwatch_workspace(workspace_id: WorkspaceId) -> Result<(), LinknotesError> {
+    tracing::info!("unwatch_workspace called with id: {:?}", workspace_id);
+    get_workspace_ref(workspace_id)?.set_watcher(None);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_reindex_files() {
+    use crate::embedder::NotesEmbedder;
+    use crate::workspace::{delete_workspace, load_workspace_with, unload_workspace};
+
+    _ = tracing_subscriber::fmt::try_init();
+
+    let temp = std::env::temp_dir();
+    let workspace_path = temp.join("testing-watcher-workspace");
+    _ = std::fs::remove_dir_all(&workspace_path);
+    let workspace = load_workspace_with(workspace_path, Some(NotesEmbedder::hashing())).unwrap();
+
+    let integrals = save_note(
+        None,
+        "Integrals".to_string(),
+        "Integrals are the area under a curve.".to_string(),
+        workspace,
+    )
+    .await
+    .unwrap();
+    let limits = save_note(
+        None,
+        "Limits".to_string(),
+        "Limits describe the value a function approaches.".to_string(),
+        workspace,
+    )
+    .await
+    .unwrap();
+    let notes_dir = get_workspace_ref(workspace).unwrap().notes_dir().unwrap();
+
+    // Files written by save_note are already indexed
+    let paths = vec![
+        notes_dir.join("Integrals.txt"),
+        notes_dir.join("Limits.txt"),
+    ];
+    let changed = reindex_files(workspace, paths).await.unwrap();
+    assert!(changed.is_empty());
+
+    // Edit one note, delete another and create a new one outside of the app
+    std::fs::write(
+        notes_dir.join("Integrals.txt"),
+        "Integrals are the inverse of derivatives.",
+    )
+    .unwrap();
+    std::fs::remove_file(notes_dir.join("Limits.txt")).unwrap();
+    std::fs::write(notes_dir.join("New%20Note.txt"), "Written in vim.").unwrap();
+    std::fs::write(notes_dir.join("not a note.md"), "Ignored").unwrap();
+    let paths = vec![
+        notes_dir.join("Integrals.txt"),
+        notes_dir.join("Limits.txt"),
+        notes_dir.join("New%20Note.txt"),
+        notes_dir.join("not a note.md"),
+    ];
+    let changed = reindex_files(workspace, paths).await.unwrap();
+    assert_eq!(changed.changed.len(), 2);
+    assert_eq!(changed.changed[0], integrals);
+    assert_eq!(changed.removed, vec![limits.clone()]);
+
+    let note = read_note(integrals, workspace).await.unwrap();
+    assert_eq!(
+        note.document.body(),
+        "Integrals are the inverse of derivatives."
+    );
+    let note = read_note(changed.changed[1].clone(), workspace)
+        .await
+        .unwrap();
+    assert_eq!(note.document.title(), "New Note");
+    assert_eq!(
+        read_note(limits, workspace).await.err(),
+        Some(LinknotesError::NoteNotFound)
+    );
+
+    // A file renamed outside of the app keeps its id and the links to it
+    let derivatives = save_note(
+        None,
+        "Derivatives".to_string(),
+        "Derivatives are the slope of a curve. See [[Integrals]].".to_string(),
+        workspace,
+    )
+    .await
+    .unwrap();
+    wait_for_indexing(workspace).await.unwrap();
+    std::fs::rename(
+        notes_dir.join("Integrals.txt"),
+        notes_dir.join("Antiderivatives.txt"),
+    )
+    .unwrap();
+    let paths = vec![
+        notes_dir.join("Antiderivatives.txt"),
+        notes_dir.join("Integrals.txt"),
+    ];
+    let changed = reindex_files(workspace, paths).await.unwrap();
+    assert!(changed.removed.is_empty());
+    assert!(changed.changed.contains(&integrals));
+    let note = read_note(integrals.clone(), workspace).await.unwrap();
+    assert_eq!(note.document.title(), "Antiderivatives");
+    let note = read_note(derivatives, workspace).await.unwrap();
+    assert_eq!(
+        note.document.body(),
+        "Derivatives are the slope of a curve. See [[Antiderivatives]]."
+    );
+
+    // The note is found by the name of its file even if the path is in another directory
+    let found = {
+        let workspace = get_workspace_ref(workspace).unwrap();
+        let document_table = workspace.document_table().await.unwrap();
+        find_note_by_file(
+            document_table.table().db(),
+            &temp.join("moved-workspace").join("Antiderivatives.txt"),
+        )
+        .await
+        .unwrap()
+    };
+    assert_eq!(found, Some((integrals, "Antiderivatives".to_string())));
+
+    delete_workspace(workspace).unwrap();
+    unload_workspace(workspace).unwrap();
+}
@@ -10,6 +10,15 @@
 2024-10-14: Initial creation of the workspace module.
 2024-10-15: Added workspace loading and unloading functions.
 2024-10-16: Implemented note management functions.
+2026-10-17: Passed the embedder to workspaces when they are loaded instead of choosing it for the whole process.
+2026-10-17: Saved the notes waiting to be indexed when a workspace is unloaded and queued them again when it is loaded.
+2026-10-17: Returned notes that are waiting to be indexed from files_in_workspace instead of waiting for the queue.
+2026-10-17: Added file names with the note id for notes that share a title with another note.
+2026-10-17: Loaded the embedder of the model source in the workspace manifest.
+2026-10-17: Opened the document table with an embedder that loads the model when it is first used, so lexical search never loads it.
+2026-10-17: Backfilled the creation time of notes stored before it was recorded when the workspace is opened.
+2026-10-17: Refused to load a workspace that is already open read-only with write access, and made get_workspace_id only look up loaded workspaces.
+2026-10-17: Reserved COM0, LPT0 and the ports with superscript digits, and indexed the lowercase file names of notes.
 
 
 ## Preconditions: A Tauri application context is required for the run function.
@@ -42,6 +51,7 @@ Acceptable and Unacceptable Input Values/Types:
 
 ### Potential performance bottleneck during embedding generation if BERT initialization is delayed.
 ### Edge cases with workspace paths or malformed input data may cause unexpected behavior.
+### The snapshot of a read-only workspace is a copy of the database files, not a RocksDB checkpoint. A write that doesn't change the size or modification time of any file while the files are copied isn't detected.
 
 */
 
@@ -49,69 +59,371 @@ use kalosm::language::*;
 use parking_lot::{MappedRwLockReadGuard, RwLock, RwLockReadGuard};
 use serde::{Deserialize, Serialize};
 use slab::Slab;
-use std::path::PathBuf;
-use std::sync::OnceLock;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, OnceLock};
 use surrealdb::{engine::local::RocksDb, Surreal};
 
 use crate::classifier::TagClassifier;
-use crate::note::{ContextualDocument, Tag};
-use crate::{bert, ContextualDocumentTable};
+use crate::embedder::{embedder, NotesEmbedder};
+use crate::embedding_migration::record_embedding_model;
+use crate::error::LinknotesError;
+use crate::indexing::{queued_document, requeue_pending_jobs, save_pending_jobs, IndexingQueue};
+use crate::lexical::define_full_text_index;
+use crate::lock::WorkspaceLock;
+use crate::manifest::{migrate_workspace, open_manifest, read_manifest};
+use crate::model::ModelSource;
+use crate::note::{define_location_index, ContextualDocument, NoteId, Tag};
+use crate::watcher::NotesWatcher;
+use crate::ContextualDocumentTable;
+
+/// The extension of the files notes are stored in
+const NOTE_EXTENSION: &str = ".txt";
+/// Most filesystems don't allow file names longer than 255 bytes
+const MAX_FILE_NAME_BYTES: usize = 255;
+/// File names windows reserves for devices. These names can't be used even with an extension. Windows also reserves
+/// the ports numbered 0 and with the superscript digits 1 to 3
+const RESERVED_FILE_NAMES: &[&str] = &[
+    "CON", "PRN", "AUX", "NUL", "COM0", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7",
+    "COM8", "COM9", "COM¹", "COM²", "COM³", "LPT0", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6",
+    "LPT7", "LPT8", "LPT9", "LPT¹", "LPT²", "LPT³",
+];
+
+/// NoteTitleError is an error type that indicates that a title can't be used as the title of a note.
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum NoteTitleError {
+    /// The title is empty
+    #[error("the title is empty")]
+    Empty,
+    /// The escaped title is longer than the filesystem allows
+    #[error("the title is too long to be used as a file name")]
+    TooLong,
+}
+
+/// Separates the escaped title from the note id in the file name of a note that shares its title with another note.
+/// `%` is always escaped as `%XX` in titles, so `%~` never appears in the file name of a title.
+const NOTE_ID_SEPARATOR: &str = "%~";
+
+/// Convert a title into a file name that is safe to use on every filesystem. The conversion is reversible with
+/// [`file_name_to_title`].
+///
+/// Characters that are not allowed in file names on some filesystems (`/ \ : * ? " < > |`), control characters, a
+/// leading `.`, and a trailing `.` or space are escaped as `%XX` for each utf8 byte. `%` itself is always escaped so
+/// the escaping can be reversed. If the title starts with a name windows reserves for devices (like `CON` or `LPT1`),
+/// the first character is escaped as well.
+///
+/// # Errors
+///
+/// Returns an error if the title is empty or the file name would be too long.
+pub(crate) fn title_to_file_name(title: &str) -> Result<String, NoteTitleError> {
+    if title.is_empty() {
+        return Err(NoteTitleError::Empty);
+    }
+    let stem = title.split('.').next().unwrap_or_default().trim_end();
+    let reserved = RESERVED_FILE_NAMES
+        .iter()
+        .any(|name| name.eq_ignore_ascii_case(stem));
+    let char_count = title.chars().count();
+
+    let mut file_name = String::with_capacity(title.len() + NOTE_EXTENSION.len());
+    for (index, c) in title.chars().enumerate() {
+        let first = index == 0;
+        let last = index == char_count - 1;
+        let escape = matches!(
+            c,
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' | '%'
+        ) || c.is_control()
+            || (first && (c == '.' || reserved))
+            || (last && (c == '.' || c == ' '));
+        if escape {
+            let mut bytes = [0; 4];
+            for byte in c.encode_utf8(&mut bytes).bytes() {
+                file_name.push_str(&format!("%{:02X}", byte));
+            }
+        } else {
+            file_name.push(c);
+        }
+    }
+    file_name.push_str(NOTE_EXTENSION);
+
+    if file_name.len() > MAX_FILE_NAME_BYTES {
+        return Err(NoteTitleError::TooLong);
+    }
+    Ok(file_name)
+}
+
+/// Get a file name for a note that is unique even if other notes have the same title. The id of the note is added
+/// after the escaped title, and [`file_name_to_title`] removes it again.
+///
+/// # Errors
+///
+/// Returns an error if the title is empty or the file name would be too long.
+pub(crate) fn title_to_unique_file_name(
+    title: &str,
+    note_id: &NoteId,
+) -> Result<String, NoteTitleError> {
+    let file_name = title_to_file_name(title)?;
+    let stem = file_name.strip_suffix(NOTE_EXTENSION).unwrap_or(&file_name);
+    let file_name = format!("{stem}{NOTE_ID_SEPARATOR}{note_id}{NOTE_EXTENSION}");
+    if file_name.len() > MAX_FILE_NAME_BYTES {
+        return Err(NoteTitleError::TooLong);
+    }
+    Ok(file_name)
+}
+
+/// Convert a file name created by [`title_to_file_name`] or [`title_to_unique_file_name`] back into the title. Returns
+/// `None` if the file name is not the file name of a note.
+pub(crate) fn file_name_to_title(file_name: &str) -> Option<String> {
+    let escaped = file_name.strip_suffix(NOTE_EXTENSION)?;
+    let escaped = match escaped.split_once(NOTE_ID_SEPARATOR) {
+        Some((escaped, _)) => escaped,
+        None => escaped,
+    };
+    let mut bytes = Vec::with_capacity(escaped.len());
+    let mut iter = escaped.bytes();
+    while let Some(byte) = iter.next() {
+        if byte == b'%' {
+            let high = (iter.next()? as char).to_digit(16)?;
+            let low = (iter.next()? as char).to_digit(16)?;
+            bytes.push((high * 16 + low) as u8);
+        } else {
+            bytes.push(byte);
+        }
+    }
+    let title = String::from_utf8(bytes).ok()?;
+    (!title.is_empty()).then_some(title)
+}
+
+/// How a workspace was opened
+enum WorkspaceAccess {
+    /// This process holds the lock file of the workspace and opens the database in place
+    Writable(WorkspaceLock),
+    /// Another process may hold the lock file. The database is copied to a snapshot directory which is opened instead,
+    /// so the database the other process has open is never touched. See [`copy_databases`] for how the copy is kept
+    /// consistent
+    ReadOnly { snapshot: PathBuf },
+}
+
+impl Drop for WorkspaceAccess {
+    fn drop(&mut self) {
+        if let Self::ReadOnly { snapshot } = self {
+            _ = std::fs::remove_dir_all(snapshot);
+        }
+    }
+}
+
+/// How many times copying the database of a read-only workspace is tried before giving up
+const SNAPSHOT_ATTEMPTS: usize = 3;
+
+/// The path, size and modification time of every file in a directory. If the fingerprint is the same before and
+/// after a copy, no file was written to while it was copied.
+fn dir_fingerprint(
+    dir: &Path,
+) -> std::io::Result<Vec<(PathBuf, u64, Option<std::time::SystemTime>)>> {
+    let mut fingerprint = Vec::new();
+    if !dir.exists() {
+        return Ok(fingerprint);
+    }
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        let metadata = entry.metadata()?;
+        if metadata.is_dir() {
+            fingerprint.extend(dir_fingerprint(&entry.path())?);
+        } else {
+            fingerprint.push((entry.path(), metadata.len(), metadata.modified().ok()));
+        }
+    }
+    fingerprint.sort();
+    Ok(fingerprint)
+}
+
+/// Copy the databases of a workspace into a snapshot directory. RocksDB has no way to take a checkpoint through
+/// surrealdb, so the files are copied while the process that has the workspace open may still be writing to them. The
+/// copy is only kept if no file changed while it was copied. Otherwise it is tried again.
+///
+/// Returns `LinknotesError::SnapshotFailed` if the copy fails or the databases change during every attempt.
+fn copy_databases(location: &Path, snapshot: &Path) -> Result<(), LinknotesError> {
+    let mut last_error = String::new();
+    for attempt in 1..=SNAPSHOT_ATTEMPTS {
+        let copied = ["notes.db", "documents"]
+            .into_iter()
+            .try_fold(true, |unchanged, database| {
+                let source = location.join(database);
+                let target = snapshot.join(database);
+                if target.exists() {
+                    std::fs::remove_dir_all(&target)?;
+                }
+                if !source.exists() {
+                    return Ok::<_, std::io::Error>(unchanged);
+                }
+                let before = dir_fingerprint(&source)?;
+                copy_dir_all(&source, &target)?;
+                Ok(unchanged && before == dir_fingerprint(&source)?)
+            });
+        match copied {
+            Ok(true) => return Ok(()),
+            Ok(false) => last_error = "the database changed while it was copied".to_string(),
+            Err(err) => last_error = err.to_string(),
+        }
+        tracing::warn!(
+            "Failed to copy the database of {:?} (attempt {}): {}",
+            location,
+            attempt,
+            last_error
+        );
+    }
+    Err(LinknotesError::SnapshotFailed(last_error))
+}
+
+/// Copy a directory and everything inside it
+pub(crate) fn copy_dir_all(from: &Path, to: &Path) -> std::io::Result<()> {
+    std::fs::create_dir_all(to)?;
+    for entry in std::fs::read_dir(from)? {
+        let entry = entry?;
+        let target = to.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            copy_dir_all(&entry.path(), &target)?;
+        } else {
+            std::fs::copy(entry.path(), target)?;
+        }
+    }
+    Ok(())
+}
 
 pub struct Workspace {
     pub location: PathBuf,
-    table: OnceLock<anyhow::Result<ContextualDocumentTable>>,
+    generation: u64,
+    table: OnceLock<Result<ContextualDocumentTable, LinknotesError>>,
     tags: RwLock<Vec<String>>,
     classifier: RwLock<Option<TagClassifier>>,
     lock: tokio::sync::Mutex<()>,
+    watcher: parking_lot::Mutex<Option<NotesWatcher>>,
+    indexing: Arc<IndexingQueue>,
+    embedder: Option<NotesEmbedder>,
+    // This must be dropped after the table so the database is closed before the lock or snapshot is removed
+    access: WorkspaceAccess,
 }
 
 /// Represents a workspace that manages documents, tags, and a classifier.
 ///
 /// # Fields
 /// - `location`: The file path where the workspace is located.
+/// - `generation`: A unique number for this load of the workspace. Ids of workspaces that were unloaded don't match the generation of a workspace that later reuses the slot.
 /// - `table`: A lock for the document table, initialized once.
 /// - `lock`: A mutex lock for synchronizing access to the document table.
 /// - `tags`: A read-write lock for managing tags associated with documents.
 /// - `classifier`: A read-write lock for the document classifier.
+/// - `watcher`: The watcher on the notes directory if the workspace is watched.
+/// - `indexing`: The queue of saved notes waiting to be indexed in the background.
+/// - `embedder`: The embedder the workspace was loaded with, or `None` to use the model source in the workspace manifest.
+/// - `access`: The lock file the workspace holds, or the snapshot the workspace reads from if it was opened read-only.
 ///
 /// # Methods
-/// - `new(location: PathBuf) -> Self`: Creates a new workspace at the specified location.
-/// - `document_path(&self, title: &str) -> anyhow::Result<PathBuf>`: Returns the file path for a document with the given title, creating the notes directory if it doesn't exist.
-/// - `files(&self) -> anyhow::Result<Vec<ContextualDocument>>`: Asynchronously retrieves all contextual documents from the document table.
-/// - `document_table(&self) -> anyhow::Result<&ContextualDocumentTable>`: Asynchronously initializes and returns the document table, creating the database connection and table if necessary.
+/// - `new(location: PathBuf, generation: u64, access: WorkspaceAccess, embedder: Option<NotesEmbedder>) -> Self`: Creates a new workspace at the specified location.
+/// - `is_read_only(&self) -> bool`: Returns true if the workspace was opened in read-only mode.
+/// - `check_writable(&self) -> Result<(), LinknotesError>`: Returns `LinknotesError::WorkspaceReadOnly` if the workspace was opened in read-only mode.
+/// - `notes_dir(&self) -> Result<PathBuf, LinknotesError>`: Returns the directory notes are stored in, creating it if it doesn't exist.
+/// - `set_watcher(&self, watcher: Option<NotesWatcher>)`: Replaces the watcher on the notes directory.
+/// - `indexing(&self) -> Arc<IndexingQueue>`: Returns the background indexing queue of the workspace.
+/// - `model_source(&self) -> Result<ModelSource, LinknotesError>`: Returns the model source in the workspace manifest, or the source configured for the app if the manifest doesn't have one.
+/// - `model_id(&self) -> Result<String, LinknotesError>`: Returns the id of the model notes in the workspace are embedded with without loading it.
+/// - `lazy_embedder(&self) -> Result<NotesEmbedder, LinknotesError>`: Returns the embedder of the workspace without loading the model. The model is loaded the first time it embeds text.
+/// - `embedder(&self) -> Result<NotesEmbedder, LinknotesError>`: Asynchronously returns the embedder notes in the workspace are embedded with, loading the model if necessary.
+/// - `files(&self) -> Result<Vec<ContextualDocument>, LinknotesError>`: Asynchronously retrieves all contextual documents from the document table.
+/// - `current_files(&self) -> Result<Vec<ContextualDocument>, LinknotesError>`: Like `files`, but notes waiting in the indexing queue are returned with the text they were last saved with.
+/// - `document_table(&self) -> Result<&ContextualDocumentTable, LinknotesError>`: Asynchronously initializes and returns the document table, creating the database connection and table if necessary.
 /// - `get_tag_id(&self, tag: &str) -> u32`: Returns the ID of the specified tag, adding it to the tag list if it doesn't exist.
 /// - `get_tag_name(&self, id: u32) -> String`: Returns the name of the tag with the specified ID.
 /// - `tag_count(&self) -> usize`: Returns the number of tags in the workspace.
 /// - `retrain_classifier(&self)`: Retrains the document classifier by clearing the current classifier.
 /// - `classify(&self, document: &ContextualDocument) -> anyhow::Result<Tag>`: Asynchronously classifies the given document, initializing the classifier if necessary.
 impl Workspace {
-    fn new(location: PathBuf) -> Self {
+    fn new(
+        location: PathBuf,
+        generation: u64,
+        access: WorkspaceAccess,
+        embedder: Option<NotesEmbedder>,
+    ) -> Self {
         // Create a new workspace at the specified location
         Self {
             location,
+            generation,
             table: OnceLock::new(),
             lock: tokio::sync::Mutex::const_new(()),
             tags: RwLock::new(Vec::new()),
             classifier: RwLock::new(None),
+            watcher: parking_lot::Mutex::new(None),
+            indexing: Arc::new(IndexingQueue::new()),
+            embedder,
+            access,
+        }
+    }
+
+    pub fn is_read_only(&self) -> bool {
+        matches!(self.access, WorkspaceAccess::ReadOnly { .. })
+    }
+
+    pub(crate) fn check_writable(&self) -> Result<(), LinknotesError> {
+        // Commands that change the workspace call this first
+        if self.is_read_only() {
+            return Err(LinknotesError::WorkspaceReadOnly);
         }
+        Ok(())
     }
 
-    pub fn document_path(&self, title: &str) -> anyhow::Result<PathBuf> {
-        // Returns the file path for a document with the given title
+    pub(crate) fn notes_dir(&self) -> Result<PathBuf, LinknotesError> {
         let notes_dir = self.location.join("notes");
 
         // Create the notes directory if it doesn't exist
         if !notes_dir.exists() {
-            std::fs::create_dir_all(&notes_dir).unwrap();
+            std::fs::create_dir_all(&notes_dir)?;
         }
+        Ok(notes_dir)
+    }
+
+    pub(crate) fn set_watcher(&self, watcher: Option<NotesWatcher>) {
+        // Dropping the old watcher stops it
+        *self.watcher.lock() = watcher;
+    }
+
+    pub(crate) fn indexing(&self) -> Arc<IndexingQueue> {
+        // The queue is shared with the background worker so it can be used without holding the workspace
+        self.indexing.clone()
+    }
+
+    pub(crate) fn model_source(&self) -> Result<ModelSource, LinknotesError> {
+        // The manifest is read every time so a model chosen with set_embedding_model is used right away
+        match read_manifest(&self.location)?.and_then(|manifest| manifest.model_source) {
+            Some(source) => Ok(source),
+            None => ModelSource::from_env(),
+        }
+    }
 
-        // Construct the file path using the title
-        let file_name = format!("{}.txt", title);
-        let file_path = notes_dir.join(file_name);
-        Ok(file_path)
+    pub(crate) fn model_id(&self) -> Result<String, LinknotesError> {
+        match &self.embedder {
+            Some(embedder) => Ok(embedder.model_id()),
+            None => Ok(self.model_source()?.id()),
+        }
+    }
+
+    pub(crate) fn lazy_embedder(&self) -> Result<NotesEmbedder, LinknotesError> {
+        match &self.embedder {
+            Some(embedder) => Ok(embedder.clone()),
+            None => Ok(NotesEmbedder::lazy(self.model_source()?)),
+        }
     }
 
-    async fn files(&self) -> anyhow::Result<Vec<ContextualDocument>> {
+    pub(crate) async fn embedder(&self) -> Result<NotesEmbedder, LinknotesError> {
+        // Workspaces loaded without an embedder use the model in their manifest. Workspaces with the same model share it
+        match &self.embedder {
+            Some(embedder) => Ok(embedder.clone()),
+            None => embedder(&self.model_source()?)
+                .await
+                .map_err(LinknotesError::embedding_model),
+        }
+    }
+
+    pub(crate) async fn files(&self) -> Result<Vec<ContextualDocument>, LinknotesError> {
         // Asynchronously retrieves all contextual documents from the document table
         #[derive(Serialize, Deserialize)]
         struct FilePath {
@@ -124,12 +436,37 @@ impl Workspace {
         Ok(paths)
     }
 
-    pub async fn document_table(&self) -> anyhow::Result<&ContextualDocumentTable> {
+    pub(crate) async fn current_files(&self) -> Result<Vec<ContextualDocument>, LinknotesError> {
+        // Notes that are waiting to be indexed replace their indexed version, or are added if they were never indexed
+        let mut files = self.files().await?;
+        for (note_id, job) in self.indexing.queued_jobs() {
+            match files.iter().position(|file| file.note_id == note_id) {
+                Some(index) => {
+                    let indexed = files.remove(index);
+                    files.insert(
+                        index,
+                        queued_document(&self.location, note_id, job, Some(indexed)),
+                    );
+                }
+                None => files.push(queued_document(&self.location, note_id, job, None)),
+            }
+        }
+        Ok(files)
+    }
+
+    pub async fn document_table(&self) -> Result<&ContextualDocumentTable, LinknotesError> {
         // Asynchronously initializes and returns the document table
         let _guard = self.lock.lock().await;
         if self.table.get().is_none() {
             let init = || async {
-                let root = PathBuf::from(&self.location);
+                let root = match &self.access {
+                    WorkspaceAccess::Writable(_) => PathBuf::from(&self.location),
+                    WorkspaceAccess::ReadOnly { snapshot } => {
+                        // Copy the database so we don't open the files another process is writing to
+                        copy_databases(&self.location, snapshot)?;
+                        snapshot.clone()
+                    }
+                };
                 if !root.exists() {
                     std::fs::create_dir_all(&root)?;
                 }
@@ -143,21 +480,44 @@ impl Workspace {
                 let document_table = db
                     .document_table_builder("documents")
                     .at(root.join("documents"))
-                    .with_embedding_model(bert().await?.clone())
+                    // Notes are embedded before they are inserted, so the table only loads the model if it embeds text itself
+                    .with_embedding_model(self.lazy_embedder()?)
                     .with_chunker(DefaultSentenceChunker)
                     .build::<ContextualDocument>()
-                    .await?;
+                    .await
+                    .map_err(LinknotesError::storage)?;
+
+                // Upgrade the notes of workspaces with an older schema version
+                migrate_workspace(&document_table, self).await?;
 
-                anyhow::Ok(document_table)
+                // Index the words of every note for lexical search
+                define_full_text_index(&document_table).await?;
+                // Index the file names of the notes so colliding file names are found quickly
+                define_location_index(document_table.table().db()).await?;
+
+                // Remember which model the workspace is indexed with so a different model is never searched
+                if !self.is_read_only() {
+                    record_embedding_model(&self.location, &self.lazy_embedder()?, &document_table)
+                        .await?;
+                }
+
+                Ok::<_, LinknotesError>(document_table)
             };
 
-            _ = self.table.set(init().await);
+            match init().await {
+                // The copy of a read-only workspace may succeed the next time, so the error isn't kept
+                Err(err @ LinknotesError::SnapshotFailed(_)) => return Err(err),
+                result => _ = self.table.set(result),
+            }
+        }
+        match self.table.get() {
+            Some(Ok(table)) => Ok(table),
+            // Return the error from the first time the table was initialized
+            Some(Err(err)) => Err(err.clone()),
+            None => Err(LinknotesError::storage(
+                "the document table is not initialized",
+            )),
         }
-        self.table.get().unwrap().as_ref().map_err(|err| {
-            // Return an error if the document table is not initialized
-            let err = err.to_string();
-            anyhow::anyhow!(err)
-        })
     }
 
     pub fn get_tag_id(&self, tag: &str) -> u32 {
@@ -206,12 +566,17 @@ impl Workspace {
     }
 }
 
-// An in memory workspace. This is an id to a global table of notes.
+// An in memory workspace. This is an id to a global table of notes. The slot in the table is reused after the
+// workspace is unloaded, so the id also stores the generation of the workspace that was loaded into the slot.
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)] // Represents a workspace ID
 pub struct WorkspaceId {
     id: usize,
+    generation: u64,
 }
 
+/// The generation of the next workspace that is loaded. Generations start at 1 so a default id never points at a workspace.
+static NEXT_GENERATION: AtomicU64 = AtomicU64::new(1);
+
 /// This is the in memory list of open workspaces loaded by the frontend. Because we are moving between JS and Rust,
 /// we need to load and unload the workspaces manually.
 static OPEN_WORKSPACES: OnceLock<RwLock<Slab<Workspace>>> = OnceLock::new();
@@ -222,71 +587,389 @@ fn open_workspaces() -> &'static RwLock<Slab<Workspace>> {
     OPEN_WORKSPACES.get_or_init(|| RwLock::new(Slab::new()))
 }
 
-/// Get a reference to a workspace by the id
-pub fn get_workspace_ref(id: WorkspaceId) -> MappedRwLockReadGuard<'static, Workspace> {
+/// Get a reference to a workspace by the id. Returns `LinknotesError::WorkspaceNotLoaded` if the workspace was
+/// never loaded or has been unloaded.
+pub fn get_workspace_ref(
+    id: WorkspaceId,
+) -> Result<MappedRwLockReadGuard<'static, Workspace>, LinknotesError> {
     // Get a reference to a workspace by the ID
     tracing::info!("get_workspace_ref called with id: {:?}", id);
-    RwLockReadGuard::map(open_workspaces().read(), |slab| slab.get(id.id).unwrap())
+    RwLockReadGuard::try_map(open_workspaces().read(), |slab| {
+        slab.get(id.id)
+            .filter(|workspace| workspace.generation == id.generation)
+    })
+    .map_err(|_| LinknotesError::WorkspaceNotLoaded)
 }
 
-/// Load a workspace at a path into memory. This will either load the existing workspace from the filesystem or create a new workspace at the path.
-#[tauri::command]
-pub fn load_workspace(path: PathBuf) -> WorkspaceId {
-    // Load a workspace at a path into memory
-    tracing::info!("Loading workspace at {:?}", path);
+/// Delete the database of a workspace if it failed to open, so the next call to `document_table` creates an empty
+/// database. The notes directory is kept. A database that opened is left alone because it can't be deleted while it is
+/// open.
+///
+/// Returns `true` if the database was deleted.
+pub(crate) fn reset_broken_database(id: WorkspaceId) -> Result<bool, LinknotesError> {
     let mut workspaces = open_workspaces().write();
-    let new_workspace = Workspace::new(path);
-    let id = workspaces.insert(new_workspace);
-    tracing::info!("Workspace loaded with id: {:?}", id);
-    WorkspaceId { id }
+    let workspace = workspaces
+        .get_mut(id.id)
+        .filter(|workspace| workspace.generation == id.generation)
+        .ok_or(LinknotesError::WorkspaceNotLoaded)?;
+    workspace.check_writable()?;
+    if !matches!(workspace.table.get(), Some(Err(_))) {
+        return Ok(false);
+    }
+    tracing::warn!("Deleting the broken database of {:?}", workspace.location);
+    workspace.table = OnceLock::new();
+    for database in ["notes.db", "documents"] {
+        let path = workspace.location.join(database);
+        if path.exists() {
+            std::fs::remove_dir_all(&path)?;
+        }
+    }
+    Ok(true)
 }
 
-#[tauri::command]
-pub fn get_workspace_id(path: PathBuf) -> WorkspaceId {
-    // Get the ID of a workspace at a path
-    tracing::info!("get_workspace_id called with path: {:?}", path);
-    let workspaces = open_workspaces().read();
+/// Check if two paths point to the same workspace directory. Paths that exist are compared after canonicalizing them
+/// so `./workspace` and `/home/user/workspace` are the same workspace.
+fn same_workspace_path(a: &Path, b: &Path) -> bool {
+    match (std::fs::canonicalize(a), std::fs::canonicalize(b)) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => a == b,
+    }
+}
+
+/// Find the workspace at a path if it is already open, or open a new workspace at the path. The write lock is held
+/// for the whole lookup so two calls can't both open the same directory. A workspace that is already open read-only
+/// can't be opened with write access, so that returns `LinknotesError::WorkspaceReadOnly`.
+fn open_workspace(
+    path: PathBuf,
+    read_only: bool,
+    embedder: Option<NotesEmbedder>,
+) -> Result<WorkspaceId, LinknotesError> {
+    let mut workspaces = open_workspaces().write();
 
     // Check if the workspace already exists
     for (id, workspace) in workspaces.iter() {
-        if workspace.location == path {
+        if same_workspace_path(&workspace.location, &path) {
             tracing::info!("Workspace found with id: {:?}", id);
-            return WorkspaceId { id };
+            // The workspace has to be unloaded before it can be loaded with write access
+            if workspace.is_read_only() && !read_only {
+                return Err(LinknotesError::WorkspaceReadOnly);
+            }
+            return Ok(WorkspaceId {
+                id,
+                generation: workspace.generation,
+            });
         }
     }
 
     // If not found, create a new workspace
-    drop(workspaces); // Drop the read lock before acquiring a write lock
-    let mut workspaces = open_workspaces().write();
-    let new_workspace = Workspace::new(path.clone());
-    let id = workspaces.insert(new_workspace);
+    let generation = NEXT_GENERATION.fetch_add(1, Ordering::Relaxed);
+    let access = if read_only {
+        let snapshot = std::env::temp_dir().join(format!(
+            "linknotes-read-only-{}-{}",
+            std::process::id(),
+            generation
+        ));
+        // Refuse workspaces from a newer version of the app before reading anything else
+        open_manifest(&path, true)?;
+        WorkspaceAccess::ReadOnly { snapshot }
+    } else {
+        // Make sure no other process has the database open
+        let lock = WorkspaceLock::acquire(&path)?;
+        // Create the manifest. The notes are upgraded to the current schema version when they are first opened
+        open_manifest(&path, false)?;
+        WorkspaceAccess::Writable(lock)
+    };
+    let id = workspaces.insert(Workspace::new(path, generation, access, embedder));
     tracing::info!("New workspace created with id: {:?}", id);
-    WorkspaceId { id }
+    let id = WorkspaceId { id, generation };
+    drop(workspaces);
+
+    // Index the notes that were still waiting when the workspace was last unloaded
+    if !read_only {
+        if let Err(err) = requeue_pending_jobs(id) {
+            tracing::error!("Failed to queue the pending notes of {:?}: {}", id, err);
+        }
+    }
+    Ok(id)
+}
+
+/// Load a workspace at a path into memory. This will either load the existing workspace from the filesystem or create a new workspace at the path.
+/// If the workspace at the path is already loaded, the id of the loaded workspace is returned so there is only ever
+/// one database open for each workspace.
+///
+/// Returns `LinknotesError::WorkspaceLocked` if another process has the workspace open. The workspace can still be
+/// opened with [`load_workspace_read_only`] in that case. Returns `LinknotesError::WorkspaceReadOnly` if this process
+/// already has the workspace open read-only.
+#[tauri::command]
+pub fn load_workspace(path: PathBuf) -> Result<WorkspaceId, LinknotesError> {
+    // Load a workspace at a path into memory
+    tracing::info!("Loading workspace at {:?}", path);
+    load_workspace_with(path, None)
+}
+
+/// Load a workspace like [`load_workspace`] that embeds its notes with `embedder`. `None` uses the embedder configured
+/// for the app. Tests pass the hashing embedder so they never download BERT. If the workspace is already loaded, it
+/// keeps the embedder it was loaded with.
+pub(crate) fn load_workspace_with(
+    path: PathBuf,
+    embedder: Option<NotesEmbedder>,
+) -> Result<WorkspaceId, LinknotesError> {
+    open_workspace(path, false, embedder)
+}
+
+/// Load a workspace without taking the lock on it. The workspace reads from a snapshot of the database taken when it is
+/// first used, and every command that would change the workspace returns `LinknotesError::WorkspaceReadOnly`. Commands
+/// return `LinknotesError::SnapshotFailed` if the snapshot can't be copied, for example because the other process kept
+/// writing to the database while it was copied. The next command that reads the workspace tries to copy it again.
+#[tauri::command]
+pub fn load_workspace_read_only(path: PathBuf) -> Result<WorkspaceId, LinknotesError> {
+    tracing::info!("Loading workspace at {:?} in read-only mode", path);
+    open_workspace(path, true, None)
+}
+
+/// Get the id of the workspace at a path. This only looks up workspaces that are already loaded. Use [`load_workspace`]
+/// to load a workspace.
+///
+/// Returns `LinknotesError::WorkspaceNotLoaded` if no workspace at the path is loaded.
+#[tauri::command]
+pub fn get_workspace_id(path: PathBuf) -> Result<WorkspaceId, LinknotesError> {
+    tracing::info!("get_workspace_id called with path: {:?}", path);
+    open_workspaces()
+        .read()
+        .iter()
+        .find(|(_, workspace)| same_workspace_path(&workspace.location, &path))
+        .map(|(id, workspace)| WorkspaceId {
+            id,
+            generation: workspace.generation,
+        })
+        .ok_or(LinknotesError::WorkspaceNotLoaded)
+}
+
+/// Check if a workspace was loaded in read-only mode.
+#[tauri::command]
+pub fn is_workspace_read_only(workspace_id: WorkspaceId) -> Result<bool, LinknotesError> {
+    Ok(get_workspace_ref(workspace_id)?.is_read_only())
 }
 
 // Unload a workspace from memory. This should be called whenever the workspace is closed.
 #[tauri::command]
-pub fn unload_workspace(id: WorkspaceId) {
+pub fn unload_workspace(id: WorkspaceId) -> Result<(), LinknotesError> {
     tracing::info!("unload_workspace called with id: {:?}", id);
     let mut workspaces = open_workspaces().write();
-    workspaces.remove(id.id);
+    // Only remove the workspace if the id is for the workspace that is currently in the slot
+    match workspaces.get(id.id) {
+        Some(workspace) if workspace.generation == id.generation => {
+            // Notes that are still waiting to be indexed are saved so they are indexed the next time the workspace is
+            // loaded. Their files are already written
+            let pending = workspace.indexing.cancel();
+            if let Err(err) = save_pending_jobs(&workspace.location, &pending) {
+                tracing::error!("Failed to save the pending notes of {:?}: {}", id, err);
+            }
+            workspaces.remove(id.id);
+        }
+        _ => return Err(LinknotesError::WorkspaceNotLoaded),
+    }
     tracing::info!("Workspace unloaded with id: {:?}", id);
+    Ok(())
 }
 
 /// Permanently delete a workspace from the filesystem.
 #[tauri::command]
-pub fn delete_workspace(id: WorkspaceId) {
+pub fn delete_workspace(id: WorkspaceId) -> Result<(), LinknotesError> {
     tracing::info!("delete_workspace called with id: {:?}", id);
-    let workspace = get_workspace_ref(id);
+    let workspace = get_workspace_ref(id)?;
+    workspace.check_writable()?;
+    // The pending notes are deleted with the workspace
+    workspace.indexing.cancel();
     let path = workspace.location.clone();
     _ = std::fs::remove_dir_all(path);
     tracing::info!("Workspace deleted with id: {:?}", id);
+    Ok(())
 }
 
 /// Remove a note from a specific path. The path should be canonicalized so it is consistent regardless of the working directory.
 #[tauri::command]
-pub async fn files_in_workspace(workspace_id: WorkspaceId) -> Vec<ContextualDocument> {
+pub async fn files_in_workspace(
+    workspace_id: WorkspaceId,
+) -> Result<Vec<ContextualDocument>, LinknotesError> {
     tracing::info!("files_in_workspace called with id: {:?}", workspace_id);
-    let workspace = get_workspace_ref(workspace_id);
-    workspace.files().await.unwrap()
+    let workspace = get_workspace_ref(workspace_id)?;
+    workspace.current_files().await
+}
+
+#[test]
+fn test_note_file_names() {
+    let titles = [
+        "Integrals",
+        "a/b\\c",
+        "../notes",
+        "100% done?",
+        "C: <drive> \"quoted\" | piped *",
+        "trailing dot.",
+        "trailing space ",
+        "con",
+        "LPT1.backup",
+        "日本語のノート",
+        "tab\tand\nnewline",
+    ];
+    for title in titles {
+        let file_name = title_to_file_name(title).unwrap();
+        let stem = file_name.strip_suffix(NOTE_EXTENSION).unwrap();
+        assert!(!stem.contains(['/', '\\', ':', '*', '?', '"', '<', '>', '|']));
+        assert!(!stem.chars().any(|c| c.is_control()));
+        assert!(!stem.starts_with('.'));
+        assert!(!stem.ends_with(['.', ' ']));
+        assert_eq!(file_name_to_title(&file_name).as_deref(), Some(title));
+    }
+
+    assert_eq!(title_to_file_name("a/b").unwrap(), "a%2Fb.txt");
+    assert_eq!(title_to_file_name("..").unwrap(), "%2E%2E.txt");
+    assert_eq!(title_to_file_name("nul").unwrap(), "%6Eul.txt");
+    assert_eq!(title_to_file_name("com0").unwrap(), "%63om0.txt");
+    assert_eq!(title_to_file_name("LPT³.txt").unwrap(), "%4CPT³.txt.txt");
+    assert_eq!(title_to_file_name(""), Err(NoteTitleError::Empty));
+    assert_eq!(
+        title_to_file_name(&"a".repeat(300)),
+        Err(NoteTitleError::TooLong)
+    );
+    assert_eq!(file_name_to_title("notes.md"), None);
+    assert_eq!(file_name_to_title("bad%2.txt"), None);
+
+    // Notes that share a title are stored in files with the note id after the title
+    let id = NoteId::new();
+    let file_name = title_to_unique_file_name("100% done?", &id).unwrap();
+    assert_eq!(file_name, format!("100%25 done%3F%~{id}.txt"));
+    assert_ne!(file_name, title_to_file_name("100% done?").unwrap());
+    assert_eq!(
+        file_name_to_title(&file_name).as_deref(),
+        Some("100% done?")
+    );
+    assert_eq!(
+        title_to_unique_file_name(&"a".repeat(240), &id),
+        Err(NoteTitleError::TooLong)
+    );
+}
+
+#[test]
+fn test_workspace_ids() {
+    let temp = std::env::temp_dir();
+    let first_path = temp.join("testing-workspace-ids-first");
+    let second_path = temp.join("testing-workspace-ids-second");
+
+    // Loading the same path twice returns the same workspace
+    let first = load_workspace(first_path.clone()).unwrap();
+    assert_eq!(load_workspace(first_path.clone()).unwrap(), first);
+    assert_eq!(get_workspace_id(first_path.clone()).unwrap(), first);
+    assert_eq!(get_workspace_ref(first).unwrap().location, first_path);
+
+    // A stale id doesn't point at the workspace that reuses the slot
+    unload_workspace(first).unwrap();
+    assert_eq!(
+        get_workspace_id(first_path.clone()),
+        Err(LinknotesError::WorkspaceNotLoaded)
+    );
+    assert_eq!(
+        get_workspace_ref(first).err(),
+        Some(LinknotesError::WorkspaceNotLoaded)
+    );
+    let second = load_workspace(second_path.clone()).unwrap();
+    assert_ne!(second, first);
+    assert_eq!(
+        get_workspace_ref(first).err(),
+        Some(LinknotesError::WorkspaceNotLoaded)
+    );
+    assert_eq!(
+        unload_workspace(first),
+        Err(LinknotesError::WorkspaceNotLoaded)
+    );
+    assert_eq!(get_workspace_ref(second).unwrap().location, second_path);
+
+    // Ids that were never loaded are rejected instead of panicking
+    let never_loaded = WorkspaceId {
+        id: usize::MAX,
+        generation: 0,
+    };
+    assert!(get_workspace_ref(never_loaded).is_err());
+
+    unload_workspace(second).unwrap();
+}
+
+#[tokio::test]
+async fn test_read_only_workspace() {
+    use crate::lock::LockFile;
+    use crate::note::save_note;
+
+    let workspace_path = std::env::temp_dir().join("testing-read-only-workspace");
+    _ = std::fs::remove_dir_all(&workspace_path);
+    std::fs::create_dir_all(&workspace_path).unwrap();
+
+    // Pretend another process has the workspace open
+    let other_process = LockFile {
+        pid: std::process::id().wrapping_add(1),
+        heartbeat: std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_millis() as u64,
+    };
+    other_process.write(&workspace_path).unwrap();
+    assert_eq!(
+        load_workspace(workspace_path.clone()),
+        Err(LinknotesError::WorkspaceLocked {
+            pid: Some(other_process.pid)
+        })
+    );
+
+    // The workspace can still be read, but not changed
+    let workspace = load_workspace_read_only(workspace_path.clone()).unwrap();
+    assert!(is_workspace_read_only(workspace).unwrap());
+    // Loading it again with write access doesn't return the read-only workspace
+    std::fs::remove_file(workspace_path.join(crate::lock::LOCK_FILE_NAME)).unwrap();
+    assert_eq!(
+        load_workspace(workspace_path.clone()),
+        Err(LinknotesError::WorkspaceReadOnly)
+    );
+    other_process.write(&workspace_path).unwrap();
+    assert_eq!(
+        save_note(None, "Note".to_string(), "Text".to_string(), workspace).await,
+        Err(LinknotesError::WorkspaceReadOnly)
+    );
+    assert_eq!(
+        delete_workspace(workspace),
+        Err(LinknotesError::WorkspaceReadOnly)
+    );
+    unload_workspace(workspace).unwrap();
+
+    // The lock of the other process is left alone
+    assert_eq!(LockFile::read(&workspace_path), Some(other_process));
+    _ = std::fs::remove_dir_all(&workspace_path);
+}
+
+#[tokio::test]
+async fn test_read_only_snapshot_failed() {
+    let workspace_path = std::env::temp_dir().join("testing-read-only-snapshot-workspace");
+    _ = std::fs::remove_dir_all(&workspace_path);
+    std::fs::create_dir_all(&workspace_path).unwrap();
+
+    // A database that can't be copied returns a typed error instead of opening a partial copy
+    std::fs::write(workspace_path.join("notes.db"), "not a directory").unwrap();
+    let workspace =
+        open_workspace(workspace_path.clone(), true, Some(NotesEmbedder::hashing())).unwrap();
+    assert!(matches!(
+        get_workspace_ref(workspace)
+            .unwrap()
+            .document_table()
+            .await
+            .err(),
+        Some(LinknotesError::SnapshotFailed(_))
+    ));
+
+    // The failed copy isn't remembered, so the next command copies the database again
+    std::fs::remove_file(workspace_path.join("notes.db")).unwrap();
+    assert!(get_workspace_ref(workspace)
+        .unwrap()
+        .document_table()
+        .await
+        .is_ok());
+    unload_workspace(workspace).unwrap();
+    _ = std::fs::remove_dir_all(&workspace_path);
 }
@@ -43,7 +43,7 @@ import { toast } from 'react-toastify';
 import 'react-toastify/dist/ReactToastify.css';
 
 // Define the WorkspaceId type if it's not already defined
-type WorkspaceId = number; // Adjust this based on the actual type
+type WorkspaceId = { id: number, generation: number }; // The slot and generation of a loaded workspace
 
 /* The `interface SearchProps` is defining the props that the `Search` component expects to receive.
 Here's a breakdown of each prop: */
@@ -53,13 +53,15 @@ interface SearchProps {
   searchQuery: string;              // Current search input value
   setSearchQuery: (query: string) => void; // Setter for updating search query
   workspace_id: WorkspaceId;        // Identifier for the workspace
-  handleNoteSelect: (title: string) => void; // Callback when a search result is selected
+  handleNoteSelect: (id: string) => void; // Callback with the id of the note when a search result is selected
 }
 
 /* The `interface SearchResult` is defining the structure of an object that represents a search result.
 Here's a breakdown of each property within the `SearchResult` interface: */
 interface SearchResult {
-  distance: number;               // Relevance distance of the search result
+  distance: number | null;        // Embedding distance of the search result, or null for full-text matches
+  score: number;                  // Relevance of the search result. Higher is better
+  id: string;                     // Stable id of the note. Several notes can have the same title
   title: string;                  // Title of the result
   character_range: [number, number]; // Range of matching characters
 }
@@ -155,7 +157,9 @@ const Search: React.FC<SearchProps> = ({ onTagClick, selectedTags, searchQuery,
       setSearchResults(uniqueResults);
     } catch (error) {
       console.error('Failed to perform search:', error);
-      toast.error(`Failed to perform search ${error}`);
+      // Commands fail with a { kind, message } object
+      const message = (error as { message?: string })?.message ?? error;
+      toast.error(`Failed to perform search ${message}`);
     }
   };
 
@@ -208,7 +212,7 @@ const Search: React.FC<SearchProps> = ({ onTagClick, selectedTags, searchQuery,
           </div>
           <div className="search-results">
             {searchResults.map((result, index) => (
-              <div key={index} className="search-result" onClick={() => handleNoteSelect(result.title)}>
+              <div key={index} className="search-result" onClick={() => handleNoteSelect(result.id)}>
                 {result.title}
               </div>
             ))}
@@ -51,7 +51,7 @@ as it utilizes React components and hooks extensively. Additionally,
 the Slate.js library is required for rich text editing capabilities, 
 meaning that the necessary dependencies must be installed and configured correctly. 
 The code also relies on Tauri for backend interactions, so a proper Tauri setup is essential to 
-invoke functions like invoke('get_workspace_id'). Other preconditions include the presence of a 
+invoke functions like invoke('load_workspace'). Other preconditions include the presence of a 
 valid workspace ID to manage notes effectively and the initial state setup for various variables 
 such as savedNotes, title, and tags. Furthermore, user permissions are necessary for file handling 
 when importing notes, and the saved notes must conform to a specific structure to ensure they render 
@@ -164,7 +164,7 @@ with the text editor.
 
 
 *Error and exception condition values or types that can occur, and their meanings
-Failed to Load Workspace: If the invoke('get_workspace_id') call fails, an error is logged, 
+Failed to Load Workspace: If the invoke('load_workspace') call fails, an error is logged, 
 and a toast notification displays "Failed to load workspace," indicating 
 that the workspace ID could not be retrieved.
 
@@ -223,6 +223,7 @@ import { createEditor, Descendant, Transforms, BaseEditor, Node } from 'slate';
 import { Slate, Editable, withReact, ReactEditor, RenderElementProps } from 'slate-react';
 import { HistoryEditor, withHistory } from 'slate-history';
 import { invoke } from "@tauri-apps/api/core";
+import { listen } from "@tauri-apps/api/event";
 import { ToastContainer, toast } from 'react-toastify';
 import 'react-toastify/dist/ReactToastify.css';
 import Search from './Search';
@@ -255,7 +256,8 @@ declare module 'slate' {
 }
 
 // Define the WorkspaceId type if it's not already defined
-type WorkspaceId = number; // Adjust this based on the actual type
+type WorkspaceId = { id: number, generation: number }; // The slot and generation of a loaded workspace
+type NoteId = string; // The stable id of a note. Notes can share a title, so they are always looked up by id
 
 // Constants
 const INITIAL_VALUE: Descendant[] = [ // Update the initial value to include a heading element
@@ -271,8 +273,9 @@ const TextEditor: React.FC = () => { // Update the component to use the CustomTy
   const [title, setTitle] = useState<string>(''); // Add state for note title
   const [searchQuery, setSearchQuery] = useState<string>(''); // Add state for search query
   const [selectedTags, setSelectedTags] = useState<string[]>([]); // Add state for selected tags
-  const [savedNotes, setSavedNotes] = useState<{ title: string, content: string, tags: { name: string, manual: boolean }[] }[]>([]); // Add state for saved notes
-  const [workspaceId, setWorkspaceId] = useState<WorkspaceId>(0); // Use WorkspaceId type here
+  const [currentNoteId, setCurrentNoteId] = useState<NoteId | null>(null); // The id of the open note, or null if it was never saved
+  const [savedNotes, setSavedNotes] = useState<{ id: NoteId, title: string, content: string, tags: { name: string, manual: boolean }[] }[]>([]); // Add state for saved notes
+  const [workspaceId, setWorkspaceId] = useState<WorkspaceId>({ id: 0, generation: 0 }); // Generation 0 is never loaded, so this is invalid until the workspace loads
   const [tags, setTags] = useState<{ name: string, manual: boolean }[]>([]); // Add state for tags
   const [selectedNoteId, setSelectedNoteId] = useState<number | null>(null); // Add state for selected note ID
   const [showConfirmation, setShowConfirmation] = useState<boolean>(false); // Add state for confirmation dialog
@@ -322,12 +325,41 @@ const TextEditor: React.FC = () => { // Update the component to use the CustomTy
     document.body.className = darkMode ? 'dark-mode' : 'light-mode'; // Update the body class based on dark mode
   }, [darkMode]); // Update the dependency
 
+  useEffect(() => {
+    // Reload the notes when they are edited outside of the app
+    const unlisten = listen('notes-changed', () => loadSavedNotes(workspaceId));
+    return () => {
+      unlisten.then((unlisten) => unlisten());
+    };
+  }, [workspaceId]);
+
+  useEffect(() => {
+    // Tell the user if the embedding model can't be loaded. Saving and searching won't work without it
+    const unlisten = listen('model-loading', (event) => {
+      const status = event.payload as { state: string, message?: string };
+      if (status.state === 'Failed') toast.error(`Failed to load the embedding model: ${status.message}`);
+    });
+    return () => {
+      unlisten.then((unlisten) => unlisten());
+    };
+  }, []);
+
   // Helper functions
   const loadWorkspace = async () => {
     try {
-      const id = await invoke('get_workspace_id', { path: "./testing-workspace" }) as WorkspaceId; // Use WorkspaceId type here
+      // Start loading the embedding model now so the first save doesn't wait for the download
+      invoke('warm_up').catch((error) => console.error('Failed to warm up the embedding model:', error));
+      const path = "./testing-workspace";
+      const id = await invoke('load_workspace', { path }).catch(async (error) => {
+        // Another instance of the app has the workspace open. Open it without making changes instead
+        if (error?.kind !== 'WorkspaceLocked') throw error;
+        toast.error('The workspace is open in another window. Changes will not be saved');
+        return await invoke('load_workspace_read_only', { path });
+      }) as WorkspaceId; // Use WorkspaceId type here
       setWorkspaceId(id); // Set the workspace ID
       loadSavedNotes(id); // Load the saved notes for the workspace
+      // Pick up notes edited outside of the app. Read-only workspaces can't be watched
+      invoke('watch_workspace', { workspaceId: id }).catch((error) => console.error('Failed to watch workspace:', error));
     } catch (error) {
       console.error('Failed to load workspace:', error); // Log the error
       toast.error('Failed to load workspace'); // Show a toast notification
@@ -336,9 +368,9 @@ const TextEditor: React.FC = () => { // Update the component to use the CustomTy
 
   const loadSavedNotes = async (workspaceId: WorkspaceId) => { // Use WorkspaceId type here
     try {
-      const files = await invoke('files_in_workspace', { workspaceId }) as { document: {title: string, body: string}, tags: { name: string, manual: boolean }[] }[]; // Use WorkspaceId type here
+      const files = await invoke('files_in_workspace', { workspaceId }) as { note_id: NoteId, document: {title: string, body: string}, tags: { name: string, manual: boolean }[] }[]; // Use WorkspaceId type here
       const notes = files.map((doc) => {
-        return { title: doc.document.title, content: doc.document.body, tags: doc.tags }; // Update the note structure
+        return { id: doc.note_id, title: doc.document.title, content: doc.document.body, tags: doc.tags }; // Update the note structure
       });
       setSavedNotes(notes); // Set the saved notes
       // set cursor to 0
@@ -361,9 +393,9 @@ const TextEditor: React.FC = () => { // Update the component to use the CustomTy
     }
   };
 
-  const loadTags = async (noteTitle: string) => {
+  const loadTags = async (noteId: NoteId) => {
     try {
-      const tags = await invoke('get_tags', { title: noteTitle, workspaceId }) as { name: string, manual: boolean }[]; // Use WorkspaceId type here
+      const tags = await invoke('get_tags', { id: noteId, workspaceId }) as { name: string, manual: boolean }[]; // Use WorkspaceId type here
       setTags(tags); // Set the tags
     } catch (error) {
       console.error('Failed to load tags:', error); // Log the error
@@ -373,6 +405,7 @@ const TextEditor: React.FC = () => { // Update the component to use the CustomTy
 
   // Event handlers
   const handleNewNote = () => {
+    setCurrentNoteId(null); // The next save creates a new note
     setTitle('');
     setValue(INITIAL_VALUE); // Reset the content
     setTags([]); // Clear the tags
@@ -404,42 +437,45 @@ const TextEditor: React.FC = () => { // Update the component to use the CustomTy
       });
       extractedText = extractedText.trim(); // Trim the extracted text
   
-      await invoke('save_note', {   // Call the save_note function
+      const id = await invoke('save_note', {   // Call the save_note function
+        id: currentNoteId, // Pass the id of the open note, or null to create a new note
         title: note.title,  // Pass the note title
         text: extractedText,    // Pass the extracted text
         workspaceId, // Pass the workspace ID
-      });
+      }) as NoteId;
+      setCurrentNoteId(id); // Keep the id so the next save updates this note
   
       setSavedNotes(prevNotes => {
-        const index = prevNotes.findIndex(n => n.title === title); // Find the index of the note
+        const index = prevNotes.findIndex(n => n.id === id); // Find the index of the note
         if (index !== -1) {
           // Update existing note
           const updatedNotes = [...prevNotes]; // Create a copy of the notes
-          updatedNotes[index] = { title: note.title, content: extractedText, tags: note.tags }; // Update the note
+          updatedNotes[index] = { id, title: note.title, content: extractedText, tags: note.tags }; // Update the note
           return updatedNotes; // Return the updated notes
         } else { // If the note doesn't exist
           // Add new note
-          return [...prevNotes, { title: note.title, content: extractedText, tags: note.tags }]; // Add the new note
+          return [...prevNotes, { id, title: note.title, content: extractedText, tags: note.tags }]; // Add the new note
         }
       });
 
-      await loadTags(note.title); // Load the tags for the note
+      await loadTags(id); // Load the tags for the note
    
       toast.success(`Note saved successfully`); // Show a success toast
     } catch (error) {
       console.error('Failed to save note:', error);
       toast.error(`Failed to save note ${JSON.stringify(error)}`); // Show an error toast
     }
-  }, [title, value, workspaceId]); // Update the dependencies
+  }, [currentNoteId, title, value, workspaceId]); // Update the dependencies
 
-  const handleNoteSelect = useCallback(async (title: string) => {
+  const handleNoteSelect = useCallback(async (noteId: NoteId) => {
     try {
-      console.log("Attempting to read file:", title); // Log the file read action
-      const content = await invoke('read_note', { title, workspaceId }) as { document: {title: string, body: string}, tags: { name: string, manual: boolean }[] }; // Use WorkspaceId type here
+      console.log("Attempting to read note:", noteId); // Log the file read action
+      const content = await invoke('read_note', { id: noteId, workspaceId }) as { document: {title: string, body: string}, tags: { name: string, manual: boolean }[] }; // Use WorkspaceId type here
       console.log("Received content from backend:", content); // Log the content
 
-      // Set the title
-      setTitle(title); // Set the title
+      // Set the title and remember which note is open
+      setTitle(content.document.title); // Set the title
+      setCurrentNoteId(noteId);
 
       // Create a new Slate-compatible value
       const newValue: Descendant[] = content.document.body.split('\n').map(line => { // Split the content by lines
@@ -460,14 +496,14 @@ const TextEditor: React.FC = () => { // Update the component to use the CustomTy
       editor.children = newValue;
       Transforms.select(editor, { path: [0, 0], offset: 0 }); // Select the first block
 
-      await loadTags(title);
+      await loadTags(noteId);
 
       // toast.success(`Loaded note: ${title}`);
     } catch (error) {
       console.error('Failed to load note:', error); // Log the error
       toast.error(`Failed to load note: ${error}`); // Show an error toast
     }
-  }, [editor, setTitle, setValue, workspaceId]);
+  }, [editor, setTitle, setValue, setCurrentNoteId, workspaceId]);
 
   const handleKeyDown = (event: React.KeyboardEvent<HTMLDivElement>) => { // Add the event parameter
     if (event.metaKey && event.key === 'z') {
@@ -501,13 +537,17 @@ const TextEditor: React.FC = () => { // Update the component to use the CustomTy
   };
 
   const handleAddTag = async (newTag: string) => { // Add the newTag parameter
+    if (!currentNoteId) {
+      toast.error('Save the note before adding tags'); // Notes only have an id after they are saved
+      return;
+    }
     try {
       await invoke('set_tags', {
-        title,
+        id: currentNoteId,
         tags: [...tags, { name: newTag, manual: true }], // Add the new tag
         workspaceId,
       });
-      await loadTags(title); // Reload the tags
+      await loadTags(currentNoteId); // Reload the tags
     } catch (error) {
       console.error('Failed to add tag:', error); // Log the error
       toast.error('Failed to add tag'); // Show an error toast
@@ -525,13 +565,14 @@ const TextEditor: React.FC = () => { // Update the component to use the CustomTy
       console.log('Deleting note with title:', savedNotes[noteId]?.title, 'Workspace ID:', workspaceId);
   
       // Call the Tauri backend
-      const result = await invoke('remove_note', { title: savedNotes[noteId].title, workspaceId }); // Use WorkspaceId type here
+      const result = await invoke('remove_note', { id: savedNotes[noteId].id, workspaceId }); // Use WorkspaceId type here
       console.log('Backend response:', result);
   
       // Update the state
       setSavedNotes(prevNotes => {
         const updatedNotes = prevNotes.filter((_, index) => index !== noteId); // Filter out the note to delete
         console.log('Updated notes:', updatedNotes); 
+        setCurrentNoteId(null); // The editor no longer shows a saved note
         setTitle(''); // Clear the title
         setTags([]);
         setValue(INITIAL_VALUE); // Clear the content
@@ -617,21 +658,19 @@ const TextEditor: React.FC = () => { // Update the component to use the CustomTy
   const getContextResult = async (cursor_utf16_index: number, document_text: string) => { 
   try {
     // Call the backend function and pass cursor position and text content
-    let documentTitle = null;
+    // The note may not be saved yet
+    const documentId = currentNoteId;
     // Don't try to provide context for an empty document
     if (document_text.length  == 0) {
       return;
     }
-    if (title.length > 0) {
-      documentTitle = title;
-    }
     const contextResults = await invoke('context_search', { 
       documentText: document_text,
       cursorUtf16Index: cursor_utf16_index,
       results: 3, // Adjust the number of results as needed
       contextSentences: 2, // Adjust the number of context sentences as needed
       workspaceId: workspaceId,
-      documentTitle,
+      documentId,
     }) as { distance: number, title: string, relevant_range: string, text: string }[]; // Use WorkspaceId type here
     console.log("Received context result:", contextResults); // Log the context results
     // Sort results by distance 
@@ -737,7 +776,7 @@ const TextEditor: React.FC = () => { // Update the component to use the CustomTy
           {savedNotes.length > 0 ? (
             <ul>
               {savedNotes.map((note, index) => (
-                <li key={index} className="note-item" onClick={() => handleNoteSelect(note.title)}> 
+                <li key={index} className="note-item" onClick={() => handleNoteSelect(note.id)}> 
                 <div className="note-content">
                     <strong>{note.title}</strong> 
                     <hr className="title-divider" /> 